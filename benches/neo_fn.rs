@@ -0,0 +1,39 @@
+//! Resolving `vim.api` functions by compiling a chunk versus the `neo_fn` cache.
+//!
+//! `cargo bench --no-default-features --features test-harness`
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use neo_api_rs::mlua::prelude::LuaFunction;
+use neo_api_rs::{NeoLuaExt, NeoMock};
+use std::hint::black_box;
+
+const PATH: &str = "vim.api.nvim_buf_set_lines";
+
+fn resolve(c: &mut Criterion) {
+    let mock = NeoMock::new().unwrap();
+
+    let mut group = c.benchmark_group("resolve vim.api function");
+
+    group.bench_function("lua.load().eval()", |b| {
+        b.iter(|| {
+            let lfn: LuaFunction = mock.load(black_box(PATH)).eval().unwrap();
+            black_box(lfn)
+        })
+    });
+
+    group.bench_function("neo_get (uncached)", |b| {
+        b.iter(|| {
+            let lfn: LuaFunction = mock.neo_get(black_box(PATH)).unwrap();
+            black_box(lfn)
+        })
+    });
+
+    group.bench_function("neo_fn (cached)", |b| {
+        b.iter(|| black_box(mock.neo_fn(black_box(PATH)).unwrap()))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, resolve);
+criterion_main!(benches);
@@ -1,3 +1,5 @@
+use syn::{parse_quote, Attribute, Field, Generics, LitStr, Type};
+
 pub fn sanitize_field_string(name: String) -> String {
     if let Some(suffix) = name.strip_prefix("r#") {
         suffix.to_string()
@@ -5,3 +7,153 @@ pub fn sanitize_field_string(name: String) -> String {
         name
     }
 }
+
+/// Options of a field, set with `#[lua(...)]`
+#[derive(Default)]
+pub struct FieldAttrs {
+    /// Key in the table, instead of the field name
+    pub rename: Option<String>,
+    /// Missing or nil values use `Default::default()`
+    pub default: bool,
+    /// Not written into the table, reading uses `Default::default()`
+    pub skip: bool,
+    /// Fields of the nested struct are stored in the same table
+    pub flatten: bool,
+}
+
+impl FieldAttrs {
+    pub fn parse(field: &Field) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+
+        for attr in lua_attrs(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let rename: LitStr = meta.value()?.parse()?;
+                    attrs.rename = Some(rename.value());
+                } else if meta.path.is_ident("default") {
+                    attrs.default = true;
+                } else if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    attrs.flatten = true;
+                } else {
+                    return Err(meta.error(
+                        "unknown lua attribute, expected rename, default, skip or flatten",
+                    ));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        if attrs.skip && (attrs.rename.is_some() || attrs.default || attrs.flatten) {
+            return Err(syn::Error::new_spanned(
+                field,
+                "#[lua(skip)] can't be combined with other lua attributes",
+            ));
+        }
+
+        if attrs.flatten && (attrs.rename.is_some() || attrs.default) {
+            return Err(syn::Error::new_spanned(
+                field,
+                "#[lua(flatten)] can't be combined with rename or default",
+            ));
+        }
+
+        Ok(attrs)
+    }
+
+    /// Tuple fields are stored by position, so these attributes don't apply
+    pub fn deny(field: &Field) -> syn::Result<()> {
+        match lua_attrs(&field.attrs).next() {
+            Some(attr) => Err(syn::Error::new_spanned(
+                attr,
+                "lua attributes are only supported on named fields",
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+fn lua_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("lua"))
+}
+
+/// Matches `Option<T>`, `std::option::Option<T>` and `core::option::Option<T>`, other types
+/// named `Option` aren't optional
+pub fn is_option(ty: &Type) -> bool {
+    let Type::Path(path_type) = ty else {
+        return false;
+    };
+
+    if path_type.qself.is_some() {
+        return false;
+    }
+
+    let path = &path_type.path;
+    let idents: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+
+    match idents.as_slice() {
+        [option] => path.leading_colon.is_none() && option == "Option",
+        [krate, module, option] => {
+            (krate == "std" || krate == "core") && module == "option" && option == "Option"
+        }
+        _ => false,
+    }
+}
+
+/// Adds `T: bound` for every type parameter
+pub fn add_trait_bounds(mut generics: Generics, bound: syn::TypeParamBound) -> Generics {
+    for param in generics.type_params_mut() {
+        param.bounds.push(bound.clone());
+    }
+
+    generics
+}
+
+pub fn into_lua_bound() -> syn::TypeParamBound {
+    parse_quote!(mlua::IntoLua)
+}
+
+pub fn from_lua_bound() -> syn::TypeParamBound {
+    parse_quote!(mlua::FromLua)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_paths() {
+        let options: [Type; 4] = [
+            parse_quote!(Option<u32>),
+            parse_quote!(std::option::Option<u32>),
+            parse_quote!(core::option::Option<u32>),
+            parse_quote!(::std::option::Option<u32>),
+        ];
+
+        for ty in options {
+            assert!(is_option(&ty), "{}", quote::quote!(#ty));
+        }
+    }
+
+    #[test]
+    fn other_types_named_option() {
+        let others: [Type; 6] = [
+            parse_quote!(config::Option),
+            parse_quote!(my::option::Option<u32>),
+            parse_quote!(std::Option<u32>),
+            parse_quote!(::Option<u32>),
+            parse_quote!(<T as Trait>::Option),
+            parse_quote!(Vec<Option<u32>>),
+        ];
+
+        for ty in others {
+            assert!(!is_option(&ty), "{}", quote::quote!(#ty));
+        }
+    }
+}
@@ -1,67 +1,139 @@
+use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, Fields, FieldsNamed, FieldsUnnamed};
 
-use crate::common::sanitize_field_string;
+use crate::common::{
+    add_trait_bounds, from_lua_bound, is_option, sanitize_field_string, FieldAttrs,
+};
 
 pub fn from_table(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
     let struct_name = &input.ident;
 
-    let mut table_fields: Vec<proc_macro2::TokenStream> = vec![];
-
-    if let syn::Data::Struct(data_struct) = &input.data {
-        match &data_struct.fields {
-            syn::Fields::Named(named_fields) => {
-                for field in named_fields.named.iter() {
-                    let field_name = &field.ident;
-                    let field_str = sanitize_field_string(field_name.as_ref().unwrap().to_string());
-
-                    if let syn::Type::Path(path_type) = &field.ty {
-                        for segment in path_type.path.segments.iter() {
-                            let field_type = &segment.ident;
-                            if field_type == "Option" {
-                                table_fields.push(quote! {
-                                    #field_name: table.get(#field_str).unwrap_or(None),
-                                });
-                            } else {
-                                table_fields.push(quote! {
-                                    #field_name: table.get(#field_str)?,
-                                });
-                            }
-                        }
-                    }
-                }
+    let syn::Data::Struct(data_struct) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "FromTable only works on structs",
+        ));
+    };
+
+    let from_lua_body = match &data_struct.fields {
+        Fields::Named(named_fields) => {
+            let table_fields = named_fields_from(named_fields)?;
+            table_body(struct_name, quote!({ #table_fields }))
+        }
+        // Newtype is converted like its inner value
+        Fields::Unnamed(unnamed_fields) if unnamed_fields.unnamed.len() == 1 => {
+            FieldAttrs::deny(&unnamed_fields.unnamed[0])?;
+
+            quote! {
+                Ok(Self(mlua::FromLua::from_lua(value, lua)?))
             }
-            _ => {
-                panic!("FromTable only works on structs with named fields");
+        }
+        Fields::Unnamed(unnamed_fields) => {
+            let table_fields = tuple_fields_from(unnamed_fields)?;
+            table_body(struct_name, quote!(( #table_fields )))
+        }
+        Fields::Unit => {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "FromTable doesn't support unit structs",
+            ))
+        }
+    };
+
+    let generics = add_trait_bounds(input.generics.clone(), from_lua_bound());
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics mlua::FromLua for #struct_name #ty_generics #where_clause {
+            fn from_lua(value: mlua::Value, lua: &mlua::Lua) -> mlua::Result<Self> {
+                #from_lua_body
             }
         }
-    } else {
-        panic!("FromTable only works on structs");
+    })
+}
+
+fn table_body(struct_name: &syn::Ident, construct: TokenStream) -> TokenStream {
+    quote! {
+        if let mlua::Value::Table(table) = value {
+            Ok(Self #construct)
+        } else {
+            Err(mlua::Error::FromLuaConversionError {
+                from: value.type_name(),
+                to: stringify!(#struct_name),
+                message: None,
+            })
+        }
     }
+}
 
-    let table_fields = proc_macro2::TokenStream::from_iter(table_fields);
-    let generics = &input.generics;
-
-    let expand = quote! {
-        impl #generics mlua::FromLua for #struct_name #generics {
-            fn from_lua(value: mlua::Value, lua: &Lua) -> mlua::Result<Self> {
-                if let mlua::Value::Table(table) = value {
-                    Ok(Self {
-                        #table_fields
-                    })
-                } else {
-                    Err(mlua::Error::FromLuaConversionError {
-                        from: value.type_name(),
-                        to: stringify!(#struct_name),
-                        message: None,
-                    })
-                }
+fn named_fields_from(named_fields: &FieldsNamed) -> syn::Result<TokenStream> {
+    let mut table_fields: Vec<TokenStream> = vec![];
 
-            }
+    for field in named_fields.named.iter() {
+        let attrs = FieldAttrs::parse(field)?;
+        let field_name = field.ident.as_ref().expect("Named field");
+        let field_type = &field.ty;
+
+        if attrs.skip {
+            table_fields.push(quote! {
+                #field_name: Default::default(),
+            });
+
+            continue;
         }
-    };
 
-    expand.into()
+        if attrs.flatten {
+            table_fields.push(quote! {
+                #field_name: mlua::FromLua::from_lua(mlua::Value::Table(table.clone()), lua)?,
+            });
+
+            continue;
+        }
+
+        let field_str = attrs
+            .rename
+            .unwrap_or_else(|| sanitize_field_string(field_name.to_string()));
+
+        if attrs.default {
+            table_fields.push(quote! {
+                #field_name: table.get::<Option<#field_type>>(#field_str)?.unwrap_or_default(),
+            });
+        } else if is_option(field_type) {
+            table_fields.push(quote! {
+                #field_name: table.get(#field_str).unwrap_or(None),
+            });
+        } else {
+            table_fields.push(quote! {
+                #field_name: table.get(#field_str)?,
+            });
+        }
+    }
+
+    Ok(TokenStream::from_iter(table_fields))
+}
+
+/// Tuple structs are read from a sequence
+fn tuple_fields_from(unnamed_fields: &FieldsUnnamed) -> syn::Result<TokenStream> {
+    let mut table_fields: Vec<TokenStream> = vec![];
+
+    for (i, field) in unnamed_fields.unnamed.iter().enumerate() {
+        FieldAttrs::deny(field)?;
+
+        let lua_index = i + 1;
+
+        table_fields.push(quote! {
+            table.raw_get(#lua_index)?,
+        });
+    }
+
+    Ok(TokenStream::from_iter(table_fields))
 }
@@ -1,92 +1,429 @@
 use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, Attribute, DeriveInput, Fields, Ident, LitStr, Variant};
+
+use crate::common::{add_trait_bounds, from_lua_bound, into_lua_bound};
+
+struct EnumVariant<'a> {
+    ident: &'a Ident,
+    /// Lua string of a unit variant, key of a tagged newtype variant
+    name: String,
+    newtype: bool,
+}
 
 pub fn into_enum(input: proc_macro::TokenStream, casing: Option<Case>) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
+    expand(input, casing)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput, casing: Option<Case>) -> syn::Result<TokenStream> {
     let enum_name = &input.ident;
+    let untagged = parse_untagged(&input.attrs)?;
 
-    fn invalid_enum() {
-        // TODO either all variants 1 field or no fields
-        // 1 field will use value of field as lua value
-        // no field will use to_string() as lua value
-        panic!("Enum may not contain any fields, implement it manually");
-    }
+    let syn::Data::Enum(data_enum) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            enum_name,
+            "IntoEnum only works on enums",
+        ));
+    };
 
-    let mut variants = Vec::new();
+    let mut variants: Vec<EnumVariant> = Vec::new();
 
-    if let syn::Data::Enum(meta_enum) = &input.data {
-        for variant in meta_enum.variants.iter() {
-            if !variant.fields.is_empty() {
-                invalid_enum();
+    for variant in data_enum.variants.iter() {
+        let newtype = match &variant.fields {
+            Fields::Unit => false,
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => true,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "IntoEnum only supports unit and newtype variants, implement it manually",
+                ))
             }
+        };
 
-            let variant = &variant.ident;
+        let name = match parse_variant_name(variant)? {
+            Some(name) => name,
+            None => match casing {
+                Some(casing) => variant.ident.to_string().to_case(casing),
+                None => variant.ident.to_string(),
+            },
+        };
 
-            let variant_value = if let Some(casing) = casing {
-                variant.to_string().to_case(casing)
-            } else {
-                variant.to_string()
-            };
-
-            variants.push(quote! {
-                Self::#variant => f.write_str(#variant_value),
-            });
+        if variants.iter().any(|other| other.name == name) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!("Lua name \"{name}\" is used by multiple variants"),
+            ));
         }
+
+        variants.push(EnumVariant {
+            ident: &variant.ident,
+            name,
+            newtype,
+        });
     }
 
-    let variants_stream = proc_macro2::TokenStream::from_iter(variants);
+    let display = display_arms(&variants);
+    let into_lua = into_lua_arms(&variants, untagged);
+    let from_lua = from_lua_body(enum_name, &variants, untagged);
+    let from_str = from_str_arms(&variants);
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let into_generics = add_trait_bounds(input.generics.clone(), into_lua_bound());
+    let (into_impl_generics, _, into_where_clause) = into_generics.split_for_impl();
+
+    let from_generics = add_trait_bounds(input.generics.clone(), from_lua_bound());
+    let (from_impl_generics, _, from_where_clause) = from_generics.split_for_impl();
 
-    let expand = quote! {
-        impl std::fmt::Display for #enum_name {
+    Ok(quote! {
+        impl #impl_generics std::fmt::Display for #enum_name #ty_generics #where_clause {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 match self {
-                    #variants_stream
+                    #display
+                }
+            }
+        }
+
+        impl #into_impl_generics mlua::IntoLua for #enum_name #ty_generics #into_where_clause {
+            fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+                match self {
+                    #into_lua
+                }
+            }
+        }
+
+        impl #from_impl_generics mlua::FromLua for #enum_name #ty_generics #from_where_clause {
+            #[allow(unused_variables)]
+            fn from_lua(value: mlua::Value, lua: &mlua::Lua) -> mlua::Result<Self> {
+                #from_lua
+            }
+        }
+
+        impl #impl_generics std::str::FromStr for #enum_name #ty_generics #where_clause {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    #from_str
+                    _ => Err(format!("Unknown {}: {s}", stringify!(#enum_name))),
+                }
+            }
+        }
+    })
+}
+
+/// `#[lua(untagged)]` on the enum
+fn parse_untagged(attrs: &[Attribute]) -> syn::Result<bool> {
+    let mut untagged = false;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("lua")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("untagged") {
+                untagged = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown lua attribute, expected untagged"))
+            }
+        })?;
+    }
+
+    Ok(untagged)
+}
+
+/// `#[lua(name = "...")]` on a variant
+fn parse_variant_name(variant: &Variant) -> syn::Result<Option<String>> {
+    let mut name = None;
+
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("lua"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                let value: LitStr = meta.value()?.parse()?;
+                name = Some(value.value());
+                Ok(())
+            } else {
+                Err(meta.error("unknown lua attribute, expected name"))
+            }
+        })?;
+    }
+
+    Ok(name)
+}
+
+fn display_arms(variants: &[EnumVariant]) -> TokenStream {
+    let arms = variants.iter().map(|variant| {
+        let ident = variant.ident;
+        let name = &variant.name;
+
+        if variant.newtype {
+            quote! { Self::#ident(_) => f.write_str(#name), }
+        } else {
+            quote! { Self::#ident => f.write_str(#name), }
+        }
+    });
+
+    TokenStream::from_iter(arms)
+}
+
+fn into_lua_arms(variants: &[EnumVariant], untagged: bool) -> TokenStream {
+    let arms = variants.iter().map(|variant| {
+        let ident = variant.ident;
+        let name = &variant.name;
+
+        if !variant.newtype {
+            quote! {
+                Self::#ident => Ok(mlua::Value::String(lua.create_string(#name)?)),
+            }
+        } else if untagged {
+            quote! {
+                Self::#ident(inner) => mlua::IntoLua::into_lua(inner, lua),
+            }
+        } else {
+            quote! {
+                Self::#ident(inner) => {
+                    let out = lua.create_table()?;
+                    out.set(#name, inner)?;
+
+                    Ok(mlua::Value::Table(out))
                 }
             }
         }
+    });
 
-        impl mlua::IntoLua for #enum_name {
-            fn into_lua(self, lua: &Lua) -> mlua::Result<mlua::Value> {
-                let str = lua.create_string(self.to_string())?;
+    TokenStream::from_iter(arms)
+}
 
-                Ok(mlua::Value::String(str))
+fn from_lua_body(enum_name: &Ident, variants: &[EnumVariant], untagged: bool) -> TokenStream {
+    let unit_arms = from_str_arms(variants);
+
+    let unit = if variants.iter().any(|variant| !variant.newtype) {
+        quote! {
+            if let mlua::Value::String(str) = &value {
+                let result: Result<Self, ()> = match &*str.to_string_lossy() {
+                    #unit_arms
+                    _ => Err(()),
+                };
+
+                if let Ok(variant) = result {
+                    return Ok(variant);
+                }
             }
         }
+    } else {
+        quote! {}
     };
 
-    expand.into()
+    let newtypes = variants
+        .iter()
+        .filter(|variant| variant.newtype)
+        .map(|variant| {
+            let ident = variant.ident;
+            let name = &variant.name;
+
+            if untagged {
+                // First variant which accepts the value
+                quote! {
+                    if let Ok(inner) = mlua::FromLua::from_lua(value.clone(), lua) {
+                        return Ok(Self::#ident(inner));
+                    }
+                }
+            } else {
+                quote! {
+                    if let mlua::Value::Table(table) = &value {
+                        if let Some(inner) = table.get::<Option<mlua::Value>>(#name)? {
+                            return Ok(Self::#ident(mlua::FromLua::from_lua(inner, lua)?));
+                        }
+                    }
+                }
+            }
+        });
+
+    let newtypes = TokenStream::from_iter(newtypes);
+
+    quote! {
+        #unit
+
+        #newtypes
+
+        Err(mlua::Error::FromLuaConversionError {
+            from: value.type_name(),
+            to: stringify!(#enum_name),
+            message: Some("No matching variant".to_string()),
+        })
+    }
 }
 
-//#[cfg(test)]
-//mod tests {
-
-    //use super::*;
-
-    //#[derive(IntoTable)]
-    //pub struct SomeStruct {
-        //pub name: String,
-        //pub age: usize,
-        //pub weight: Option<usize>,
-        //pub phone: Phone,
-        //pub gender: Gender,
-    //}
-
-    //#[derive(IntoTable)]
-    //pub struct Phone {
-        //pub mobile: String,
-        //pub home: String,
-    //}
-
-    //#[derive(IntoEnumSC)]
-    //pub enum Gender {
-        //Male,
-        //Female,
-    //}
-
-    //#[test]
-    //fn test_macro() {
-        //// TODO write test
-    //}
-//}
+/// Only unit variants can be parsed from a string
+fn from_str_arms(variants: &[EnumVariant]) -> TokenStream {
+    let arms = variants
+        .iter()
+        .filter(|variant| !variant.newtype)
+        .map(|variant| {
+            let ident = variant.ident;
+            let name = &variant.name;
+
+            quote! { #name => Ok(Self::#ident), }
+        });
+
+    TokenStream::from_iter(arms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn expand_enum(input: DeriveInput, casing: Option<Case>) -> String {
+        expand(input, casing).unwrap().to_string()
+    }
+
+    fn error(input: DeriveInput) -> String {
+        expand(input, None).unwrap_err().to_string()
+    }
+
+    fn contains(expanded: &str, part: TokenStream) -> bool {
+        expanded.contains(&part.to_string())
+    }
+
+    #[test]
+    fn unit_variants_use_the_casing() {
+        let expanded = expand_enum(
+            parse_quote! {
+                enum SplitDir {
+                    AboveLeft,
+                    BelowRight,
+                }
+            },
+            Some(Case::Snake),
+        );
+
+        assert!(contains(
+            &expanded,
+            quote!(Self::AboveLeft => f.write_str("above_left"),)
+        ));
+        assert!(contains(
+            &expanded,
+            quote!("below_right" => Ok(Self::BelowRight),)
+        ));
+    }
+
+    #[test]
+    fn lua_name_overrides_the_casing() {
+        let expanded = expand_enum(
+            parse_quote! {
+                enum Pattern {
+                    #[lua(name = "*")]
+                    All,
+                    Buffer,
+                }
+            },
+            Some(Case::Snake),
+        );
+
+        assert!(contains(&expanded, quote!("*" => Ok(Self::All),)));
+        assert!(contains(&expanded, quote!("buffer" => Ok(Self::Buffer),)));
+    }
+
+    #[test]
+    fn newtype_variants_are_tagged_tables() {
+        let expanded = expand_enum(
+            parse_quote! {
+                enum Filter {
+                    None,
+                    Pattern(String),
+                }
+            },
+            Some(Case::Snake),
+        );
+
+        assert!(contains(&expanded, quote!(out.set("pattern", inner)?;)));
+        assert!(contains(
+            &expanded,
+            quote!(table.get::<Option<mlua::Value>>("pattern")?)
+        ));
+        // Only unit variants parse from a string
+        assert!(!contains(&expanded, quote!("pattern" => Ok)));
+    }
+
+    #[test]
+    fn untagged_newtypes_convert_the_inner_value() {
+        let expanded = expand_enum(
+            parse_quote! {
+                #[lua(untagged)]
+                enum Group {
+                    Integer(u32),
+                    String(String),
+                }
+            },
+            None,
+        );
+
+        assert!(contains(
+            &expanded,
+            quote!(Self::Integer(inner) => mlua::IntoLua::into_lua(inner, lua),)
+        ));
+        assert!(!contains(&expanded, quote!(lua.create_table())));
+        // Without unit variants strings aren't matched by name
+        assert!(!contains(&expanded, quote!(to_string_lossy)));
+    }
+
+    #[test]
+    fn generics_get_lua_bounds() {
+        let expanded = expand_enum(
+            parse_quote! {
+                enum Either<T> {
+                    Value(T),
+                    Empty,
+                }
+            },
+            None,
+        );
+
+        assert!(contains(
+            &expanded,
+            quote!(impl<T: mlua::IntoLua> mlua::IntoLua for Either<T>)
+        ));
+        assert!(contains(
+            &expanded,
+            quote!(impl<T: mlua::FromLua> mlua::FromLua for Either<T>)
+        ));
+    }
+
+    #[test]
+    fn invalid_enums() {
+        assert_eq!(
+            error(parse_quote! { struct Unit; }),
+            "IntoEnum only works on enums"
+        );
+        assert_eq!(
+            error(parse_quote! { enum Pair { Pair(u32, u32) } }),
+            "IntoEnum only supports unit and newtype variants, implement it manually"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                enum Same {
+                    #[lua(name = "one")]
+                    First,
+                    #[lua(name = "one")]
+                    Second,
+                }
+            }),
+            "Lua name \"one\" is used by multiple variants"
+        );
+        assert_eq!(
+            error(parse_quote! {
+                #[lua(tagged)]
+                enum Tagged { First }
+            }),
+            "unknown lua attribute, expected untagged"
+        );
+    }
+}
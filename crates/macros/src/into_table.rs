@@ -1,67 +1,145 @@
+use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, Fields, FieldsNamed, FieldsUnnamed, Index};
 
-use crate::common::sanitize_field_string;
+use crate::common::{
+    add_trait_bounds, into_lua_bound, is_option, sanitize_field_string, FieldAttrs,
+};
 
 pub fn into_table(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
     let struct_name = &input.ident;
 
-    let mut table_fields: Vec<proc_macro2::TokenStream> = vec![];
-
-    if let syn::Data::Struct(data_struct) = &input.data {
-        match &data_struct.fields {
-            syn::Fields::Named(named_fields) => {
-                for field in named_fields.named.iter() {
-                    let field_name = &field.ident;
-                    let field_str = sanitize_field_string(field_name.as_ref().unwrap().to_string());
-
-                    if let syn::Type::Path(path_type) = &field.ty {
-                        for segment in path_type.path.segments.iter() {
-                            let field_type = &segment.ident;
-
-                            if field_type == "Option" {
-                                table_fields.push(quote! {
-                                    if let Some(value) = self.#field_name {
-                                        out.set(#field_str, value)?;
-                                    }
-                                });
-                            } else {
-                                table_fields.push(quote! {
-                                    out.set(#field_str, self.#field_name)?;
-                                });
-                            }
+    let syn::Data::Struct(data_struct) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "IntoTable only works on structs",
+        ));
+    };
+
+    let into_lua_body = match &data_struct.fields {
+        Fields::Named(named_fields) => named_body(named_fields)?,
+        // Newtype is converted like its inner value
+        Fields::Unnamed(unnamed_fields) if unnamed_fields.unnamed.len() == 1 => {
+            FieldAttrs::deny(&unnamed_fields.unnamed[0])?;
+
+            quote! {
+                mlua::IntoLua::into_lua(self.0, lua)
+            }
+        }
+        Fields::Unnamed(unnamed_fields) => tuple_body(unnamed_fields)?,
+        Fields::Unit => {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "IntoTable doesn't support unit structs",
+            ))
+        }
+    };
+
+    let generics = add_trait_bounds(input.generics.clone(), into_lua_bound());
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics mlua::IntoLua for #struct_name #ty_generics #where_clause {
+            fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+                #into_lua_body
+            }
+        }
+    })
+}
+
+fn named_body(named_fields: &FieldsNamed) -> syn::Result<TokenStream> {
+    let mut table_fields: Vec<TokenStream> = vec![];
+
+    for field in named_fields.named.iter() {
+        let attrs = FieldAttrs::parse(field)?;
+
+        if attrs.skip {
+            continue;
+        }
+
+        let field_name = field.ident.as_ref().expect("Named field");
+
+        if attrs.flatten {
+            table_fields.push(quote! {
+                match mlua::IntoLua::into_lua(self.#field_name, lua)? {
+                    mlua::Value::Table(nested) => {
+                        for pair in nested.pairs::<mlua::Value, mlua::Value>() {
+                            let (key, value) = pair?;
+                            out.set(key, value)?;
                         }
                     }
+                    mlua::Value::Nil => {}
+                    value => {
+                        return Err(mlua::Error::runtime(format!(
+                            "Can't flatten field {} of type {}",
+                            stringify!(#field_name),
+                            value.type_name(),
+                        )))
+                    }
                 }
-            }
-            _ => {
-                panic!("IntoTable only works on structs with named fields");
-            }
+            });
+
+            continue;
+        }
+
+        let field_str = attrs
+            .rename
+            .unwrap_or_else(|| sanitize_field_string(field_name.to_string()));
+
+        if is_option(&field.ty) {
+            table_fields.push(quote! {
+                if let Some(value) = self.#field_name {
+                    out.set(#field_str, value)?;
+                }
+            });
+        } else {
+            table_fields.push(quote! {
+                out.set(#field_str, self.#field_name)?;
+            });
         }
-    } else {
-        panic!("IntoTable only works on structs");
     }
 
-    let table_fields = proc_macro2::TokenStream::from_iter(table_fields);
-    let generics = &input.generics;
+    let table_fields = TokenStream::from_iter(table_fields);
 
-    let into_lua_body = quote! {
+    Ok(quote! {
         let out = lua.create_table()?;
 
         #table_fields
 
         Ok(mlua::Value::Table(out))
-    };
+    })
+}
 
-    let expand = quote! {
-        impl #generics mlua::IntoLua for #struct_name #generics {
-            fn into_lua(self, lua: &Lua) -> mlua::Result<mlua::Value> {
-                #into_lua_body
-            }
-        }
-    };
+/// Tuple structs become a sequence
+fn tuple_body(unnamed_fields: &FieldsUnnamed) -> syn::Result<TokenStream> {
+    let mut table_fields: Vec<TokenStream> = vec![];
+
+    for (i, field) in unnamed_fields.unnamed.iter().enumerate() {
+        FieldAttrs::deny(field)?;
+
+        let index = Index::from(i);
+        let lua_index = i + 1;
+
+        table_fields.push(quote! {
+            out.raw_set(#lua_index, self.#index)?;
+        });
+    }
+
+    let table_fields = TokenStream::from_iter(table_fields);
 
-    expand.into()
+    Ok(quote! {
+        let out = lua.create_table()?;
+
+        #table_fields
+
+        Ok(mlua::Value::Table(out))
+    })
 }
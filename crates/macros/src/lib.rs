@@ -4,50 +4,97 @@ mod common;
 mod from_table;
 mod into_enum;
 mod into_table;
+mod lua_serde;
+mod neo_plugin;
 
-/// A simple into table function (yet), for complex structures create your own into lua function
-#[proc_macro_derive(IntoTable)]
+/// Into lua table, fields can be configured with `#[lua(...)]`:
+/// - `rename = "key"` key in the table instead of the field name
+/// - `default` (FromTable) missing or nil values use `Default::default()`
+/// - `skip` not written into the table, reading uses `Default::default()`
+/// - `flatten` fields of the nested struct are stored in the same table
+///
+/// Newtype structs are converted like the inner value, tuple structs into a sequence
+#[proc_macro_derive(IntoTable, attributes(lua))]
 pub fn into_table(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     into_table::into_table(input)
 }
 
-/// A simple into table function (yet), for complex structures create your own into lua function
-#[proc_macro_derive(FromTable)]
+/// From lua table, accepts the same `#[lua(...)]` field attributes as `IntoTable`
+#[proc_macro_derive(FromTable, attributes(lua))]
 pub fn from_table(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     from_table::from_table(input)
 }
 
-/// Into enum (copy field name to string)
-/// Also implements Display
-#[proc_macro_derive(IntoEnum)]
+/// Into enum (copy variant name to string), also implements Display, FromLua and FromStr.
+///
+/// - Unit variants are a string, `#[lua(name = "...")]` overrides the name
+/// - Newtype variants are a table with the name as key: `{ name = inner }`
+/// - With `#[lua(untagged)]` on the enum, newtype variants are the inner value. FromLua tries
+///   the variants in order, so put the most specific one first
+/// - FromStr only parses unit variants
+#[proc_macro_derive(IntoEnum, attributes(lua))]
 pub fn into_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     into_enum::into_enum(input, None)
 }
 
-/// Into enum camel case
-/// Also implements Display
-#[proc_macro_derive(IntoEnumCC)]
+/// Into enum camel case, see `IntoEnum`
+#[proc_macro_derive(IntoEnumCC, attributes(lua))]
 pub fn into_enum_cc(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     into_enum::into_enum(input, Some(Case::Camel))
 }
 
-/// Into enum snake case
-/// Also implements Display
-#[proc_macro_derive(IntoEnumSC)]
+/// Into enum snake case, see `IntoEnum`
+#[proc_macro_derive(IntoEnumSC, attributes(lua))]
 pub fn into_enum_sc(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     into_enum::into_enum(input, Some(Case::Snake))
 }
 
-/// Into enum upper case
-/// Also implements Display
-#[proc_macro_derive(IntoEnumUC)]
+/// Into enum upper case, see `IntoEnum`
+#[proc_macro_derive(IntoEnumUC, attributes(lua))]
 pub fn into_enum_uc(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     into_enum::into_enum(input, Some(Case::Upper))
 }
 
-/// Into enum pascal case
-/// Also implements Display
-#[proc_macro_derive(IntoEnumPC)]
+/// Into enum pascal case, see `IntoEnum`
+#[proc_macro_derive(IntoEnumPC, attributes(lua))]
 pub fn into_enum_pc(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     into_enum::into_enum(input, Some(Case::Pascal))
 }
+
+/// IntoLua through the serde `Serialize` impl of the type, `None` fields are left out.
+///
+/// Needs `serde` as dependency and `mlua` with the `serialize` feature.
+#[proc_macro_derive(IntoLuaSerde)]
+pub fn into_lua_serde(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    lua_serde::into_lua_serde(input)
+}
+
+/// FromLua through the serde `Deserialize` impl of the type, unsupported Lua values such as
+/// functions are skipped. See `IntoLuaSerde`
+#[proc_macro_derive(FromLuaSerde)]
+pub fn from_lua_serde(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    lua_serde::from_lua_serde(input)
+}
+
+/// Exports the `pub` functions of an impl block as the Lua module of the plugin, the module is
+/// named after the type in snake case or `#[neo_plugin(name = "...")]`.
+///
+/// - Arguments are converted with FromLua, a first `&Lua` argument receives the Lua state
+/// - Functions return a `LuaResult` or `NeoResult`
+/// - Async functions are spawned on `RTM`, errors are logged and notified
+/// - `#[neo(name = "...")]` key in the module table
+/// - `#[neo(command = "...", desc = "...", nargs = "?")]` also creates a user command, the
+///   arguments of the command (`<f-args>`) are converted into the parameters
+/// - `#[neo(setup)]` exported as `setup(opts)`, opts are read into the argument with
+///   `NeoConfig::from_opts` when it implements `NeoConfig`, otherwise with FromLua (e.g. a
+///   `FromTable` config) and without opts from an empty table
+/// - `#[neo(skip)]` not exported
+///
+/// Needs `mlua` with the `module` and `macros` features as dependency of the plugin.
+#[proc_macro_attribute]
+pub fn neo_plugin(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    neo_plugin::neo_plugin(attr, item)
+}
@@ -0,0 +1,56 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, DeriveInput, Generics, Ident};
+
+pub fn into_lua_serde(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let generics = add_self_bound(&input.generics, name, quote!(::serde::Serialize));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // None and () become nil instead of vim.NIL, so optional keys are left out
+    quote! {
+        impl #impl_generics mlua::IntoLua for #name #ty_generics #where_clause {
+            fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+                let options = mlua::SerializeOptions::new()
+                    .serialize_none_to_null(false)
+                    .serialize_unit_to_null(false)
+                    .set_array_metatable(false);
+
+                mlua::LuaSerdeExt::to_value_with(lua, &self, options)
+            }
+        }
+    }
+    .into()
+}
+
+pub fn from_lua_serde(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let generics = add_self_bound(&input.generics, name, quote!(::serde::de::DeserializeOwned));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // Neovim tables can contain functions (e.g. keymap callbacks), those are skipped
+    quote! {
+        impl #impl_generics mlua::FromLua for #name #ty_generics #where_clause {
+            fn from_lua(value: mlua::Value, lua: &mlua::Lua) -> mlua::Result<Self> {
+                let options = mlua::DeserializeOptions::new().deny_unsupported_types(false);
+
+                mlua::LuaSerdeExt::from_value_with(lua, value, options)
+            }
+        }
+    }
+    .into()
+}
+
+/// The serde derives already bound the generics, so only the type itself is bound
+fn add_self_bound(generics: &Generics, name: &Ident, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let predicate = parse_quote!(#name #ty_generics: #bound);
+
+    generics.make_where_clause().predicates.push(predicate);
+    generics
+}
@@ -0,0 +1,535 @@
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, FnArg, Ident, ImplItem, ImplItemFn, ItemImpl, LitStr, Type, Visibility,
+};
+
+/// Options of an exported function, set with `#[neo(...)]`
+#[derive(Default)]
+struct FnAttrs {
+    skip: bool,
+    setup: bool,
+    /// Key in the module table, instead of the function name
+    name: Option<String>,
+    command: Option<String>,
+    desc: Option<String>,
+    nargs: Option<TokenStream>,
+}
+
+impl FnAttrs {
+    /// Parses and removes the `#[neo(...)]` attributes, they are not real attributes.
+    fn take(method: &mut ImplItemFn) -> syn::Result<Self> {
+        let mut attrs = Self::default();
+        let mut result = Ok(());
+
+        method.attrs.retain(|attr| {
+            if !attr.path().is_ident("neo") {
+                return true;
+            }
+
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("setup") {
+                    attrs.setup = true;
+                } else if meta.path.is_ident("name") {
+                    attrs.name = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("command") {
+                    attrs.command = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("desc") {
+                    attrs.desc = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("nargs") {
+                    let nargs: LitStr = meta.value()?.parse()?;
+
+                    let variant = match nargs.value().as_str() {
+                        "0" => quote!(Zero),
+                        "1" => quote!(One),
+                        "*" => quote!(Any),
+                        "?" => quote!(ZeroOrOne),
+                        "+" => quote!(OneOrMore),
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                nargs,
+                                "nargs should be one of \"0\", \"1\", \"*\", \"?\" or \"+\"",
+                            ))
+                        }
+                    };
+
+                    attrs.nargs = Some(quote!(neo_api_rs::CmdNargs::#variant));
+                } else {
+                    return Err(meta.error(
+                        "unknown neo attribute, expected skip, setup, name, command, desc or nargs",
+                    ));
+                }
+
+                Ok(())
+            });
+
+            if let Err(err) = parsed {
+                result = Err(err);
+            }
+
+            false
+        });
+
+        result?;
+
+        if attrs.command.is_none() && (attrs.desc.is_some() || attrs.nargs.is_some()) {
+            return Err(syn::Error::new_spanned(
+                &method.sig.ident,
+                "desc and nargs only apply to functions with #[neo(command = \"...\")]",
+            ));
+        }
+
+        if attrs.skip && (attrs.setup || attrs.name.is_some() || attrs.command.is_some()) {
+            return Err(syn::Error::new_spanned(
+                &method.sig.ident,
+                "#[neo(skip)] can't be combined with other neo attributes",
+            ));
+        }
+
+        Ok(attrs)
+    }
+}
+
+pub fn neo_plugin(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let mut module_name: Option<LitStr> = None;
+
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            module_name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unknown neo_plugin attribute, expected name"))
+        }
+    });
+
+    parse_macro_input!(attr with parser);
+
+    let item_impl = parse_macro_input!(item as ItemImpl);
+
+    expand(module_name, item_impl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(module_name: Option<LitStr>, mut item_impl: ItemImpl) -> syn::Result<TokenStream> {
+    if let Some((_, path, _)) = &item_impl.trait_ {
+        return Err(syn::Error::new_spanned(
+            path,
+            "neo_plugin only works on inherent impl blocks",
+        ));
+    }
+
+    if !item_impl.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item_impl.generics,
+            "neo_plugin doesn't support generic impl blocks",
+        ));
+    }
+
+    let self_ty = item_impl.self_ty.clone();
+
+    let module_name = match module_name {
+        Some(name) => name.parse::<Ident>()?,
+        None => {
+            let Type::Path(type_path) = self_ty.as_ref() else {
+                return Err(syn::Error::new_spanned(
+                    &self_ty,
+                    "Add a module name: #[neo_plugin(name = \"...\")]",
+                ));
+            };
+
+            let ident = &type_path
+                .path
+                .segments
+                .last()
+                .expect("Type has a name")
+                .ident;
+            format_ident!("{}", ident.to_string().to_case(Case::Snake))
+        }
+    };
+
+    let mut entries: Vec<TokenStream> = vec![];
+    let mut has_setup = false;
+
+    for item in item_impl.items.iter_mut() {
+        let ImplItem::Fn(method) = item else {
+            continue;
+        };
+
+        let attrs = FnAttrs::take(method)?;
+
+        if attrs.skip {
+            continue;
+        }
+
+        if !matches!(method.vis, Visibility::Public(_)) {
+            if attrs.setup || attrs.name.is_some() || attrs.command.is_some() {
+                return Err(syn::Error::new_spanned(
+                    &method.sig.ident,
+                    "Only pub functions are exported",
+                ));
+            }
+
+            continue;
+        }
+
+        if attrs.setup {
+            if has_setup {
+                return Err(syn::Error::new_spanned(
+                    &method.sig.ident,
+                    "Only one function can be #[neo(setup)]",
+                ));
+            }
+
+            has_setup = true;
+        }
+
+        entries.push(export_fn(&self_ty, &module_name, method, attrs)?);
+    }
+
+    let entries = TokenStream::from_iter(entries);
+    // Log file and tag of the plugin
+    let plugin = module_name.to_string();
+
+    // Callable without the `module` feature, e.g. in tests with `NeoMock`
+    Ok(quote! {
+        #item_impl
+
+        #[allow(dead_code)]
+        fn #module_name(
+            lua: &neo_api_rs::mlua::Lua,
+        ) -> neo_api_rs::mlua::Result<neo_api_rs::mlua::Table> {
+            use neo_api_rs::mlua;
+
+            neo_api_rs::NeoScheduler::init(lua)?;
+            neo_api_rs::NeoLogger::init(
+                lua,
+                neo_api_rs::LoggerConfig {
+                    plugin: #plugin.to_string(),
+                    ..Default::default()
+                },
+            )?;
+
+            let module = lua.create_table()?;
+
+            #entries
+
+            Ok(module)
+        }
+
+        neo_api_rs::plugin_module!(#module_name);
+    })
+}
+
+fn export_fn(
+    self_ty: &Type,
+    module_name: &Ident,
+    method: &ImplItemFn,
+    attrs: FnAttrs,
+) -> syn::Result<TokenStream> {
+    let sig = &method.sig;
+    let ident = &sig.ident;
+    let is_async = sig.asyncness.is_some();
+
+    let mut uses_lua = false;
+    let mut arg_types: Vec<&Type> = vec![];
+
+    for (i, input) in sig.inputs.iter().enumerate() {
+        let FnArg::Typed(pat_type) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "Exported functions can't take self, use an associated function",
+            ));
+        };
+
+        if i == 0 && is_lua_ref(&pat_type.ty) {
+            if is_async {
+                return Err(syn::Error::new_spanned(
+                    input,
+                    "Async functions run on the tokio runtime and can't borrow Lua, \
+                     use NeoScheduler to get back on the main thread",
+                ));
+            }
+
+            uses_lua = true;
+            continue;
+        }
+
+        arg_types.push(&pat_type.ty);
+    }
+
+    let args: Vec<_> = (0..arg_types.len())
+        .map(|i| format_ident!("arg{}", i))
+        .collect();
+
+    let lua_arg = if uses_lua { quote!(lua,) } else { quote!() };
+    let lua_param = if uses_lua || attrs.setup {
+        quote!(lua)
+    } else {
+        quote!(_)
+    };
+
+    let function = if attrs.setup {
+        if is_async || args.len() != 1 {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "#[neo(setup)] should be a sync function which takes the config struct",
+            ));
+        }
+
+        let config_ty = arg_types[0];
+        // Prefix of the setup errors
+        let plugin = module_name.to_string();
+
+        // NeoConfig defaults and unknown key errors, or FromLua
+        quote! {
+            lua.create_function(|lua, opts: mlua::Value| {
+                use neo_api_rs::{SetupFromLua as _, SetupNeoConfig as _};
+
+                let config: #config_ty = (&neo_api_rs::SetupOpts::<#config_ty>::new())
+                    .read(lua, #plugin, opts)?;
+
+                Ok::<_, mlua::Error>(#self_ty::#ident(#lua_arg config)?)
+            })?
+        }
+    } else if is_async {
+        quote! {
+            lua.create_function(|_, (#(#args,)*): (#(#arg_types,)*)| {
+                neo_api_rs::RTM.spawn(async move {
+                    if let Err(err) = #self_ty::#ident(#(#args),*).await {
+                        let err = err.to_string();
+                        neo_api_rs::NeoDebug::error(&err);
+
+                        neo_api_rs::NeoScheduler::schedule(move |lua| {
+                            neo_api_rs::NeoApi::notify_level(
+                                lua,
+                                &err,
+                                neo_api_rs::LogLevel::Error,
+                            )
+                        });
+                    }
+                });
+
+                Ok(())
+            })?
+        }
+    } else {
+        quote! {
+            lua.create_function(|#lua_param, (#(#args,)*): (#(#arg_types,)*)| {
+                Ok::<_, mlua::Error>(#self_ty::#ident(#lua_arg #(#args),*)?)
+            })?
+        }
+    };
+
+    let lua_name = match (&attrs.name, attrs.setup) {
+        (Some(name), _) => name.clone(),
+        (None, true) => "setup".to_string(),
+        (None, false) => ident.to_string(),
+    };
+
+    let command = match &attrs.command {
+        Some(command) => {
+            let desc = match &attrs.desc {
+                Some(desc) => quote!(Some(#desc.to_string())),
+                None => quote!(None),
+            };
+
+            let nargs = match &attrs.nargs {
+                Some(nargs) => quote!(Some(#nargs)),
+                None => quote!(None),
+            };
+
+            // The callback receives the command args, its <f-args> are the parameters
+            quote! {
+                let exported = function.clone();
+                let callback = lua.create_function(move |_, args: neo_api_rs::UserCommandArgs| {
+                    exported.call::<()>(mlua::Variadic::from_iter(args.fargs))
+                })?;
+
+                neo_api_rs::NeoApi::create_user_command(
+                    lua,
+                    #command,
+                    callback,
+                    neo_api_rs::UserCommandOpts {
+                        nargs: #nargs,
+                        desc: #desc,
+                        ..Default::default()
+                    },
+                )?;
+            }
+        }
+        None => quote!(),
+    };
+
+    Ok(quote! {
+        {
+            let function = #function;
+
+            #command
+
+            module.set(#lua_name, function)?;
+        }
+    })
+}
+
+/// Matches `&Lua`, `&mlua::Lua` etc.
+fn is_lua_ref(ty: &Type) -> bool {
+    let Type::Reference(reference) = ty else {
+        return false;
+    };
+
+    match reference.elem.as_ref() {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Lua"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn expand_impl(item_impl: ItemImpl) -> String {
+        expand(None, item_impl).unwrap().to_string()
+    }
+
+    fn contains(expanded: &str, part: TokenStream) -> bool {
+        expanded.contains(&part.to_string())
+    }
+
+    #[test]
+    fn module_is_named_after_the_type() {
+        let expanded = expand_impl(parse_quote! {
+            impl MyPlugin {
+                pub fn open(lua: &Lua, path: String) -> LuaResult<()> {}
+
+                fn private() {}
+            }
+        });
+
+        assert!(contains(
+            &expanded,
+            quote!(fn my_plugin(lua: &neo_api_rs::mlua::Lua,))
+        ));
+        assert!(contains(
+            &expanded,
+            quote!(neo_api_rs::plugin_module!(my_plugin);)
+        ));
+        assert!(contains(
+            &expanded,
+            quote!(plugin: "my_plugin".to_string(),)
+        ));
+        assert!(contains(&expanded, quote!(MyPlugin::open(lua, arg0)?)));
+        assert!(contains(&expanded, quote!(module.set("open", function)?)));
+        assert!(!contains(
+            &expanded,
+            quote!(module.set("private", function)?)
+        ));
+    }
+
+    #[test]
+    fn setup_reads_the_opts() {
+        let expanded = expand_impl(parse_quote! {
+            impl MyPlugin {
+                #[neo(setup)]
+                pub fn init(lua: &Lua, config: Config) -> LuaResult<()> {}
+            }
+        });
+
+        assert!(contains(&expanded, quote!(module.set("setup", function)?)));
+        assert!(contains(
+            &expanded,
+            quote!((&neo_api_rs::SetupOpts::<Config>::new()).read(lua, "my_plugin", opts)?)
+        ));
+        assert!(contains(&expanded, quote!(MyPlugin::init(lua, config)?)));
+        // Not a real attribute
+        assert!(!contains(&expanded, quote!(neo(setup))));
+    }
+
+    #[test]
+    fn command_passes_the_fargs() {
+        let expanded = expand_impl(parse_quote! {
+            impl MyPlugin {
+                #[neo(command = "MyOpen", desc = "Open a path", nargs = "?")]
+                pub fn open(lua: &Lua, path: Option<String>) -> LuaResult<()> {}
+            }
+        });
+
+        assert!(contains(
+            &expanded,
+            quote!(args: neo_api_rs::UserCommandArgs)
+        ));
+        assert!(contains(
+            &expanded,
+            quote!(exported.call::<()>(mlua::Variadic::from_iter(args.fargs)))
+        ));
+        assert!(contains(
+            &expanded,
+            quote!(lua, "MyOpen", callback, neo_api_rs::UserCommandOpts)
+        ));
+        assert!(contains(
+            &expanded,
+            quote!(nargs: Some(neo_api_rs::CmdNargs::ZeroOrOne))
+        ));
+        assert!(contains(
+            &expanded,
+            quote!(desc: Some("Open a path".to_string()))
+        ));
+        assert!(contains(&expanded, quote!(module.set("open", function)?)));
+    }
+
+    #[test]
+    fn async_functions_are_spawned() {
+        let expanded = expand_impl(parse_quote! {
+            impl MyPlugin {
+                #[neo(name = "index")]
+                pub async fn index_directories(cwd: String, depth: u32) -> NeoResult<()> {}
+            }
+        });
+
+        assert!(contains(&expanded, quote!(neo_api_rs::RTM.spawn)));
+        assert!(contains(
+            &expanded,
+            quote!(MyPlugin::index_directories(arg0, arg1).await)
+        ));
+        assert!(contains(&expanded, quote!(module.set("index", function)?)));
+    }
+
+    #[test]
+    fn invalid_functions() {
+        let errors: [(ItemImpl, &str); 4] = [
+            (
+                parse_quote!(impl P { #[neo(setup)] pub async fn setup(config: Config) {} }),
+                "#[neo(setup)] should be a sync function which takes the config struct",
+            ),
+            (
+                parse_quote!(impl P { pub fn open(&self) {} }),
+                "Exported functions can't take self, use an associated function",
+            ),
+            (
+                parse_quote!(impl P { pub async fn open(lua: &Lua) {} }),
+                "Async functions run on the tokio runtime and can't borrow Lua, \
+                 use NeoScheduler to get back on the main thread",
+            ),
+            (
+                parse_quote!(impl P { #[neo(desc = "Open")] pub fn open() {} }),
+                "desc and nargs only apply to functions with #[neo(command = \"...\")]",
+            ),
+        ];
+
+        for (item_impl, message) in errors {
+            let err = expand(None, item_impl).unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+    }
+}
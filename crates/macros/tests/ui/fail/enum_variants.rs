@@ -0,0 +1,16 @@
+use macros::IntoEnum;
+
+#[derive(IntoEnum)]
+enum Struct {
+    Named { name: String },
+}
+
+#[derive(IntoEnum)]
+enum Duplicate {
+    #[lua(name = "one")]
+    First,
+    #[lua(name = "one")]
+    Second,
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+use macros::IntoTable;
+
+#[derive(IntoTable)]
+struct Skip {
+    #[lua(skip, rename = "other")]
+    name: String,
+}
+
+#[derive(IntoTable)]
+struct Flatten {
+    #[lua(flatten, default)]
+    name: String,
+}
+
+#[derive(IntoTable)]
+struct Unknown {
+    #[lua(renamed = "other")]
+    name: String,
+}
+
+#[derive(IntoTable)]
+struct Tuple(#[lua(rename = "first")] u32, u32);
+
+fn main() {}
@@ -0,0 +1,34 @@
+use macros::neo_plugin;
+
+struct Setup;
+
+#[neo_plugin]
+impl Setup {
+    #[neo(setup)]
+    pub async fn setup(config: u32) {}
+}
+
+struct Command;
+
+#[neo_plugin]
+impl Command {
+    #[neo(command = "Open", nargs = "2")]
+    pub fn open(path: String) {}
+}
+
+struct Borrow;
+
+#[neo_plugin]
+impl Borrow {
+    pub async fn index(lua: &mlua::Lua) {}
+}
+
+struct Private;
+
+#[neo_plugin]
+impl Private {
+    #[neo(command = "Open")]
+    fn open() {}
+}
+
+fn main() {}
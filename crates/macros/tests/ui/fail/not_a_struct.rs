@@ -0,0 +1,13 @@
+use macros::{IntoEnum, IntoTable};
+
+#[derive(IntoTable)]
+enum Level {
+    Error,
+}
+
+#[derive(IntoEnum)]
+struct Name {
+    name: String,
+}
+
+fn main() {}
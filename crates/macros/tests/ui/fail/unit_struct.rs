@@ -0,0 +1,9 @@
+use macros::{FromTable, IntoTable};
+
+#[derive(IntoTable)]
+struct Empty;
+
+#[derive(FromTable)]
+struct Marker;
+
+fn main() {}
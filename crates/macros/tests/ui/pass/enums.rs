@@ -0,0 +1,105 @@
+use macros::{IntoEnum, IntoEnumSC};
+use mlua::{FromLua, IntoLua, Lua};
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, IntoEnumSC)]
+pub enum SplitDir {
+    AboveLeft,
+    BelowRight,
+}
+
+#[derive(Debug, Clone, PartialEq, IntoEnum)]
+pub enum Pattern {
+    #[lua(name = "*")]
+    All,
+    Buffer,
+}
+
+#[derive(Debug, Clone, PartialEq, IntoEnumSC)]
+pub enum Filter {
+    None,
+    Pattern(String),
+    Buffers(Vec<u32>),
+}
+
+/// Like `AutoCmdGroup`
+#[derive(Debug, Clone, PartialEq, IntoEnum)]
+#[lua(untagged)]
+pub enum Group {
+    Integer(u32),
+    String(String),
+}
+
+/// Like `PopupBorder`
+#[derive(Debug, Clone, PartialEq, IntoEnumSC)]
+#[lua(untagged)]
+pub enum Border {
+    None,
+    Rounded,
+    Custom(Vec<String>),
+}
+
+fn round_trip<T>(lua: &Lua, value: T) -> mlua::Result<()>
+where
+    T: IntoLua + FromLua + Clone + PartialEq + Debug,
+{
+    let lua_value = value.clone().into_lua(lua)?;
+
+    assert_eq!(T::from_lua(lua_value, lua)?, value);
+
+    Ok(())
+}
+
+fn parse<T>(value: T)
+where
+    T: Display + FromStr + PartialEq + Debug,
+    T::Err: Debug,
+{
+    assert_eq!(value.to_string().parse::<T>().unwrap(), value);
+}
+
+fn main() -> mlua::Result<()> {
+    let lua = Lua::new();
+
+    round_trip(&lua, SplitDir::AboveLeft)?;
+    round_trip(&lua, Pattern::All)?;
+    round_trip(&lua, Filter::None)?;
+    round_trip(&lua, Filter::Pattern("*.rs".to_string()))?;
+    round_trip(&lua, Filter::Buffers(vec![1, 3]))?;
+    round_trip(&lua, Group::Integer(4))?;
+    round_trip(&lua, Group::String("neo-fuzzy".to_string()))?;
+    round_trip(&lua, Border::Rounded)?;
+    round_trip(&lua, Border::Custom(vec!["╭".to_string(), "─".to_string()]))?;
+
+    parse(SplitDir::BelowRight);
+    parse(Pattern::All);
+    parse(Pattern::Buffer);
+    parse(Filter::None);
+    parse(Border::Rounded);
+
+    assert_eq!(SplitDir::AboveLeft.to_string(), "above_left");
+    assert_eq!(Pattern::All.to_string(), "*");
+    assert!("pattern".parse::<Filter>().is_err());
+    assert!("AboveLeft".parse::<SplitDir>().is_err());
+
+    // Lua representation
+    let globals = lua.globals();
+    globals.set("dir", SplitDir::AboveLeft)?;
+    globals.set("filter", Filter::Pattern("*.rs".to_string()))?;
+    globals.set("group", Group::Integer(4))?;
+    globals.set("border", Border::Custom(vec!["╭".to_string()]))?;
+
+    let check: bool = lua
+        .load(
+            "dir == 'above_left' and filter.pattern == '*.rs' and group == 4 and border[1] == '╭'",
+        )
+        .eval()?;
+    assert!(check);
+
+    // Unknown names are errors instead of a default
+    let unknown = mlua::Value::String(lua.create_string("left")?);
+    assert!(SplitDir::from_lua(unknown, &lua).is_err());
+
+    Ok(())
+}
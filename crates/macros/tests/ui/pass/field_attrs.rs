@@ -0,0 +1,65 @@
+use macros::{FromTable, IntoTable};
+use mlua::{FromLua, IntoLua, Lua, Table};
+
+#[derive(Debug, Default, PartialEq, IntoTable, FromTable)]
+struct Position {
+    line: u32,
+    col: u32,
+}
+
+#[derive(Debug, PartialEq, IntoTable, FromTable)]
+struct Mark {
+    #[lua(rename = "type")]
+    kind: String,
+    #[lua(default)]
+    priority: u32,
+    #[lua(skip)]
+    cached: Vec<u32>,
+    #[lua(flatten)]
+    pos: Position,
+    desc: Option<String>,
+}
+
+fn main() -> mlua::Result<()> {
+    let lua = Lua::new();
+
+    let mark = Mark {
+        kind: "error".into(),
+        priority: 5,
+        cached: vec![1],
+        pos: Position { line: 3, col: 7 },
+        desc: None,
+    };
+
+    let table = Table::from_lua(mark.into_lua(&lua)?, &lua)?;
+
+    assert_eq!(table.get::<String>("type")?, "error");
+    assert_eq!(table.get::<u32>("priority")?, 5);
+    assert_eq!(table.get::<u32>("line")?, 3);
+    assert_eq!(table.get::<u32>("col")?, 7);
+
+    for key in ["kind", "cached", "pos", "desc"] {
+        assert!(!table.contains_key(key)?, "{key} is set");
+    }
+
+    // Missing default and skipped fields, nil option
+    let table: Table = lua.load("{ type = 'hint', line = 1, col = 2 }").eval()?;
+    let mark = Mark::from_lua(mlua::Value::Table(table), &lua)?;
+
+    assert_eq!(
+        mark,
+        Mark {
+            kind: "hint".into(),
+            priority: 0,
+            cached: vec![],
+            pos: Position { line: 1, col: 2 },
+            desc: None,
+        }
+    );
+
+    // Required field
+    let table: Table = lua.load("{ line = 1, col = 2 }").eval()?;
+    assert!(Mark::from_lua(mlua::Value::Table(table), &lua).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use macros::{FromTable, IntoEnumSC, IntoTable};
+use mlua::{FromLua, IntoLua, Lua};
+
+#[derive(Debug, PartialEq, IntoTable, FromTable)]
+pub struct SomeStruct {
+    pub name: String,
+    pub age: usize,
+    pub weight: Option<usize>,
+    pub phone: Phone,
+    pub gender: Gender,
+}
+
+#[derive(Debug, PartialEq, IntoTable, FromTable)]
+pub struct Phone {
+    pub mobile: String,
+    pub home: String,
+}
+
+#[derive(Debug, PartialEq, IntoEnumSC)]
+pub enum Gender {
+    Male,
+    Female,
+}
+
+fn main() -> mlua::Result<()> {
+    let lua = Lua::new();
+
+    let some = SomeStruct {
+        name: "name".into(),
+        age: 30,
+        weight: None,
+        phone: Phone {
+            mobile: "06".into(),
+            home: "010".into(),
+        },
+        gender: Gender::Female,
+    };
+
+    let value = some.into_lua(&lua)?;
+    lua.globals().set("some", value.clone())?;
+
+    let check: bool = lua
+        .load("some.phone.home == '010' and some.gender == 'female' and some.weight == nil")
+        .eval()?;
+    assert!(check);
+
+    let some = SomeStruct::from_lua(value, &lua)?;
+
+    assert_eq!(some.gender, Gender::Female);
+    assert_eq!(some.phone.mobile, "06");
+
+    Ok(())
+}
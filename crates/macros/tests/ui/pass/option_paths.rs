@@ -0,0 +1,52 @@
+use macros::{FromTable, IntoTable};
+use mlua::{FromLua, IntoLua, Lua, Table};
+
+mod config {
+    /// Named `Option`, but not optional
+    pub type Option = u32;
+}
+
+#[derive(Debug, PartialEq, IntoTable, FromTable)]
+struct Paths {
+    plain: Option<u32>,
+    std: std::option::Option<u32>,
+    core: core::option::Option<u32>,
+    custom: config::Option,
+}
+
+fn main() -> mlua::Result<()> {
+    let lua = Lua::new();
+
+    let paths = Paths {
+        plain: None,
+        std: None,
+        core: Some(2),
+        custom: 3,
+    };
+
+    let table = Table::from_lua(paths.into_lua(&lua)?, &lua)?;
+
+    assert!(!table.contains_key("plain")?);
+    assert!(!table.contains_key("std")?);
+    assert_eq!(table.get::<u32>("core")?, 2);
+    assert_eq!(table.get::<u32>("custom")?, 3);
+
+    let table: Table = lua.load("{ custom = 1 }").eval()?;
+    let paths = Paths::from_lua(mlua::Value::Table(table), &lua)?;
+
+    assert_eq!(
+        paths,
+        Paths {
+            plain: None,
+            std: None,
+            core: None,
+            custom: 1,
+        }
+    );
+
+    // The custom `Option` is required
+    let table: Table = lua.load("{ plain = 1 }").eval()?;
+    assert!(Paths::from_lua(mlua::Value::Table(table), &lua).is_err());
+
+    Ok(())
+}
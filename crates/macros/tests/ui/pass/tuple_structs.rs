@@ -0,0 +1,28 @@
+use macros::{FromTable, IntoTable};
+use mlua::{FromLua, IntoLua, Lua, Table};
+
+/// Converted like the inner value
+#[derive(Debug, PartialEq, IntoTable, FromTable)]
+struct BufId(u32);
+
+/// Converted into a sequence
+#[derive(Debug, PartialEq, IntoTable, FromTable)]
+struct Range(u32, u32);
+
+fn main() -> mlua::Result<()> {
+    let lua = Lua::new();
+
+    let value = BufId(7).into_lua(&lua)?;
+    assert_eq!(value.as_u32(), Some(7));
+    assert_eq!(BufId::from_lua(value, &lua)?, BufId(7));
+
+    let value = Range(1, 4).into_lua(&lua)?;
+    let table = Table::from_lua(value.clone(), &lua)?;
+
+    assert_eq!(table.raw_len(), 2);
+    assert_eq!(table.raw_get::<u32>(1)?, 1);
+    assert_eq!(table.raw_get::<u32>(2)?, 4);
+    assert_eq!(Range::from_lua(value, &lua)?, Range(1, 4));
+
+    Ok(())
+}
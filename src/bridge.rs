@@ -1,74 +1,351 @@
-use mlua::prelude::{LuaError, LuaResult};
-use std::{any::Any, collections::HashMap, sync::LazyLock};
-use tokio::sync::RwLock;
+use mlua::prelude::{LuaResult, LuaValue};
+use mlua::Lua;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::{LazyLock, Mutex, PoisonError, RwLock};
 
-pub static LUA_BRIDGE: LazyLock<RwLock<NeoBridge>> =
-    LazyLock::new(|| RwLock::new(NeoBridge(HashMap::new())));
+use crate::{AutoCmdEvent, AutoCmdOpts, NeoApi, NeoBuffer, NeoError, NeoResult, NeoWindow};
 
-/// This can be used to store small data for inside lua async functions,
-/// where you can't move the data inside the closure.
-pub struct NeoBridge(HashMap<String, Box<dyn Any>>);
+/// The type is part of the key, so keys with the same name but another type don't collide
+type EntryKey = (BridgeScope, &'static str, TypeId);
 
-unsafe impl Send for NeoBridge {}
-unsafe impl Sync for NeoBridge {}
+type Entries = HashMap<EntryKey, Box<dyn Any + Send + Sync>>;
 
-impl NeoBridge {
-    pub async fn insert(key: &str, cb: Box<dyn Any>) {
-        let mut bridge = LUA_BRIDGE.write().await;
-        bridge.0.insert(key.to_string(), cb);
-    }
+static LUA_BRIDGE: LazyLock<RwLock<Entries>> = LazyLock::new(|| RwLock::new(HashMap::new()));
 
-    /// Will clone the stored data.
-    pub async fn clone<T: 'static + Clone>(key: &str) -> LuaResult<T> {
-        let bridge = LUA_BRIDGE.read().await;
-
-        if let Some(cb) = bridge.0.get(key) {
-            if let Some(downcast) = cb.downcast_ref::<T>() {
-                return Ok(downcast.clone());
-            } else {
-                return Err(LuaError::external("Can't downcast to the expected type"));
-            }
+/// Scopes which have an autocmd that removes the entries on wipe / close
+static WATCHED: LazyLock<Mutex<HashSet<BridgeScope>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Typed key of an entry in the `NeoBridge`.
+///
+/// ```ignore
+/// const SELECTED: BridgeKey<Vec<PathBuf>> = BridgeKey::new("selected");
+/// ```
+pub struct BridgeKey<T> {
+    name: &'static str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> BridgeKey<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _marker: PhantomData,
         }
+    }
 
-        Err(LuaError::external("Item with key doesn't exist"))
+    pub fn name(&self) -> &'static str {
+        self.name
     }
+}
 
-    pub fn borrow<T: 'static>(&self, key: &str) -> LuaResult<&T> {
-        if let Some(cb) = self.0.get(key) {
-            if let Some(downcast) = cb.downcast_ref::<T>() {
-                return Ok(downcast);
-            } else {
-                return Err(LuaError::external("Can't downcast to the expected type"));
-            }
-        }
+impl<T: 'static> BridgeKey<T> {
+    fn entry_key(&self, scope: BridgeScope) -> EntryKey {
+        (scope, self.name, TypeId::of::<T>())
+    }
+}
 
-        Err(LuaError::external("Item with key doesn't exist"))
+impl<T> Clone for BridgeKey<T> {
+    fn clone(&self) -> Self {
+        *self
     }
+}
+
+impl<T> Copy for BridgeKey<T> {}
+
+/// Lifetime of an entry, buffer and window entries are removed when the buffer is wiped or the
+/// window is closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BridgeScope {
+    Global,
+    Buffer(NeoBuffer),
+    Window(NeoWindow),
+}
 
-    pub fn borrow_mut<T: 'static>(&mut self, key: &str) -> LuaResult<&mut T> {
-        if let Some(cb) = self.0.get_mut(key) {
-            if let Some(downcast) = cb.downcast_mut::<T>() {
-                return Ok(downcast);
-            } else {
-                return Err(LuaError::external("Can't downcast to the expected type"));
+impl BridgeScope {
+    /// Replaces `NeoBuffer::ZERO` and `NeoWindow::CURRENT` with the handle of the current
+    /// buffer or window. `NeoBridge::insert` does this, the other functions use the scope as is.
+    pub fn resolve(self, lua: &Lua) -> LuaResult<Self> {
+        match self {
+            Self::Buffer(buf) if buf.id() == 0 => {
+                Ok(Self::Buffer(NeoBuffer::get_current_buf(lua)?))
             }
+            Self::Window(win) if win.id() == 0 => {
+                Ok(Self::Window(NeoWindow::get_current_win(lua)?))
+            }
+            scope => Ok(scope),
         }
+    }
+}
+
+/// This can be used to store small data for inside lua functions,
+/// where you can't move the data inside the closure.
+///
+/// Entries are identified by scope, key name and key type, two keys with the same name but
+/// different types refer to different entries.
+pub struct NeoBridge;
+
+impl NeoBridge {
+    /// Inserts a value, returns the previous value of the key.
+    /// For buffer and window scopes an autocmd is created which removes the entries,
+    /// the current buffer or window (id 0) is resolved to its handle first.
+    pub fn insert<T: Send + Sync + 'static>(
+        lua: &Lua,
+        scope: BridgeScope,
+        key: BridgeKey<T>,
+        value: T,
+    ) -> LuaResult<Option<T>> {
+        let scope = scope.resolve(lua)?;
+
+        Self::watch(lua, scope)?;
+
+        Ok(Self::insert_entry(scope, key, value))
+    }
+
+    /// Inserts a value in the global scope, can be used outside the Lua thread.
+    pub fn insert_global<T: Send + Sync + 'static>(key: BridgeKey<T>, value: T) -> Option<T> {
+        Self::insert_entry(BridgeScope::Global, key, value)
+    }
+
+    /// Will clone the stored data.
+    pub fn get<T: Clone + Send + Sync + 'static>(
+        scope: BridgeScope,
+        key: BridgeKey<T>,
+    ) -> NeoResult<T> {
+        Self::with(scope, key, T::clone)
+    }
+
+    /// The entry is taken out while `f` runs, so `f` can use the bridge without a deadlock but
+    /// sees the key as missing, the same goes for other threads.
+    pub fn with<T: Send + Sync + 'static, R>(
+        scope: BridgeScope,
+        key: BridgeKey<T>,
+        f: impl FnOnce(&T) -> R,
+    ) -> NeoResult<R> {
+        Self::with_mut(scope, key, |value| f(value))
+    }
+
+    /// The entry is taken out while `f` runs, so `f` can use the bridge without a deadlock but
+    /// sees the key as missing, the same goes for other threads. A value inserted by `f` is kept
+    /// instead of the taken one.
+    pub fn with_mut<T: Send + Sync + 'static, R>(
+        scope: BridgeScope,
+        key: BridgeKey<T>,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> NeoResult<R> {
+        let mut value = Self::take(scope, key)?;
+        let result = f(&mut value);
 
-        Err(LuaError::external("Item with key doesn't exist"))
+        let mut bridge = LUA_BRIDGE.write().unwrap_or_else(PoisonError::into_inner);
+        bridge
+            .entry(key.entry_key(scope))
+            .or_insert(Box::new(value));
+
+        Ok(result)
     }
 
     /// Will consume the stored data.
-    pub async fn consume<T: 'static>(key: &str) -> LuaResult<T> {
-        let mut bridge = LUA_BRIDGE.write().await;
-
-        if let Some(cb) = bridge.0.remove(key) {
-            if let Ok(downcast) = cb.downcast::<T>() {
-                return Ok(*downcast);
-            } else {
-                return Err(LuaError::external("Can't downcast to the expected type"));
-            }
+    pub fn take<T: 'static>(scope: BridgeScope, key: BridgeKey<T>) -> NeoResult<T> {
+        let mut bridge = LUA_BRIDGE.write().unwrap_or_else(PoisonError::into_inner);
+
+        match bridge
+            .remove(&key.entry_key(scope))
+            .and_then(|entry| entry.downcast::<T>().ok())
+        {
+            Some(value) => Ok(*value),
+            None => Err(NeoError::MissingKey(key.name.to_string())),
+        }
+    }
+
+    /// Returns true if the entry existed.
+    pub fn remove<T: 'static>(scope: BridgeScope, key: BridgeKey<T>) -> bool {
+        let mut bridge = LUA_BRIDGE.write().unwrap_or_else(PoisonError::into_inner);
+
+        bridge.remove(&key.entry_key(scope)).is_some()
+    }
+
+    pub fn contains<T: 'static>(scope: BridgeScope, key: BridgeKey<T>) -> bool {
+        let bridge = LUA_BRIDGE.read().unwrap_or_else(PoisonError::into_inner);
+
+        bridge.contains_key(&key.entry_key(scope))
+    }
+
+    /// Removes all entries of a scope.
+    pub fn clear_scope(scope: BridgeScope) {
+        let mut bridge = LUA_BRIDGE.write().unwrap_or_else(PoisonError::into_inner);
+
+        bridge.retain(|(entry_scope, _, _), _| *entry_scope != scope);
+    }
+
+    fn insert_entry<T: Send + Sync + 'static>(
+        scope: BridgeScope,
+        key: BridgeKey<T>,
+        value: T,
+    ) -> Option<T> {
+        let mut bridge = LUA_BRIDGE.write().unwrap_or_else(PoisonError::into_inner);
+
+        bridge
+            .insert(key.entry_key(scope), Box::new(value))
+            .and_then(|prev| prev.downcast::<T>().ok())
+            .map(|prev| *prev)
+    }
+
+    fn watch(lua: &Lua, scope: BridgeScope) -> LuaResult<()> {
+        let (event, buffer, pattern) = match scope {
+            BridgeScope::Global => return Ok(()),
+            BridgeScope::Buffer(buf) => (AutoCmdEvent::BufWipeout, Some(buf.id()), vec![]),
+            BridgeScope::Window(win) => (AutoCmdEvent::WinClosed, None, vec![win.id().to_string()]),
+        };
+
+        let mut watched = WATCHED.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if !watched.insert(scope) {
+            return Ok(());
+        }
+
+        let callback = lua.create_function(move |_, _: LuaValue| {
+            Self::clear_scope(scope);
+
+            let mut watched = WATCHED.lock().unwrap_or_else(PoisonError::into_inner);
+            watched.remove(&scope);
+
+            Ok(())
+        })?;
+
+        let result = NeoApi::create_autocmd(
+            lua,
+            &[event],
+            AutoCmdOpts {
+                callback,
+                buffer,
+                group: None,
+                pattern,
+                once: true,
+                desc: Some("Removes scoped NeoBridge entries".to_string()),
+            },
+        );
+
+        if result.is_err() {
+            watched.remove(&scope);
         }
 
-        Err(LuaError::external("Item with key doesn't exist"))
+        result.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_name_with_other_type_is_another_entry() {
+        const COUNT: BridgeKey<u32> = BridgeKey::new("test_same_name");
+        const LABEL: BridgeKey<String> = BridgeKey::new("test_same_name");
+
+        assert_eq!(NeoBridge::insert_global(COUNT, 1), None);
+        assert_eq!(NeoBridge::insert_global(LABEL, "one".to_string()), None);
+
+        assert_eq!(NeoBridge::get(BridgeScope::Global, COUNT).unwrap(), 1);
+        assert_eq!(NeoBridge::get(BridgeScope::Global, LABEL).unwrap(), "one");
+
+        assert_eq!(NeoBridge::insert_global(COUNT, 2), Some(1));
+        assert_eq!(NeoBridge::take(BridgeScope::Global, COUNT).unwrap(), 2);
+
+        assert!(!NeoBridge::contains(BridgeScope::Global, COUNT));
+        assert!(NeoBridge::contains(BridgeScope::Global, LABEL));
+        assert!(NeoBridge::remove(BridgeScope::Global, LABEL));
+    }
+
+    #[test]
+    fn scopes_are_separate() {
+        const KEY: BridgeKey<u32> = BridgeKey::new("test_scopes");
+        let buf = BridgeScope::Buffer(NeoBuffer::from_id(1));
+
+        NeoBridge::insert_global(KEY, 1);
+        NeoBridge::insert_entry(buf, KEY, 2);
+
+        NeoBridge::clear_scope(buf);
+
+        assert_eq!(NeoBridge::get(BridgeScope::Global, KEY).unwrap(), 1);
+        assert!(matches!(
+            NeoBridge::get(buf, KEY),
+            Err(NeoError::MissingKey(_))
+        ));
+    }
+
+    #[test]
+    fn closures_can_use_the_bridge() {
+        const LIST: BridgeKey<Vec<u32>> = BridgeKey::new("test_closures_list");
+        const COUNT: BridgeKey<u32> = BridgeKey::new("test_closures_count");
+
+        NeoBridge::insert_global(LIST, vec![1, 2]);
+        NeoBridge::insert_global(COUNT, 0);
+
+        let len = NeoBridge::with(BridgeScope::Global, LIST, |list| {
+            NeoBridge::with_mut(BridgeScope::Global, COUNT, |count| *count += 1).unwrap();
+
+            // Taken out while the closure runs
+            assert!(!NeoBridge::contains(BridgeScope::Global, LIST));
+
+            list.len()
+        })
+        .unwrap();
+
+        assert_eq!(len, 2);
+        assert_eq!(NeoBridge::get(BridgeScope::Global, LIST).unwrap(), [1, 2]);
+        assert_eq!(NeoBridge::get(BridgeScope::Global, COUNT).unwrap(), 1);
+
+        // A value inserted by the closure wins
+        NeoBridge::with_mut(BridgeScope::Global, LIST, |list| {
+            list.push(3);
+            NeoBridge::insert_global(LIST, vec![4]);
+        })
+        .unwrap();
+
+        assert_eq!(NeoBridge::take(BridgeScope::Global, LIST).unwrap(), [4]);
+        assert!(NeoBridge::remove(BridgeScope::Global, COUNT));
+    }
+
+    #[cfg(feature = "test-harness")]
+    #[test]
+    fn current_buffer_and_window_are_resolved() -> LuaResult<()> {
+        use crate::NeoMock;
+        use mlua::prelude::LuaTable;
+
+        const KEY: BridgeKey<u32> = BridgeKey::new("test_resolved");
+
+        let mock = NeoMock::new()?;
+        mock.returns("vim.api.nvim_get_current_buf", 5)?;
+        mock.returns("vim.api.nvim_get_current_win", 1001)?;
+        mock.returns("vim.api.nvim_create_autocmd", 1)?;
+
+        NeoBridge::insert(&mock, BridgeScope::Buffer(NeoBuffer::ZERO), KEY, 1)?;
+        NeoBridge::insert(&mock, BridgeScope::Window(NeoWindow::CURRENT), KEY, 2)?;
+
+        let buf = BridgeScope::Buffer(NeoBuffer::from_id(5));
+        let win = BridgeScope::Window(NeoWindow::new(1001));
+
+        assert_eq!(NeoBridge::take(buf, KEY).unwrap(), 1);
+        assert_eq!(NeoBridge::take(win, KEY).unwrap(), 2);
+        assert!(!NeoBridge::contains(
+            BridgeScope::Buffer(NeoBuffer::ZERO),
+            KEY
+        ));
+
+        let autocmds = mock.calls_to("vim.api.nvim_create_autocmd");
+        assert_eq!(autocmds.len(), 2);
+
+        let buffer_opts: LuaTable = autocmds[0].arg(&mock, 1)?;
+        assert_eq!(buffer_opts.get::<u32>("buffer")?, 5);
+
+        let window_opts: LuaTable = autocmds[1].arg(&mock, 1)?;
+        assert_eq!(window_opts.get::<Vec<String>>("pattern")?, ["1001"]);
+
+        NeoBridge::clear_scope(buf);
+        NeoBridge::clear_scope(win);
+
+        Ok(())
     }
 }
@@ -1,11 +1,17 @@
 #![allow(unused)]
 use crate::neo_api::NeoApi;
 use crate::neo_api_types::{ExtmarkOpts, OptValueType};
-use crate::{BufferDeleteOpts, FileTypeMatch, KeymapOpts, Mode};
+use crate::{
+    BufferDeleteOpts, FileTypeMatch, KeymapInfo, KeymapOpts, KeymapRhs, Mode, NeoLuaExt,
+    UserCommandOpts,
+};
 use mlua::prelude::{IntoLua, Lua, LuaError, LuaFunction, LuaResult, LuaTable, LuaValue};
 use mlua::FromLua;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+/// Serialized as the id
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct NeoBuffer(u32);
 
 impl FromLua for NeoBuffer {
@@ -46,7 +52,7 @@ impl NeoBuffer {
       • buf_open_scratch
     */
     pub fn create(lua: &Lua, listed: bool, scratch: bool) -> LuaResult<Self> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_create_buf").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_create_buf")?;
         let buf_id: u32 = lfn.call((listed, scratch))?;
 
         if buf_id == 0 {
@@ -56,14 +62,37 @@ impl NeoBuffer {
         Ok(NeoBuffer::from_id(buf_id))
     }
 
-    //pub fn exists(&self, lua: &Lua) -> LuaResult<bool> {
-    //let lfn: LuaFunction = lua.load("vim.fn.buflisted").eval()?;
+    /**
+    Checks if a buffer is valid.
+
+    Note: ~
+      • Even if a buffer is valid it may have been unloaded. See |api-buffer|
+        for more info about unloaded buffers.
+
+    Return: ~
+        true if the buffer is valid, false otherwise.
+    */
+    pub fn is_valid(&self, lua: &Lua) -> LuaResult<bool> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_is_valid")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Checks if a buffer is valid and loaded. See |api-buffer| for more info
+    about unloaded buffers.
 
-    //lfn.call(self.id())
-    //}
+    Return: ~
+        true if the buffer is valid and loaded, false otherwise.
+    */
+    pub fn is_loaded(&self, lua: &Lua) -> LuaResult<bool> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_is_loaded")?;
+
+        lfn.call(self.id())
+    }
 
     pub fn bufadd(lua: &Lua, path: &str) -> LuaResult<Self> {
-        let lfn: LuaFunction = lua.load("vim.fn.bufadd").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.fn.bufadd")?;
 
         lfn.call(path)
     }
@@ -79,16 +108,13 @@ impl NeoBuffer {
     }
 
     pub fn get_current_buf(lua: &Lua) -> LuaResult<NeoBuffer> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_get_current_buf").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_current_buf")?;
 
         lfn.call(())
     }
 
     pub fn keymap_opts(&self, silent: bool) -> KeymapOpts {
-        KeymapOpts {
-            buffer: Some(self.0),
-            silent: Some(silent),
-        }
+        KeymapOpts::new(self.0, silent)
     }
 
     pub fn set_keymap<'a>(
@@ -96,13 +122,66 @@ impl NeoBuffer {
         lua: &'a Lua,
         mode: Mode,
         lhs: &str,
-        rhs: LuaFunction,
+        rhs: impl Into<KeymapRhs>,
+    ) -> LuaResult<()> {
+        NeoApi::set_keymap(lua, &[mode], lhs, rhs, self.keymap_opts(true))
+    }
+
+    pub fn del_keymap(&self, lua: &Lua, mode: Mode, lhs: &str) -> LuaResult<()> {
+        NeoApi::del_keymap(lua, &[mode], lhs, Some(*self))
+    }
+
+    /**
+    Gets a list of buffer-local |mapping| definitions.
+
+    Parameters: ~
+      • {mode}    Mode short-name ("n", "i", "v", ...)
+
+    Return: ~
+        Array of |maparg()|-like dictionaries describing mappings. The
+        "buffer" key holds the associated buffer handle.
+    */
+    pub fn get_keymap(&self, lua: &Lua, mode: Mode) -> LuaResult<Vec<KeymapInfo>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_get_keymap")?;
+
+        lfn.call((self.id(), mode.get_str()))
+    }
+
+    /// Creates a buffer-local command |:command-buffer|, see |nvim_create_user_command()|
+    pub fn create_user_command(
+        &self,
+        lua: &Lua,
+        name: &str,
+        callback: LuaFunction,
+        opts: UserCommandOpts,
     ) -> LuaResult<()> {
-        NeoApi::set_keymap(lua, mode, lhs, rhs, self.keymap_opts(true))
+        let opts = UserCommandOpts {
+            buffer: Some(*self),
+            ..opts
+        };
+
+        NeoApi::create_user_command(lua, name, callback, opts)
+    }
+
+    /// Deletes a buffer-local user-defined command
+    pub fn del_user_command(&self, lua: &Lua, name: &str) -> LuaResult<()> {
+        NeoApi::del_user_command(lua, name, Some(*self))
+    }
+
+    /**
+    Gets the full file name for the buffer
+
+    Return: ~
+        Buffer name
+    */
+    pub fn get_name(&self, lua: &Lua) -> LuaResult<String> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_get_name")?;
+
+        lfn.call(self.id())
     }
 
     pub fn set_name(&self, lua: &Lua, path: &str) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_set_name").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_set_name")?;
 
         lfn.call((self.id(), path))
     }
@@ -114,7 +193,7 @@ impl NeoBuffer {
         not allowed when |textlock| is active or in the |cmdwin|
     */
     pub fn set_current(&self, lua: &Lua) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_set_current_buf").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_set_current_buf")?;
 
         lfn.call(self.id())
     }
@@ -132,7 +211,7 @@ impl NeoBuffer {
                   • unload: Unloaded only, do not delete. See |:bunload|
     */
     pub fn delete(&self, lua: &Lua, opts: BufferDeleteOpts) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_delete").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_delete")?;
 
         // Bug in nvim API, it won't allow opts not being passed, so create an empty table
         lfn.call((self.id(), opts))
@@ -161,17 +240,17 @@ impl NeoBuffer {
     }
 
     pub fn get_treesitter_lang(&self, lua: &Lua, ft: &str) -> LuaResult<Option<String>> {
-        let lfn_gl: LuaFunction = lua.load("vim.treesitter.language.get_lang").eval()?;
+        let lfn_gl: LuaFunction = lua.neo_fn("vim.treesitter.language.get_lang")?;
         lfn_gl.call(ft)
     }
 
     pub fn start_treesitter(&self, lua: &Lua, lang: &str) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.treesitter.start").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.treesitter.start")?;
         lfn.call((self.id(), lang))
     }
 
     pub fn stop_treesitter(&self, lua: &Lua) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.treesitter.stop").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.treesitter.stop")?;
         lfn.call(self.id())
     }
 
@@ -218,7 +297,7 @@ impl NeoBuffer {
         col_start: u32,
         col_end: i32,
     ) -> LuaResult<i32> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_add_highlight").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_add_highlight")?;
 
         lfn.call((self.id(), ns_id, hl_group, line, col_start, col_end))
     }
@@ -256,18 +335,228 @@ impl NeoBuffer {
         strict_indexing: bool,
         lines: &[T],
     ) -> mlua::Result<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_set_lines").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_set_lines")?;
         let lines: Vec<_> = lines.iter().map(AsRef::as_ref).collect();
 
         lfn.call((self.id(), start, end, strict_indexing, lines))
     }
 
-    pub fn set_text(
-        &self, lua: &Lua,
-        start_row: u32, start_col: u32, end_row: u32, end_col: u32) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_set_text").eval()?;
+    /**
+    Sets (replaces) a range in the buffer
+
+    This is recommended over |nvim_buf_set_lines()| when only modifying parts
+    of a line, as extmarks will be preserved on non-modified parts of the
+    touched lines.
+
+    Indexing is zero-based. Row indices are end-inclusive, and column indices
+    are end-exclusive.
+
+    To insert text at a given `(row, column)` location, use
+    `start_row = end_row = row` and `start_col = end_col = col`. To delete the
+    text in a range, use `replacement = {}`.
+
+    Prefer |nvim_buf_set_lines()| if you are only adding or deleting entire
+    lines.
+
+    Attributes: ~
+        not allowed when |textlock| is active
+
+    Parameters: ~
+      • {start_row}    First line index
+      • {start_col}    Starting column (byte offset) on first line
+      • {end_row}      Last line index, inclusive
+      • {end_col}      Ending column (byte offset) on last line, exclusive
+      • {replacement}  Array of lines to use as replacement
+    */
+    pub fn set_text<T: AsRef<str>>(
+        &self,
+        lua: &Lua,
+        start_row: u32,
+        start_col: u32,
+        end_row: u32,
+        end_col: u32,
+        replacement: &[T],
+    ) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_set_text")?;
+        let replacement: Vec<_> = replacement.iter().map(AsRef::as_ref).collect();
+
+        lfn.call((self.id(), start_row, start_col, end_row, end_col, replacement))
+    }
+
+    /**
+    Gets a range from the buffer.
+
+    This differs from |nvim_buf_get_lines()| in that it allows retrieving only
+    portions of a line.
+
+    Indexing is zero-based. Row indices are end-inclusive, and column indices
+    are end-exclusive.
+
+    Prefer |nvim_buf_get_lines()| when retrieving entire lines.
+
+    Parameters: ~
+      • {start_row}  First line index
+      • {start_col}  Starting column (byte offset) on first line
+      • {end_row}    Last line index, inclusive
+      • {end_col}    Ending column (byte offset) on last line, exclusive
+
+    Return: ~
+        Array of lines, or empty array for unloaded buffer.
+    */
+    pub fn get_text(
+        &self,
+        lua: &Lua,
+        start_row: u32,
+        start_col: u32,
+        end_row: u32,
+        end_col: u32,
+    ) -> LuaResult<Vec<String>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_get_text")?;
+
+        lfn.call((
+            self.id(),
+            start_row,
+            start_col,
+            end_row,
+            end_col,
+            lua.create_table()?,
+        ))
+    }
+
+    /**
+    Returns the byte offset of a line (0-indexed). |api-indexing|
+
+    Line 1 (index=0) has offset 0. UTF-8 bytes are counted. EOL is one byte.
+    'fileformat' and 'fileencoding' are ignored. The line index just after the
+    last line gives the total byte-count of the buffer. A final EOL byte is
+    counted if it would be written, see 'eol'.
+
+    Unlike |line2byte()|, throws error for out-of-bounds indexing. Returns -1
+    for unloaded buffer.
+
+    Parameters: ~
+      • {index}   Line index
+
+    Return: ~
+        Integer byte offset, or -1 for unloaded buffer.
+    */
+    pub fn get_offset(&self, lua: &Lua, index: u32) -> LuaResult<i64> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_get_offset")?;
+
+        lfn.call((self.id(), index))
+    }
+
+    /**
+    Gets a changed tick of a buffer
+
+    Return: ~
+        `b:changedtick` value.
+    */
+    pub fn get_changedtick(&self, lua: &Lua) -> LuaResult<u32> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_get_changedtick")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Returns a `(row,col)` tuple representing the position of the named mark.
+    "End of line" column position is returned as |v:maxcol| (big number). See
+    |mark-motions|.
+
+    Marks are (1,0)-indexed. |api-indexing|
+
+    Parameters: ~
+      • {name}    Mark name
+
+    Return: ~
+        (row, col) tuple, None if the mark is not set.
+    */
+    pub fn get_mark(&self, lua: &Lua, name: char) -> LuaResult<Option<(u32, u32)>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_get_mark")?;
+
+        let mark: LuaTable = lfn.call((self.id(), name.to_string()))?;
+        let mark: (u32, u32) = (mark.raw_get(1)?, mark.raw_get(2)?);
+
+        Ok((mark != (0, 0)).then_some(mark))
+    }
+
+    /**
+    Sets a named mark in the given buffer, all marks are allowed
+    file/uppercase, visual, last change, etc. See |mark-motions|.
+
+    Marks are (1,0)-indexed. |api-indexing|
+
+    Note: ~
+      • Passing 0 as line deletes the mark
+
+    Parameters: ~
+      • {name}    Mark name
+      • {line}    Line number
+      • {col}     Column/row number
+
+    Return: ~
+        true if the mark was set, else error.
+    */
+    pub fn set_mark(&self, lua: &Lua, name: char, line: u32, col: u32) -> LuaResult<bool> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_set_mark")?;
+
+        lfn.call((self.id(), name.to_string(), line, col, lua.create_table()?))
+    }
+
+    /**
+    Deletes a named mark in the buffer. See |mark-motions|.
+
+    Note: ~
+      • only deletes marks set in the buffer, if the mark is not set in the
+        buffer it will return false.
+
+    Return: ~
+        true if the mark was deleted, else false.
+    */
+    pub fn del_mark(&self, lua: &Lua, name: char) -> LuaResult<bool> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_del_mark")?;
+
+        lfn.call((self.id(), name.to_string()))
+    }
+
+    /**
+    Gets a buffer-scoped (b:) variable.
+
+    Parameters: ~
+      • {name}    Variable name
+
+    Return: ~
+        Variable value
+    */
+    pub fn get_var<V: FromLua>(&self, lua: &Lua, name: &str) -> LuaResult<V> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_get_var")?;
+
+        lfn.call((self.id(), name))
+    }
+
+    /**
+    Sets a buffer-scoped (b:) variable
+
+    Parameters: ~
+      • {name}    Variable name
+      • {value}   Variable value
+    */
+    pub fn set_var<V: IntoLua>(&self, lua: &Lua, name: &str, value: V) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_set_var")?;
+
+        lfn.call((self.id(), name, value))
+    }
+
+    /**
+    Removes a buffer-scoped (b:) variable
+
+    Parameters: ~
+      • {name}    Variable name
+    */
+    pub fn del_var(&self, lua: &Lua, name: &str) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_del_var")?;
 
-        lfn.call((self.id(), start_row, start_col, end_row, end_col))
+        lfn.call((self.id(), name))
     }
 
     pub fn get_lines(
@@ -277,19 +566,19 @@ impl NeoBuffer {
         end: i32,
         strict_indexing: bool,
     ) -> LuaResult<Vec<String>> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_get_lines").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_get_lines")?;
 
         lfn.call((self.id(), start, end, strict_indexing))
     }
 
     pub fn line_count(&self, lua: &Lua) -> LuaResult<usize> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_line_count").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_line_count")?;
 
         lfn.call(self.id())
     }
 
     pub fn call(&self, lua: &Lua, cb: LuaFunction) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_call").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_call")?;
 
         lfn.call((self.id(), cb))
     }
@@ -348,7 +637,7 @@ impl NeoBuffer {
         line_start: u32,
         line_end: i32,
     ) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_clear_namespace").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_clear_namespace")?;
 
         lfn.call((self.id(), ns_id, line_start, line_end))
     }
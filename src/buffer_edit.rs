@@ -0,0 +1,384 @@
+use crate::{CmdOpts, NeoApi, NeoBuffer, NeoError, NeoResult};
+use mlua::Lua;
+
+/// Unit of the columns of a `TextPos`, LSP servers usually send UTF-16
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PosEncoding {
+    #[default]
+    Byte,
+    Utf16,
+    Utf32,
+}
+
+impl PosEncoding {
+    /// Byte index of the column in the line, clamped to the end of the line. A UTF-16 or UTF-32
+    /// column inside a character moves to the end of the character.
+    pub fn byte_col(&self, line: &str, col: u32) -> u32 {
+        let col = col as usize;
+
+        let byte_col = match self {
+            Self::Byte => col.min(line.len()),
+            Self::Utf16 | Self::Utf32 => {
+                let mut units = 0;
+                let mut byte_col = line.len();
+
+                for (idx, char) in line.char_indices() {
+                    if col <= units {
+                        byte_col = idx;
+                        break;
+                    }
+
+                    units += match self {
+                        Self::Utf16 => char.len_utf16(),
+                        _ => 1,
+                    };
+                }
+
+                byte_col
+            }
+        };
+
+        byte_col as u32
+    }
+}
+
+/// 0-based position in a buffer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextPos {
+    pub row: u32,
+    pub col: u32,
+}
+
+impl TextPos {
+    pub fn new(row: u32, col: u32) -> Self {
+        Self { row, col }
+    }
+}
+
+/// Replaces the text between start and end (exclusive) with the lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: TextPos,
+    pub end: TextPos,
+    pub lines: Vec<String>,
+}
+
+/**
+Collects text edits and applies them as a single undo step.
+
+All positions refer to the buffer before the edit. The edits are applied from the end of the
+buffer to the start, so an edit never shifts the ranges of the other edits. Inserts at the
+same position end up in the order they were added. Every range is checked before the first
+edit, so an invalid edit leaves the buffer untouched. Like in LSP, `(line_count, 0)` is the end
+of the last line.
+
+```ignore
+BufferEdit::new(buf)
+    .encoding(PosEncoding::Utf16)
+    .if_unchanged(changedtick)
+    .replace(TextPos::new(0, 4), TextPos::new(0, 7), "bar")
+    .insert(TextPos::new(10, 0), "// TODO\n")
+    .apply(lua)?;
+```
+*/
+#[derive(Debug, Clone)]
+pub struct BufferEdit {
+    buf: NeoBuffer,
+    encoding: PosEncoding,
+    changedtick: Option<u32>,
+    edits: Vec<TextEdit>,
+}
+
+impl BufferEdit {
+    pub fn new(buf: NeoBuffer) -> Self {
+        Self {
+            buf,
+            encoding: PosEncoding::Byte,
+            changedtick: None,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Unit of the columns, bytes by default
+    pub fn encoding(mut self, encoding: PosEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Rejects the edit with `NeoError::StaleEdit` when `b:changedtick` is different
+    pub fn if_unchanged(mut self, changedtick: u32) -> Self {
+        self.changedtick = Some(changedtick);
+        self
+    }
+
+    /// Replaces the range, the text is split into lines on `\n`
+    pub fn replace(mut self, start: TextPos, end: TextPos, text: &str) -> Self {
+        self.edits.push(TextEdit {
+            start,
+            end,
+            lines: text.split('\n').map(String::from).collect(),
+        });
+        self
+    }
+
+    pub fn insert(self, pos: TextPos, text: &str) -> Self {
+        self.replace(pos, pos, text)
+    }
+
+    pub fn delete(mut self, start: TextPos, end: TextPos) -> Self {
+        self.edits.push(TextEdit {
+            start,
+            end,
+            lines: Vec::new(),
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    /// Applies all edits in the buffer, undo reverts them at once.
+    pub fn apply(self, lua: &Lua) -> NeoResult<()> {
+        if self.edits.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(expected) = self.changedtick {
+            let actual = self.buf.get_changedtick(lua)?;
+
+            if actual != expected {
+                return Err(NeoError::StaleEdit { expected, actual });
+            }
+        }
+
+        let mut edits = self.edits;
+        let line_count = self.buf.line_count(lua)? as u32;
+
+        Self::clamp_to_buffer(&mut edits, line_count)?;
+        Self::to_byte_cols(lua, self.buf, self.encoding, &mut edits)?;
+        Self::sort_edits(&mut edits)?;
+
+        let buf = self.buf;
+
+        // undojoin only works on the current buffer
+        let apply = lua.create_function(move |lua, ()| {
+            for (i, edit) in edits.iter().rev().enumerate() {
+                if 0 < i {
+                    NeoApi::cmd(lua, CmdOpts::simple("undojoin"))?;
+                }
+
+                buf.set_text(
+                    lua,
+                    edit.start.row,
+                    edit.start.col,
+                    edit.end.row,
+                    edit.end.col,
+                    &edit.lines,
+                )?;
+            }
+
+            Ok(())
+        })?;
+
+        buf.call(lua, apply)?;
+
+        Ok(())
+    }
+
+    fn to_byte_cols(
+        lua: &Lua,
+        buf: NeoBuffer,
+        encoding: PosEncoding,
+        edits: &mut [TextEdit],
+    ) -> NeoResult<()> {
+        let first_row = edits.iter().map(|edit| edit.start.row).min().unwrap_or(0);
+        let last_row = edits.iter().map(|edit| edit.end.row).max().unwrap_or(0);
+
+        let lines = buf.get_lines(lua, first_row as i32, last_row as i32 + 1, true)?;
+
+        for edit in edits.iter_mut() {
+            for pos in [&mut edit.start, &mut edit.end] {
+                let line = &lines[(pos.row - first_row) as usize];
+                pos.col = encoding.byte_col(line, pos.col);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rejects rows past the buffer, `(line_count, 0)` becomes the end of the last line
+    fn clamp_to_buffer(edits: &mut [TextEdit], line_count: u32) -> NeoResult<()> {
+        let buf_end = TextPos::new(line_count, 0);
+
+        for edit in edits.iter_mut() {
+            for pos in [&mut edit.start, &mut edit.end] {
+                if *pos == buf_end {
+                    // byte_col clamps the column to the end of the line
+                    *pos = TextPos::new(line_count.saturating_sub(1), u32::MAX);
+                } else if line_count <= pos.row {
+                    return Err(NeoError::InvalidEdit(format!(
+                        "{:?} is past the end of the buffer with {} lines",
+                        pos, line_count
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stable sort on the range, edits may touch but not overlap
+    fn sort_edits(edits: &mut [TextEdit]) -> NeoResult<()> {
+        if let Some(edit) = edits.iter().find(|edit| edit.end < edit.start) {
+            return Err(NeoError::InvalidEdit(format!(
+                "end {:?} is before start {:?}",
+                edit.end, edit.start
+            )));
+        }
+
+        // An insert goes before a replace at the same position
+        edits.sort_by_key(|edit| (edit.start, edit.end));
+
+        for pair in edits.windows(2) {
+            if pair[1].start < pair[0].end {
+                return Err(NeoError::InvalidEdit(format!(
+                    "range {:?}..{:?} overlaps {:?}..{:?}",
+                    pair[0].start, pair[0].end, pair[1].start, pair[1].end
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(start: (u32, u32), end: (u32, u32), text: &str) -> TextEdit {
+        TextEdit {
+            start: TextPos::new(start.0, start.1),
+            end: TextPos::new(end.0, end.1),
+            lines: vec![text.to_string()],
+        }
+    }
+
+    #[test]
+    fn byte_col_ascii() {
+        for encoding in [PosEncoding::Byte, PosEncoding::Utf16, PosEncoding::Utf32] {
+            assert_eq!(encoding.byte_col("hello", 0), 0);
+            assert_eq!(encoding.byte_col("hello", 3), 3);
+            assert_eq!(encoding.byte_col("hello", 5), 5);
+        }
+    }
+
+    #[test]
+    fn byte_col_multibyte() {
+        // 'ä' is 2 bytes and 1 UTF-16 unit
+        assert_eq!(PosEncoding::Byte.byte_col("äbc", 2), 2);
+        assert_eq!(PosEncoding::Utf16.byte_col("äbc", 1), 2);
+        assert_eq!(PosEncoding::Utf32.byte_col("äbc", 1), 2);
+        assert_eq!(PosEncoding::Utf16.byte_col("äbc", 2), 3);
+    }
+
+    #[test]
+    fn byte_col_surrogate_pairs() {
+        // '😀' is 4 bytes and 2 UTF-16 units
+        assert_eq!(PosEncoding::Utf16.byte_col("😀x", 2), 4);
+        assert_eq!(PosEncoding::Utf32.byte_col("😀x", 1), 4);
+        assert_eq!(PosEncoding::Utf16.byte_col("a😀b", 3), 5);
+    }
+
+    #[test]
+    fn byte_col_inside_a_character() {
+        assert_eq!(PosEncoding::Utf16.byte_col("a😀b", 2), 5);
+    }
+
+    #[test]
+    fn byte_col_past_the_end() {
+        for encoding in [PosEncoding::Byte, PosEncoding::Utf16, PosEncoding::Utf32] {
+            assert_eq!(encoding.byte_col("abc", 10), 3);
+            assert_eq!(encoding.byte_col("äö", u32::MAX), 4);
+            assert_eq!(encoding.byte_col("", 1), 0);
+        }
+    }
+
+    #[test]
+    fn inserts_keep_their_order() {
+        let mut edits = vec![
+            edit((0, 4), (0, 6), "replace"),
+            edit((0, 4), (0, 4), "first"),
+            edit((0, 0), (0, 1), "start"),
+            edit((0, 4), (0, 4), "second"),
+        ];
+
+        BufferEdit::sort_edits(&mut edits).unwrap();
+
+        let order: Vec<_> = edits.iter().map(|edit| edit.lines[0].as_str()).collect();
+        assert_eq!(order, ["start", "first", "second", "replace"]);
+    }
+
+    #[test]
+    fn touching_ranges_are_valid() {
+        let mut edits = vec![
+            edit((0, 3), (1, 2), "b"),
+            edit((0, 0), (0, 3), "a"),
+            edit((1, 2), (1, 2), "c"),
+        ];
+
+        assert!(BufferEdit::sort_edits(&mut edits).is_ok());
+    }
+
+    #[test]
+    fn overlapping_ranges_are_invalid() {
+        let mut edits = vec![edit((0, 0), (0, 4), "a"), edit((0, 3), (0, 5), "b")];
+
+        assert!(matches!(
+            BufferEdit::sort_edits(&mut edits),
+            Err(NeoError::InvalidEdit(_))
+        ));
+
+        let mut edits = vec![edit((0, 0), (2, 0), "a"), edit((1, 0), (1, 0), "b")];
+
+        assert!(BufferEdit::sort_edits(&mut edits).is_err());
+    }
+
+    #[test]
+    fn end_before_start_is_invalid() {
+        let mut edits = vec![edit((1, 0), (0, 5), "a")];
+
+        assert!(matches!(
+            BufferEdit::sort_edits(&mut edits),
+            Err(NeoError::InvalidEdit(_))
+        ));
+    }
+
+    #[test]
+    fn buffer_end_is_the_end_of_the_last_line() {
+        let mut edits = vec![edit((1, 0), (3, 0), "a"), edit((3, 0), (3, 0), "b")];
+
+        BufferEdit::clamp_to_buffer(&mut edits, 3).unwrap();
+
+        assert_eq!(edits[0].start, TextPos::new(1, 0));
+        assert_eq!(edits[0].end, TextPos::new(2, u32::MAX));
+        assert_eq!(edits[1].start, TextPos::new(2, u32::MAX));
+    }
+
+    #[test]
+    fn rows_past_the_buffer_are_invalid() {
+        for (start, end) in [((0, 0), (3, 1)), ((0, 0), (4, 0)), ((3, 1), (3, 2))] {
+            let mut edits = vec![edit(start, end, "a")];
+
+            assert!(matches!(
+                BufferEdit::clamp_to_buffer(&mut edits, 3),
+                Err(NeoError::InvalidEdit(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// RGB color, displayed as `#rrggbb` like the colors of `HLOpts`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NeoColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl NeoColor {
+    pub const BLACK: Self = Self::new(0, 0, 0);
+    pub const WHITE: Self = Self::new(255, 255, 255);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Color as returned by |nvim_get_hl()|, e.g. `0xff8800`
+    pub const fn from_u32(rgb: u32) -> Self {
+        Self::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+
+    pub const fn to_u32(self) -> u32 {
+        ((self.r as u32) << 16) | ((self.g as u32) << 8) | self.b as u32
+    }
+
+    /// Parses `#rrggbb` or `#rgb`, the `#` is optional. Color names return `None`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        match hex.len() {
+            6 => u32::from_str_radix(hex, 16).ok().map(Self::from_u32),
+            3 => {
+                let rgb = u16::from_str_radix(hex, 16).ok()?;
+                let channel = |shift: u16| ((rgb >> shift) & 0xf) as u8 * 0x11;
+
+                Some(Self::new(channel(8), channel(4), channel(0)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_hex(self) -> String {
+        self.to_string()
+    }
+
+    /// Mixes in the other color, alpha 0 keeps this color and 1 gives the other one
+    pub fn blend(self, other: Self, alpha: f32) -> Self {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let mix =
+            |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * alpha).round() as u8;
+
+        Self::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+        )
+    }
+
+    /// Moves the color towards white by the amount between 0 and 1
+    pub fn lighten(self, amount: f32) -> Self {
+        self.blend(Self::WHITE, amount)
+    }
+
+    /// Moves the color towards black by the amount between 0 and 1
+    pub fn darken(self, amount: f32) -> Self {
+        self.blend(Self::BLACK, amount)
+    }
+
+    /// WCAG relative luminance between 0 and 1, useful to tell dark and light backgrounds apart
+    pub fn luminance(self) -> f32 {
+        let linear = |channel: u8| {
+            let c = channel as f32 / 255.0;
+
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+
+    /// True when white text has more contrast on the color than black text
+    pub fn is_dark(self) -> bool {
+        // (1.05 / (l + 0.05)) > ((l + 0.05) / 0.05) below this luminance
+        self.luminance() < 0.179
+    }
+}
+
+impl fmt::Display for NeoColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl FromStr for NeoColor {
+    type Err = String;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(hex).ok_or_else(|| format!("'{hex}' is not a #rrggbb color"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex() {
+        assert_eq!(
+            NeoColor::from_hex("#ff8800"),
+            Some(NeoColor::new(255, 136, 0))
+        );
+        assert_eq!(
+            NeoColor::from_hex("FF8800"),
+            Some(NeoColor::new(255, 136, 0))
+        );
+        assert_eq!(NeoColor::from_hex("#f80"), Some(NeoColor::new(255, 136, 0)));
+        assert_eq!(
+            NeoColor::from_hex("abc"),
+            Some(NeoColor::new(170, 187, 204))
+        );
+
+        for hex in [
+            "", "#", "#ff88", "#ff88000", "red", "#gg8800", "+ff880", "#+f8", "#ff88 0",
+        ] {
+            assert_eq!(NeoColor::from_hex(hex), None, "{hex}");
+        }
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let color = NeoColor::from_u32(0x1a2b3c);
+
+        assert_eq!(color.to_hex(), "#1a2b3c");
+        assert_eq!(color.to_hex().parse(), Ok(color));
+    }
+
+    #[test]
+    fn blend() {
+        let red = NeoColor::new(255, 0, 0);
+        let blue = NeoColor::new(0, 0, 255);
+
+        assert_eq!(red.blend(blue, 0.0), red);
+        assert_eq!(red.blend(blue, 1.0), blue);
+        assert_eq!(red.blend(blue, 0.5), NeoColor::new(128, 0, 128));
+        assert_eq!(red.blend(blue, -1.0), red);
+        assert_eq!(red.blend(blue, 2.0), blue);
+    }
+
+    #[test]
+    fn lighten_and_darken() {
+        let gray = NeoColor::new(100, 100, 100);
+
+        assert_eq!(gray.lighten(0.0), gray);
+        assert_eq!(gray.lighten(1.0), NeoColor::WHITE);
+        assert_eq!(gray.lighten(0.5), NeoColor::new(178, 178, 178));
+        assert_eq!(gray.darken(1.0), NeoColor::BLACK);
+        assert_eq!(gray.darken(0.5), NeoColor::new(50, 50, 50));
+    }
+
+    #[test]
+    fn luminance() {
+        assert_eq!(NeoColor::BLACK.luminance(), 0.0);
+        assert!((NeoColor::WHITE.luminance() - 1.0).abs() < 1e-6);
+        assert!((NeoColor::new(128, 128, 128).luminance() - 0.2158).abs() < 1e-3);
+
+        assert!(NeoColor::from_u32(0x1e1e2e).is_dark());
+        assert!(NeoColor::new(64, 64, 64).is_dark());
+        assert!(!NeoColor::new(128, 128, 128).is_dark());
+        assert!(!NeoColor::from_u32(0xeff1f5).is_dark());
+        assert!(!NeoColor::new(0, 200, 0).is_dark());
+    }
+}
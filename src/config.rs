@@ -0,0 +1,481 @@
+use mlua::prelude::{LuaResult, LuaTable, LuaValue};
+use mlua::{FromLua, Lua};
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::{LogLevel, NeoApi};
+
+/// Invalid or unknown key in the options of `setup(opts)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Full key, e.g. `fuzzy.keymaps.close`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Plugin configuration which is read from `require('plugin').setup({...})`.
+///
+/// Only the keys in the opts override the defaults, invalid values keep the default. Invalid
+/// and unknown keys are collected with their full path.
+///
+/// ```ignore
+/// #[derive(Default)]
+/// struct TravellerConfig {
+///     show_hidden: bool,
+///     fuzzy: FuzzyOptions,
+/// }
+///
+/// impl NeoConfig for TravellerConfig {
+///     fn merge(&mut self, reader: &mut ConfigReader) {
+///         reader.read("show_hidden", &mut self.show_hidden);
+///         reader.nested("fuzzy", &mut self.fuzzy);
+///     }
+/// }
+///
+/// let config = TravellerConfig::from_opts(lua, "traveller", opts)?;
+/// ```
+pub trait NeoConfig: Default {
+    /// Reads every supported key, keys which are not read are reported as unknown.
+    fn merge(&mut self, reader: &mut ConfigReader);
+
+    /// Defaults merged with the opts, the errors are reported with `vim.notify`.
+    fn from_opts(lua: &Lua, plugin: &str, opts: LuaValue) -> LuaResult<Self> {
+        let mut config = Self::default();
+        let errors = config.merge_opts(lua, opts);
+
+        if !errors.is_empty() {
+            let mut msg = format!("{plugin}: invalid setup options");
+
+            for err in errors.iter() {
+                msg.push_str(&format!("\n  {err}"));
+            }
+
+            NeoApi::notify_level(lua, &msg, LogLevel::Warn)?;
+        }
+
+        Ok(config)
+    }
+
+    /// Merges the opts (a table or nil) and returns the errors.
+    fn merge_opts(&mut self, lua: &Lua, opts: LuaValue) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+
+        match opts {
+            LuaValue::Nil => {}
+            LuaValue::Table(table) => {
+                let mut reader = ConfigReader::new(lua, table, String::new(), &mut errors);
+                self.merge(&mut reader);
+                reader.finish();
+            }
+            opts => errors.push(ConfigError {
+                path: "opts".to_string(),
+                message: format!("expected table, got {}", opts.type_name()),
+            }),
+        }
+
+        errors
+    }
+}
+
+/// Reads the keys of one table of the options, see `NeoConfig`.
+pub struct ConfigReader<'a> {
+    lua: &'a Lua,
+    table: LuaTable,
+    /// Path of the table, empty for the root
+    path: String,
+    known: Vec<&'static str>,
+    errors: &'a mut Vec<ConfigError>,
+}
+
+impl<'a> ConfigReader<'a> {
+    fn new(lua: &'a Lua, table: LuaTable, path: String, errors: &'a mut Vec<ConfigError>) -> Self {
+        Self {
+            lua,
+            table,
+            path,
+            known: Vec::new(),
+            errors,
+        }
+    }
+
+    pub fn lua(&self) -> &Lua {
+        self.lua
+    }
+
+    /// Overrides the target when the key is set and has a valid value.
+    pub fn read<V: FromLua>(&mut self, key: &'static str, target: &mut V) {
+        self.read_with(key, target, |_| Ok(()));
+    }
+
+    /// Same as `read`, the value is only used when validate returns Ok.
+    pub fn read_with<V: FromLua>(
+        &mut self,
+        key: &'static str,
+        target: &mut V,
+        validate: impl FnOnce(&V) -> Result<(), String>,
+    ) {
+        let Some(value) = self.value(key) else {
+            return;
+        };
+
+        match V::from_lua(value, self.lua) {
+            Ok(value) => match validate(&value) {
+                Ok(()) => *target = value,
+                Err(message) => self.error(key, message),
+            },
+            Err(err) => self.error(key, err),
+        }
+    }
+
+    /// Merges a nested table into the target.
+    pub fn nested<C: NeoConfig>(&mut self, key: &'static str, target: &mut C) {
+        let Some(value) = self.value(key) else {
+            return;
+        };
+
+        let LuaValue::Table(table) = value else {
+            self.error(key, format!("expected table, got {}", value.type_name()));
+            return;
+        };
+
+        let path = self.key_path(key);
+        let mut reader = ConfigReader::new(self.lua, table, path, self.errors);
+
+        target.merge(&mut reader);
+        reader.finish();
+    }
+
+    /// Reports an error for a key of this table, e.g. for combinations of keys.
+    pub fn error(&mut self, key: &str, message: impl fmt::Display) {
+        let path = self.key_path(key);
+
+        self.errors.push(ConfigError {
+            path,
+            message: message.to_string(),
+        });
+    }
+
+    /// Marks the key as known, returns the value if it is set.
+    fn value(&mut self, key: &'static str) -> Option<LuaValue> {
+        self.known.push(key);
+
+        match self.table.raw_get::<LuaValue>(key) {
+            Ok(LuaValue::Nil) => None,
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.error(key, err);
+                None
+            }
+        }
+    }
+
+    fn key_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{key}", self.path)
+        }
+    }
+
+    /// Reports the keys which weren't read.
+    fn finish(mut self) {
+        let mut unknown = Vec::new();
+
+        for pair in self.table.clone().pairs::<LuaValue, LuaValue>() {
+            let Ok((key, _)) = pair else {
+                continue;
+            };
+
+            let key = match key {
+                LuaValue::String(key) => key.to_string_lossy().to_string(),
+                LuaValue::Integer(idx) => format!("[{idx}]"),
+                key => format!("<{}>", key.type_name()),
+            };
+
+            if !self.known.contains(&key.as_str()) {
+                unknown.push(key);
+            }
+        }
+
+        // Table order is random
+        unknown.sort();
+
+        for key in unknown {
+            self.error(&key, "unknown key");
+        }
+    }
+}
+
+/// Reads the config of `#[neo(setup)]` with `NeoConfig::from_opts` when the type implements
+/// it, otherwise with `FromLua`. The macro calls `(&SetupOpts::<T>::new()).read(..)` with both
+/// traits in scope, method lookup tries `SetupNeoConfig` before it adds the `&` for
+/// `SetupFromLua`.
+#[doc(hidden)]
+pub struct SetupOpts<T>(PhantomData<T>);
+
+impl<T> SetupOpts<T> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait SetupNeoConfig<T> {
+    fn read(&self, lua: &Lua, plugin: &str, opts: LuaValue) -> LuaResult<T>;
+}
+
+impl<T: NeoConfig> SetupNeoConfig<T> for SetupOpts<T> {
+    fn read(&self, lua: &Lua, plugin: &str, opts: LuaValue) -> LuaResult<T> {
+        T::from_opts(lua, plugin, opts)
+    }
+}
+
+#[doc(hidden)]
+pub trait SetupFromLua<T> {
+    fn read(&self, lua: &Lua, plugin: &str, opts: LuaValue) -> LuaResult<T>;
+}
+
+impl<T: FromLua> SetupFromLua<T> for &SetupOpts<T> {
+    /// setup() without options reads the config from an empty table
+    fn read(&self, lua: &Lua, _plugin: &str, opts: LuaValue) -> LuaResult<T> {
+        let opts = match opts {
+            LuaValue::Nil => LuaValue::Table(lua.create_table()?),
+            opts => opts,
+        };
+
+        T::from_lua(opts, lua)
+    }
+}
+
+#[cfg(all(test, feature = "test-harness"))]
+mod tests {
+    use super::*;
+    use crate::NeoMock;
+
+    #[derive(Debug, PartialEq)]
+    struct Keymaps {
+        close: String,
+    }
+
+    impl Default for Keymaps {
+        fn default() -> Self {
+            Self {
+                close: "<Esc>".to_string(),
+            }
+        }
+    }
+
+    impl NeoConfig for Keymaps {
+        fn merge(&mut self, reader: &mut ConfigReader) {
+            reader.read_with("close", &mut self.close, |close| {
+                if close.is_empty() {
+                    Err("can't be empty".to_string())
+                } else {
+                    Ok(())
+                }
+            });
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Fuzzy {
+        keymaps: Keymaps,
+    }
+
+    impl NeoConfig for Fuzzy {
+        fn merge(&mut self, reader: &mut ConfigReader) {
+            reader.nested("keymaps", &mut self.keymaps);
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct TestConfig {
+        show_hidden: bool,
+        depth: u32,
+        fuzzy: Fuzzy,
+    }
+
+    impl Default for TestConfig {
+        fn default() -> Self {
+            Self {
+                show_hidden: false,
+                depth: 3,
+                fuzzy: Fuzzy::default(),
+            }
+        }
+    }
+
+    impl NeoConfig for TestConfig {
+        fn merge(&mut self, reader: &mut ConfigReader) {
+            reader.read("show_hidden", &mut self.show_hidden);
+            reader.read_with("depth", &mut self.depth, |depth| match *depth {
+                0 => Err("must be positive".to_string()),
+                _ => Ok(()),
+            });
+            reader.nested("fuzzy", &mut self.fuzzy);
+        }
+    }
+
+    fn merge(mock: &NeoMock, opts: &str) -> LuaResult<(TestConfig, Vec<ConfigError>)> {
+        let opts: LuaValue = mock.load(opts).eval()?;
+        let mut config = TestConfig::default();
+        let errors = config.merge_opts(mock, opts);
+
+        Ok((config, errors))
+    }
+
+    fn paths(errors: &[ConfigError]) -> Vec<&str> {
+        errors.iter().map(|err| err.path.as_str()).collect()
+    }
+
+    #[test]
+    fn set_keys_override_the_defaults() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        let (config, errors) = merge(
+            &mock,
+            "{ show_hidden = true, fuzzy = { keymaps = { close = 'q' } } }",
+        )?;
+
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(config.show_hidden);
+        assert_eq!(config.depth, 3);
+        assert_eq!(config.fuzzy.keymaps.close, "q");
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_keys() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        let (config, errors) = merge(
+            &mock,
+            "{ show_hiden = true, fuzzy = { keymaps = { close = 'q', quit = 'x' } } }",
+        )?;
+
+        assert_eq!(paths(&errors), ["fuzzy.keymaps.quit", "show_hiden"]);
+        assert!(errors.iter().all(|err| err.message == "unknown key"));
+        assert_eq!(config.fuzzy.keymaps.close, "q");
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_types_keep_the_default() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        let (config, errors) = merge(&mock, "{ depth = 'deep', fuzzy = 1 }")?;
+
+        assert_eq!(paths(&errors), ["depth", "fuzzy"]);
+        assert_eq!(errors[1].message, "expected table, got integer");
+        assert_eq!(config, TestConfig::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_validation_keeps_the_default() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        let (config, errors) = merge(&mock, "{ depth = 0 }")?;
+
+        assert_eq!(
+            errors,
+            [ConfigError {
+                path: "depth".to_string(),
+                message: "must be positive".to_string(),
+            }]
+        );
+        assert_eq!(config.depth, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_errors_have_the_full_path() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        let (config, errors) = merge(&mock, "{ fuzzy = { keymaps = { close = '' } } }")?;
+
+        assert_eq!(
+            errors,
+            [ConfigError {
+                path: "fuzzy.keymaps.close".to_string(),
+                message: "can't be empty".to_string(),
+            }]
+        );
+        assert_eq!(config.fuzzy.keymaps.close, "<Esc>");
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_items_are_unknown_keys() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        let (_, errors) = merge(&mock, "{ 'hidden', show_hidden = true }")?;
+
+        assert_eq!(paths(&errors), ["[1]"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nil_and_non_table_opts() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+
+        let (config, errors) = merge(&mock, "nil")?;
+        assert!(errors.is_empty());
+        assert_eq!(config, TestConfig::default());
+
+        let (config, errors) = merge(&mock, "'hidden'")?;
+        assert_eq!(
+            errors,
+            [ConfigError {
+                path: "opts".to_string(),
+                message: "expected table, got string".to_string(),
+            }]
+        );
+        assert_eq!(config, TestConfig::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn setup_reads_neo_configs_with_from_opts() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        let opts: LuaValue = mock.load("{ depth = 5, colour = 'red' }").eval()?;
+
+        let config: TestConfig = (&SetupOpts::<TestConfig>::new()).read(&mock, "test", opts)?;
+
+        assert_eq!(config.depth, 5);
+
+        let notify = mock.calls_to("vim.notify");
+        assert_eq!(notify.len(), 1);
+        assert_eq!(
+            notify[0].arg::<String>(&mock, 0)?,
+            "test: invalid setup options\n  colour: unknown key"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn setup_reads_other_types_with_from_lua() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+
+        let opts: LuaValue = mock.load("{ depth = 5 }").eval()?;
+        let table: LuaTable = (&SetupOpts::<LuaTable>::new()).read(&mock, "test", opts)?;
+        assert_eq!(table.get::<u32>("depth")?, 5);
+
+        // setup() without options
+        let table: LuaTable = (&SetupOpts::<LuaTable>::new()).read(&mock, "test", LuaValue::Nil)?;
+        assert!(table.is_empty());
+
+        assert!(!mock.called("vim.notify"));
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use std::{borrow::Cow, path::PathBuf, str::FromStr};
 use tokio::fs;
 
-use crate::{LineOut, NeoDebug, NeoUtils, RTM};
+use crate::{LineOut, NeoDebug, NeoResult, NeoUtils};
 
 pub struct Database {
     mem: sqlx::SqlitePool,
@@ -9,32 +9,16 @@ pub struct Database {
 }
 
 impl Database {
-    pub fn new() -> Self {
-        RTM.block_on(async move {
-            let result = Database::init().await;
-
-            if let Err(err) = result {
-                NeoDebug::log(err.to_string()).await;
-                std::process::exit(0);
-            };
-
-            result.unwrap()
-        })
-    }
-
-    pub async fn init() -> sqlx::Result<Self> {
+    pub async fn init() -> NeoResult<Self> {
         let tmp = NeoUtils::home_directory().join(".local/share/neo-api-rs");
         let extension_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("src/extensions/fuzzy")
             .to_string_lossy()
             .to_string();
 
-        if !tmp.is_dir() {
-            fs::create_dir(&tmp).await?;
-        }
+        fs::create_dir_all(&tmp).await?;
 
-        let mem_options = sqlx::sqlite::SqliteConnectOptions::from_str(":memory:")
-            .unwrap()
+        let mem_options = sqlx::sqlite::SqliteConnectOptions::from_str(":memory:")?
             .extension(extension_path.clone());
 
         let file_options = sqlx::sqlite::SqliteConnectOptions::new()
@@ -73,7 +57,7 @@ impl Database {
         .execute(&file)
         .await?;
 
-        NeoDebug::log("Databases initialized").await;
+        NeoDebug::log("Databases initialized");
 
         Ok(Self { file, mem })
     }
@@ -85,7 +69,7 @@ impl Database {
         {
             Ok(count) => count == 0,
             Err(e) => {
-                NeoDebug::log(e).await;
+                NeoDebug::log(e);
                 true
             }
         }
@@ -116,11 +100,11 @@ impl Database {
 
         match out {
             Ok(out) => {
-                //NeoDebug::log_dbg(&out).await;
+                //NeoDebug::log_dbg(&out);
                 Ok(out)
             }
             Err(e) => {
-                NeoDebug::log(&e).await;
+                NeoDebug::log(&e);
                 Err(e)
             }
         }
@@ -157,7 +141,7 @@ impl Database {
         match out {
             Ok(out) => out,
             Err(err) => {
-                NeoDebug::log(&err).await;
+                NeoDebug::log(&err);
                 vec![]
             }
         }
@@ -169,7 +153,7 @@ impl Database {
             .execute(&self.file)
             .await
         {
-            NeoDebug::log(e).await;
+            NeoDebug::log(e);
         }
     }
 
@@ -208,7 +192,7 @@ impl Database {
             .execute(&self.file)
             .await
         {
-            NeoDebug::log(err).await;
+            NeoDebug::log(err);
         }
     }
 
@@ -217,7 +201,7 @@ impl Database {
             .execute(&self.mem)
             .await
         {
-            NeoDebug::log(err).await;
+            NeoDebug::log(err);
         }
     }
 
@@ -1,114 +1,114 @@
 use mlua::prelude::LuaResult;
 use mlua::Lua;
-use std::fmt::Debug;
-use std::time::Duration;
-use std::{env, fmt::Display};
-use std::io::Write;
-use tokio::{
-    fs::{self, OpenOptions},
-    io::{self, AsyncWriteExt},
-};
-
-use crate::{
-    HLText, NeoBuffer, NeoPopup, PopupAlign, PopupBorder, PopupRelative, PopupSize, TextType,
-    WinOptions,
-};
+use std::fmt::{Debug, Display};
+use std::time::{Duration, Instant};
+use tokio::io;
+
+use crate::{LogLevel, NeoLogViewer, NeoLogger};
 
 pub struct NeoDebug;
 
 impl NeoDebug {
-    pub async fn log<AsStr: Display>(message: AsStr) {
-        let mut dir = env::temp_dir();
-        dir.push("neo-api-rs");
-
-        let _ = fs::create_dir_all(&dir).await;
-
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .append(true)
-            .open(dir.join("debug.log"))
-            .await.unwrap();
-
-        let mut bytes = vec![];
-        let _ = writeln!(bytes, "{}", message);
-        let _ = file.write_all(&bytes).await;
+    /// Logs on debug level, see `NeoLogger` for the configuration.
+    #[track_caller]
+    pub fn log<AsStr: Display>(message: AsStr) {
+        NeoLogger::write_here(LogLevel::Debug, message);
     }
 
-    pub async fn log_dbg<AsDbg: Debug>(message: AsDbg) {
-        let mut dir = env::temp_dir();
-        dir.push("neo-api-rs");
+    #[track_caller]
+    pub fn log_dbg<AsDbg: Debug>(message: AsDbg) {
+        NeoLogger::write_here(LogLevel::Debug, format!("{:?}", message));
+    }
 
-        let _ = fs::create_dir_all(&dir).await;
+    #[track_caller]
+    pub fn trace(message: impl Display) {
+        NeoLogger::write_here(LogLevel::Trace, message);
+    }
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .append(true)
-            .open(dir.join("debug.log"))
-            .await.unwrap();
+    #[track_caller]
+    pub fn debug(message: impl Display) {
+        NeoLogger::write_here(LogLevel::Debug, message);
+    }
 
-        let message = format!("{:?}\n", message);
-        let _ = file.write_all(message.as_bytes()).await;
+    #[track_caller]
+    pub fn info(message: impl Display) {
+        NeoLogger::write_here(LogLevel::Info, message);
     }
 
-    pub async fn log_duration(before: Duration, after: Duration, tag: &str) {
-        let message = format!("{tag}: {}ms", after.as_millis() - before.as_millis()); 
-        Self::log(message).await
+    #[track_caller]
+    pub fn warn(message: impl Display) {
+        NeoLogger::write_here(LogLevel::Warn, message);
     }
 
-    pub async fn clear_logs() -> io::Result<()> {
-        let log_file = env::temp_dir().join("neo-api-rs/debug.log");
+    #[track_caller]
+    pub fn error(message: impl Display) {
+        NeoLogger::write_here(LogLevel::Error, message);
+    }
 
-        fs::write(log_file, b"").await
+    /// Logs the time between before and after on debug level. With the `tracing` feature and
+    /// an active subscriber it's recorded as a `timed` span with an `elapsed_ms` field.
+    #[track_caller]
+    pub fn log_duration(before: Duration, after: Duration, tag: &str) {
+        let elapsed_ms = after.saturating_sub(before).as_millis() as u64;
+
+        #[cfg(feature = "tracing")]
+        if !tracing::debug_span!("timed", tag, elapsed_ms).is_disabled() {
+            return;
+        }
+
+        Self::log(format!("{tag}: {elapsed_ms}ms"));
     }
 
-    pub async fn display(lua: Lua) -> LuaResult<()> {
-        let buf = NeoBuffer::create(&lua, false, true)?;
+    /// Measures until the returned timer is dropped. With the `tracing` feature and an active
+    /// subscriber the timing is recorded as a span.
+    pub fn timed(tag: &'static str) -> NeoTimer {
+        NeoTimer::new(tag)
+    }
 
-        // TODO readonly
+    pub async fn clear_logs() -> io::Result<()> {
+        NeoLogger::clear().await
+    }
+
+    /// Opens the interactive log viewer, see `NeoLogViewer`.
+    pub fn display(lua: &Lua) -> LuaResult<()> {
+        NeoLogViewer::open(lua)
+    }
+}
+
+pub struct NeoTimer {
+    tag: &'static str,
+    start: Instant,
+    #[cfg(feature = "tracing")]
+    _span: tracing::Span,
+}
 
-        let mut log_file = env::temp_dir();
-        log_file.push("neo-api-rs/debug.log");
-        let log_file = fs::read_to_string(log_file).await?;
+impl NeoTimer {
+    fn new(tag: &'static str) -> Self {
+        Self {
+            tag,
+            start: Instant::now(),
+            #[cfg(feature = "tracing")]
+            _span: tracing::debug_span!("timed", tag),
+        }
+    }
 
-        let mut lines = vec![];
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
 
-        for line in log_file.lines() {
-            lines.push(line);
+impl Drop for NeoTimer {
+    fn drop(&mut self) {
+        // The subscriber records the span with its duration when it closes
+        #[cfg(feature = "tracing")]
+        if !self._span.is_disabled() {
+            return;
         }
 
-        buf.set_lines(&lua, 0, -1, false, &lines)?;
-
-        let popup_win = NeoPopup::open_win(
-            &lua,
-            &buf,
-            true,
-            WinOptions {
-                relative: PopupRelative::Editor,
-                width: Some(PopupSize::Percentage(0.8)),
-                height: Some(PopupSize::Percentage(0.8)),
-                col: Some(PopupSize::Percentage(0.1)),
-                row: Some(PopupSize::Percentage(0.1)),
-                style: None,
-                border: PopupBorder::Rounded,
-                title: Some(TextType::Tuples(vec![HLText::new("Debug logs", "Debug")])),
-                title_pos: PopupAlign::Left,
-                anchor: crate::Anchor::NorthWest,
-                ..Default::default()
-            },
-        )?;
-
-        buf.set_keymap(
-            &lua,
-            crate::Mode::Normal,
-            "<Esc>",
-            lua.create_function(move |lua, ()| {
-                popup_win.close(lua, false)?;
-                Ok(())
-            })?,
-        )?;
-
-        Ok(())
+        NeoLogger::write(
+            LogLevel::Debug,
+            None,
+            format!("{}: {}ms", self.tag, self.start.elapsed().as_millis()),
+        );
     }
 }
@@ -0,0 +1,110 @@
+use mlua::prelude::LuaError;
+use std::{fmt, io};
+
+pub type NeoResult<T> = Result<T, NeoError>;
+
+/// Errors of neo-api-rs, converts into a `LuaError` so it can be returned from Lua callbacks.
+#[derive(Debug)]
+pub enum NeoError {
+    /// Error raised by Lua or the Neovim API
+    Lua(LuaError),
+    /// Error of the fuzzy finder database
+    Db(sqlx::Error),
+    Io(io::Error),
+    /// An external command couldn't be started
+    Spawn {
+        cmd: String,
+        source: io::Error,
+    },
+    /// An external command exited with a non zero exit code
+    CmdFailed {
+        cmd: String,
+        stderr: String,
+    },
+    /// Key doesn't exist in a store (e.g. `NeoBridge`)
+    MissingKey(String),
+    /// Shared state is in use and couldn't be locked without blocking
+    Locked(&'static str),
+    /// Task or scheduled job was dropped before it finished
+    Cancelled,
+    /// Msgpack RPC request to Neovim failed
+    Rpc(String),
+    /// Buffer changed since the edit was created
+    StaleEdit {
+        expected: u32,
+        actual: u32,
+    },
+    /// Edit ranges are reversed or overlap
+    InvalidEdit(String),
+}
+
+impl NeoError {
+    pub fn spawn(cmd: impl Into<String>, source: io::Error) -> Self {
+        Self::Spawn {
+            cmd: cmd.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for NeoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lua(err) => write!(f, "Lua error: {err}"),
+            Self::Db(err) => write!(f, "Database error: {err}"),
+            Self::Io(err) => write!(f, "IO error: {err}"),
+            Self::Spawn { cmd, source } => write!(f, "Can't run command '{cmd}': {source}"),
+            Self::CmdFailed { cmd, stderr } => write!(f, "Command '{cmd}' failed: {stderr}"),
+            Self::MissingKey(key) => write!(f, "Item with key '{key}' doesn't exist"),
+            Self::Locked(name) => write!(f, "{name} is locked"),
+            Self::Cancelled => write!(f, "Task was cancelled"),
+            Self::Rpc(msg) => write!(f, "RPC error: {msg}"),
+            Self::StaleEdit { expected, actual } => {
+                write!(
+                    f,
+                    "Buffer changed, changedtick is {actual} instead of {expected}"
+                )
+            }
+            Self::InvalidEdit(msg) => write!(f, "Invalid edit: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for NeoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Lua(err) => Some(err),
+            Self::Db(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::Spawn { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<LuaError> for NeoError {
+    fn from(err: LuaError) -> Self {
+        Self::Lua(err)
+    }
+}
+
+impl From<sqlx::Error> for NeoError {
+    fn from(err: sqlx::Error) -> Self {
+        Self::Db(err)
+    }
+}
+
+impl From<io::Error> for NeoError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<NeoError> for LuaError {
+    fn from(err: NeoError) -> Self {
+        match err {
+            NeoError::Lua(err) => err,
+            err => LuaError::external(err),
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use mlua::prelude::LuaResult;
+use mlua::Lua;
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::{NeoError, NeoResult, NeoScheduler, RTM};
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type StartJob = Box<dyn FnOnce(CancelToken) -> BoxFuture + Send>;
+
+/// Cheap to clone, all clones share the cancelled state.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<CancelState>);
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Release);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Resolves when the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            // Created before the check, so a cancel in between is not missed
+            let notified = self.0.notify.notified();
+
+            if self.is_cancelled() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+
+    /// Returns `NeoError::Cancelled` when cancelled, use it between steps of a job.
+    pub fn check(&self) -> NeoResult<()> {
+        if self.is_cancelled() {
+            Err(NeoError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JobOpts {
+    /// Jobs run from high to low priority, equal priorities in queue order
+    pub priority: JobPriority,
+    /// A job with a key supersedes queued and running jobs with the same key
+    pub key: Option<&'static str>,
+}
+
+struct QueuedJob {
+    priority: JobPriority,
+    seq: u64,
+    key: Option<&'static str>,
+    token: CancelToken,
+    start: StartJob,
+}
+
+impl PartialEq for QueuedJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.seq == other.seq
+    }
+}
+
+impl Eq for QueuedJob {}
+
+impl PartialOrd for QueuedJob {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedJob {
+    /// Max heap, so the lowest sequence number of the highest priority comes first
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    jobs: BinaryHeap<QueuedJob>,
+    running: Option<(Option<&'static str>, CancelToken)>,
+    debounce: HashMap<&'static str, Duration>,
+    seq: u64,
+    is_running: bool,
+}
+
+impl QueueState {
+    /// Cancelled jobs stay queued, so their callback still receives `NeoError::Cancelled`.
+    fn supersede(&mut self, key: &'static str) {
+        for job in self.jobs.iter().filter(|job| job.key == Some(key)) {
+            job.token.cancel();
+        }
+
+        if let Some((Some(running_key), token)) = &self.running {
+            if *running_key == key {
+                token.cancel();
+            }
+        }
+    }
+}
+
+/// Runs async jobs one at a time on the tokio runtime, results are delivered to a callback.
+///
+/// ```ignore
+/// static JOBS: LazyLock<JobQueue> = LazyLock::new(JobQueue::default);
+///
+/// JOBS.push_lua(
+///     JobOpts { key: Some("grep"), ..Default::default() },
+///     |token| async move { grep(query, token).await },
+///     |lua, result| show_results(lua, result?),
+/// );
+/// ```
+#[derive(Default)]
+pub struct JobQueue {
+    state: Mutex<QueueState>,
+}
+
+impl JobQueue {
+    /// Queues a job, the callback receives the result or `NeoError::Cancelled` when the job
+    /// got superseded or cancelled. The callback runs on the tokio runtime.
+    pub fn push<T, F, Fut, C>(&'static self, opts: JobOpts, job: F, callback: C) -> CancelToken
+    where
+        T: Send + 'static,
+        F: FnOnce(CancelToken) -> Fut + Send + 'static,
+        Fut: Future<Output = NeoResult<T>> + Send + 'static,
+        C: FnOnce(NeoResult<T>) + Send + 'static,
+    {
+        let token = CancelToken::new();
+
+        let start: StartJob = Box::new(move |token: CancelToken| {
+            Box::pin(async move {
+                let result = tokio::select! {
+                    biased;
+                    _ = token.cancelled() => Err(NeoError::Cancelled),
+                    result = job(token.clone()) => result,
+                };
+
+                // Superseded while finishing
+                match result {
+                    Ok(_) if token.is_cancelled() => callback(Err(NeoError::Cancelled)),
+                    result => callback(result),
+                }
+            })
+        });
+
+        let mut state = self.lock();
+
+        if let Some(key) = opts.key {
+            state.supersede(key);
+        }
+
+        state.seq += 1;
+
+        let seq = state.seq;
+
+        state.jobs.push(QueuedJob {
+            priority: opts.priority,
+            seq,
+            key: opts.key,
+            token: token.clone(),
+            start,
+        });
+
+        if !state.is_running {
+            state.is_running = true;
+            RTM.spawn(self.run());
+        }
+
+        token
+    }
+
+    /// Same as `push`, but the callback runs on the Neovim main thread.
+    pub fn push_lua<T, F, Fut, C>(&'static self, opts: JobOpts, job: F, callback: C) -> CancelToken
+    where
+        T: Send + 'static,
+        F: FnOnce(CancelToken) -> Fut + Send + 'static,
+        Fut: Future<Output = NeoResult<T>> + Send + 'static,
+        C: FnOnce(&Lua, NeoResult<T>) -> LuaResult<()> + Send + 'static,
+    {
+        self.push(opts, job, move |result| {
+            NeoScheduler::schedule(move |lua| callback(lua, result));
+        })
+    }
+
+    /// Removes queued jobs with this key and cancels the running one.
+    pub fn cancel(&self, key: &'static str) {
+        self.lock().supersede(key);
+    }
+
+    /// Waits before running a job with this key, a newer job arriving in the meantime
+    /// replaces it. Useful for search queries while typing.
+    pub fn set_debounce(&self, key: &'static str, debounce: Duration) {
+        self.lock().debounce.insert(key, debounce);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    async fn run(&'static self) {
+        loop {
+            let (job, debounce) = {
+                let mut state = self.lock();
+
+                let Some(job) = state.jobs.pop() else {
+                    state.is_running = false;
+                    state.running = None;
+                    return;
+                };
+
+                let debounce = job
+                    .key
+                    .and_then(|key| state.debounce.get(key).copied())
+                    .unwrap_or_default();
+
+                state.running = Some((job.key, job.token.clone()));
+
+                (job, debounce)
+            };
+
+            if !debounce.is_zero() {
+                tokio::select! {
+                    _ = job.token.cancelled() => {}
+                    _ = tokio::time::sleep(debounce) => {}
+                }
+            }
+
+            (job.start)(job.token).await;
+
+            self.lock().running = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc, LazyLock};
+    use tokio::sync::oneshot;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    type JobResult = (&'static str, NeoResult<()>);
+
+    struct Channels {
+        started: mpsc::Sender<&'static str>,
+        results: mpsc::Sender<JobResult>,
+    }
+
+    /// Waits for the returned gate and applies the result after the cancellation check, like
+    /// `Diffuse` does.
+    fn push_search(
+        queue: &'static JobQueue,
+        applied: &'static Mutex<Vec<&'static str>>,
+        key: Option<&'static str>,
+        name: &'static str,
+        channels: &Channels,
+    ) -> oneshot::Sender<()> {
+        let (release, gate) = oneshot::channel::<()>();
+        let started = channels.started.clone();
+        let results = channels.results.clone();
+
+        queue.push(
+            JobOpts {
+                key,
+                ..Default::default()
+            },
+            move |token| async move {
+                started.send(name).unwrap();
+                let _ = gate.await;
+
+                token.check()?;
+                applied.lock().unwrap().push(name);
+
+                Ok(())
+            },
+            move |result| results.send((name, result)).unwrap(),
+        );
+
+        release
+    }
+
+    fn channels() -> (
+        Channels,
+        mpsc::Receiver<&'static str>,
+        mpsc::Receiver<JobResult>,
+    ) {
+        let (started, started_receiver) = mpsc::channel();
+        let (results, results_receiver) = mpsc::channel();
+
+        (
+            Channels { started, results },
+            started_receiver,
+            results_receiver,
+        )
+    }
+
+    fn results(receiver: mpsc::Receiver<JobResult>, count: usize) -> HashMap<&'static str, bool> {
+        (0..count)
+            .map(|_| {
+                let (name, result) = receiver.recv_timeout(TIMEOUT).unwrap();
+                (name, result.is_ok())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn superseded_jobs_never_apply_their_result() {
+        static QUEUE: LazyLock<JobQueue> = LazyLock::new(JobQueue::default);
+        static APPLIED: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+        let (channels, _started, receiver) = channels();
+
+        let gates = [
+            push_search(&QUEUE, &APPLIED, Some("search"), "a", &channels),
+            push_search(&QUEUE, &APPLIED, Some("search"), "ab", &channels),
+            push_search(&QUEUE, &APPLIED, Some("search"), "abc", &channels),
+        ];
+
+        for gate in gates {
+            let _ = gate.send(());
+        }
+
+        let results = results(receiver, 3);
+
+        assert_eq!(*APPLIED.lock().unwrap(), ["abc"]);
+        assert!(!results["a"] && !results["ab"] && results["abc"]);
+    }
+
+    #[test]
+    fn running_job_is_superseded() {
+        static QUEUE: LazyLock<JobQueue> = LazyLock::new(JobQueue::default);
+        static APPLIED: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+        let (channels, started, receiver) = channels();
+
+        let a = push_search(&QUEUE, &APPLIED, Some("search"), "a", &channels);
+        assert_eq!(started.recv_timeout(TIMEOUT), Ok("a"));
+
+        let ab = push_search(&QUEUE, &APPLIED, Some("search"), "ab", &channels);
+
+        let _ = a.send(());
+        ab.send(()).unwrap();
+
+        let results = results(receiver, 2);
+
+        assert_eq!(*APPLIED.lock().unwrap(), ["ab"]);
+        assert!(!results["a"] && results["ab"]);
+    }
+
+    #[test]
+    fn jobs_without_key_are_not_superseded() {
+        static QUEUE: LazyLock<JobQueue> = LazyLock::new(JobQueue::default);
+        static APPLIED: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+        let (channels, started, receiver) = channels();
+
+        let populate = push_search(&QUEUE, &APPLIED, None, "populate", &channels);
+        assert_eq!(started.recv_timeout(TIMEOUT), Ok("populate"));
+
+        let gates = [
+            populate,
+            push_search(&QUEUE, &APPLIED, Some("search"), "a", &channels),
+            push_search(&QUEUE, &APPLIED, Some("search"), "ab", &channels),
+        ];
+
+        for gate in gates {
+            let _ = gate.send(());
+        }
+
+        let results = results(receiver, 3);
+
+        assert_eq!(*APPLIED.lock().unwrap(), ["populate", "ab"]);
+        assert!(results["populate"] && !results["a"] && results["ab"]);
+    }
+}
@@ -1,11 +1,23 @@
 mod bridge;
 mod buffer;
+mod buffer_edit;
+mod color;
+mod config;
 mod database;
 mod debug;
+mod error;
+mod jobs;
+mod log_viewer;
+mod logger;
+mod lua_cache;
 mod neo_api;
 mod neo_api_types;
 mod popup;
+mod scheduler;
 mod search;
+mod tabpage;
+#[cfg(feature = "test-harness")]
+mod testing;
 mod theme;
 mod traits;
 mod utils;
@@ -14,18 +26,31 @@ mod window;
 
 pub use bridge::*;
 pub use buffer::*;
+pub use buffer_edit::*;
+pub use color::*;
+pub use config::*;
 pub use database::*;
 pub use debug::*;
+pub use error::*;
+pub use jobs::*;
+pub use log_viewer::*;
+pub use logger::*;
+pub use lua_cache::*;
 pub use neo_api::*;
 pub use neo_api_types::*;
 pub use popup::*;
+pub use scheduler::*;
 pub use search::*;
+pub use tabpage::*;
+#[cfg(feature = "test-harness")]
+pub use testing::*;
 pub use theme::*;
 pub use traits::*;
 pub use utils::*;
 pub use window::*;
 
 pub use async_trait;
+pub use macros;
 pub use mlua;
 pub use tokio;
 
@@ -34,3 +59,28 @@ use tokio::runtime::Runtime;
 
 /// Tokio runtime multithreaded
 pub static RTM: LazyLock<Runtime> = LazyLock::new(|| tokio::runtime::Runtime::new().unwrap());
+
+/// Exports the module function of `#[neo_plugin]` as `luaopen_<name>`, which needs the `module`
+/// feature of mlua. Without it (e.g. `test-harness`) the function is only called directly.
+#[cfg(feature = "module")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! plugin_module {
+    ($name:ident) => {
+        const _: () = {
+            use $crate::mlua;
+
+            #[mlua::lua_module]
+            fn $name(lua: &mlua::Lua) -> mlua::Result<mlua::Table> {
+                self::$name(lua)
+            }
+        };
+    };
+}
+
+#[cfg(not(feature = "module"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! plugin_module {
+    ($name:ident) => {};
+}
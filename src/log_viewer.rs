@@ -0,0 +1,600 @@
+use mlua::prelude::{LuaFunction, LuaResult};
+use mlua::Lua;
+use std::io::{self, ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use tokio::fs::{self, File};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::{
+    HLOpts, HLText, LogLevel, Mode, NeoApi, NeoBuffer, NeoError, NeoLogger, NeoLuaExt, NeoPopup,
+    NeoResult, NeoScheduler, NeoTheme, PopupAlign, PopupBorder, PopupRelative, PopupSize, TextType,
+    WinCursor, WinOptions, RTM,
+};
+
+const TIMER_ID: &str = "neo_log_viewer";
+const FOLLOW_MS: u32 = 250;
+
+const LEVELS: [LogLevel; 5] = [
+    LogLevel::Trace,
+    LogLevel::Debug,
+    LogLevel::Info,
+    LogLevel::Warn,
+    LogLevel::Error,
+];
+
+static VIEWER: LazyLock<Mutex<LogViewer>> = LazyLock::new(|| Mutex::new(LogViewer::default()));
+
+#[derive(Debug, Clone)]
+struct LogEntry {
+    level: Option<LogLevel>,
+    text: String,
+}
+
+impl LogEntry {
+    /// Parses `<timestamp> <LEVEL> [<plugin>] <message>`, lines which don't match (like
+    /// multiline messages) inherit the level of the previous entry.
+    fn parse(line: &str, prev: Option<&LogEntry>) -> Self {
+        let mut parts = line.split_whitespace();
+        let _timestamp = parts.next();
+
+        let level = parts
+            .next()
+            .and_then(|level| level.parse::<LogLevel>().ok());
+        let tagged = parts
+            .next()
+            .is_some_and(|plugin| plugin.starts_with('[') && plugin.ends_with(']'));
+
+        let level = match (level, tagged) {
+            (Some(level), true) => Some(level),
+            _ => prev.and_then(|prev| prev.level),
+        };
+
+        Self {
+            level,
+            text: line.to_string(),
+        }
+    }
+
+    fn hl_group(&self) -> &'static str {
+        match self.level {
+            Some(LogLevel::Trace) => "NeoLogTrace",
+            Some(LogLevel::Debug) => "NeoLogDebug",
+            Some(LogLevel::Info) => "NeoLogInfo",
+            Some(LogLevel::Warn) => "NeoLogWarn",
+            Some(LogLevel::Error) => "NeoLogError",
+            Some(LogLevel::Off) | None => "Normal",
+        }
+    }
+}
+
+/// Part of a log file, read by a background task
+struct LogRead {
+    file: PathBuf,
+    /// Offset the read started at, reads which don't match the viewer anymore are dropped
+    from: u64,
+    bytes: Vec<u8>,
+    /// File is shorter than the offset because it got cleared or rotated, read from the start
+    truncated: bool,
+    /// `*.log` files in the directory of the log, only listed on a reload
+    files: Option<Vec<PathBuf>>,
+}
+
+impl LogRead {
+    async fn read(file: PathBuf, from: u64, list_files: bool) -> NeoResult<Self> {
+        NeoLogger::flush().await;
+
+        let files = if list_files {
+            Some(log_files(&file).await?)
+        } else {
+            None
+        };
+
+        let mut read = Self {
+            file,
+            from,
+            bytes: vec![],
+            truncated: false,
+            files,
+        };
+
+        let mut handle = match File::open(&read.file).await {
+            Ok(handle) => handle,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(read),
+            Err(err) => return Err(err.into()),
+        };
+
+        read.truncated = handle.metadata().await?.len() < from;
+
+        let start = if read.truncated { 0 } else { from };
+        handle.seek(SeekFrom::Start(start)).await?;
+        handle.read_to_end(&mut read.bytes).await?;
+
+        Ok(read)
+    }
+}
+
+/// The log file first, followed by the logs of other plugins in the same directory
+async fn log_files(file: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![file.to_path_buf()];
+
+    let Some(dir) = file.parent() else {
+        return Ok(files);
+    };
+
+    let mut dir_entries = match fs::read_dir(dir).await {
+        Ok(dir_entries) => dir_entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(files),
+        Err(err) => return Err(err),
+    };
+
+    let mut others = vec![];
+
+    while let Some(dir_entry) = dir_entries.next_entry().await? {
+        let path = dir_entry.path();
+
+        if path != file && path.extension().is_some_and(|ext| ext == "log") {
+            others.push(path);
+        }
+    }
+
+    others.sort();
+    files.extend(others);
+
+    Ok(files)
+}
+
+#[derive(Default)]
+struct LogViewer {
+    popup: Option<NeoPopup>,
+    ns_id: u32,
+    /// Log file which is shown
+    file: PathBuf,
+    /// Log files which `p` cycles through
+    files: Vec<PathBuf>,
+    entries: Vec<LogEntry>,
+    /// Bytes of the log file which have been read
+    offset: u64,
+    /// Unfinished last line of the log file
+    partial: String,
+    min_level: Option<LogLevel>,
+    follow: bool,
+    /// A background read is running, follow ticks are skipped meanwhile
+    reading: bool,
+    /// Amount of lines in the buffer
+    rendered: usize,
+}
+
+impl LogViewer {
+    fn lock() -> LuaResult<MutexGuard<'static, LogViewer>> {
+        VIEWER
+            .try_lock()
+            .map_err(|_| NeoError::Locked("Log viewer").into())
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter().filter(|entry| self.is_visible(entry))
+    }
+
+    fn clear_entries(&mut self) {
+        self.entries.clear();
+        self.partial.clear();
+        self.offset = 0;
+    }
+
+    /// Reads the log in a background task, so the main thread never waits on the disk. The
+    /// result is rendered on the main thread through `NeoScheduler`. A reload reads the whole
+    /// file and lists the log files again.
+    fn read(&mut self, reload: bool) {
+        if reload {
+            self.clear_entries();
+        } else if self.reading {
+            return;
+        }
+
+        self.reading = true;
+
+        let file = self.file.clone();
+        let from = self.offset;
+
+        RTM.spawn(async move {
+            let read = LogRead::read(file, from, reload).await;
+
+            NeoScheduler::schedule(move |lua| {
+                let mut viewer = LogViewer::lock()?;
+                viewer.reading = false;
+
+                match read {
+                    Ok(read) => viewer.apply(lua, read, reload),
+                    Err(err) => NeoApi::notify_level(lua, &err, LogLevel::Error),
+                }
+            });
+        });
+    }
+
+    /// Adds the entries of a background read and renders them.
+    fn apply(&mut self, lua: &Lua, read: LogRead, reload: bool) -> LuaResult<()> {
+        if self.popup.is_none() || read.file != self.file || read.from != self.offset {
+            return Ok(());
+        }
+
+        if let Some(files) = read.files {
+            self.files = files;
+        }
+
+        if read.truncated {
+            self.clear_entries();
+        }
+
+        self.offset += read.bytes.len() as u64;
+
+        let mut text = std::mem::take(&mut self.partial);
+        text.push_str(&String::from_utf8_lossy(&read.bytes));
+
+        if !text.ends_with('\n') {
+            let split = text.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+            self.partial = text.split_off(split);
+        }
+
+        let before = self.entries.len();
+
+        for line in text.lines() {
+            let entry = LogEntry::parse(line, self.entries.last());
+            self.entries.push(entry);
+        }
+
+        let count = self.entries.len() - before;
+
+        if reload || read.truncated {
+            self.render(lua)
+        } else if 0 < count {
+            self.render_new(lua, count)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn status(&self) -> String {
+        let level = self.min_level.map(|level| level.as_str()).unwrap_or("ALL");
+        let file = self.file.file_name().unwrap_or_default().to_string_lossy();
+        let follow = if self.follow { "on" } else { "off" };
+
+        format!(
+            " level: {level}  file: {file}  follow: {follow}  \
+            [l]evel [p] next log [f]ollow [r]eload [c]lear [y]ank [q]uit"
+        )
+    }
+
+    fn is_visible(&self, entry: &LogEntry) -> bool {
+        match (self.min_level, entry.level) {
+            (Some(min), Some(level)) => min as u8 <= level as u8,
+            _ => true,
+        }
+    }
+
+    fn render(&mut self, lua: &Lua) -> LuaResult<()> {
+        let Some(popup) = self.popup else {
+            return Ok(());
+        };
+
+        let buf = popup.buf;
+        let entries: Vec<&LogEntry> = self.visible().collect();
+        let lines: Vec<&str> = entries.iter().map(|entry| entry.text.as_str()).collect();
+
+        buf.set_option_value(lua, "modifiable", true)?;
+        buf.set_lines(lua, 0, -1, false, &lines)?;
+        buf.set_option_value(lua, "modifiable", false)?;
+        buf.set_option_value(lua, "modified", false)?;
+
+        buf.clear_namespace(lua, self.ns_id as i32, 0, -1)?;
+
+        for (line, entry) in entries.iter().enumerate() {
+            buf.add_highlight(lua, self.ns_id as i32, entry.hl_group(), line, 0, -1)?;
+        }
+
+        popup.win.set_option_value(lua, "winbar", self.status())?;
+        self.rendered = lines.len();
+
+        self.scroll_to_end(lua)
+    }
+
+    /// Appends the last `count` entries, if they pass the filters.
+    fn render_new(&mut self, lua: &Lua, count: usize) -> LuaResult<()> {
+        let Some(popup) = self.popup else {
+            return Ok(());
+        };
+
+        if self.rendered == 0 || self.entries.len() < count {
+            return self.render(lua);
+        }
+
+        let start = self.entries.len() - count;
+        let entries: Vec<&LogEntry> = self.entries[start..]
+            .iter()
+            .filter(|entry| self.is_visible(entry))
+            .collect();
+
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let lines: Vec<&str> = entries.iter().map(|entry| entry.text.as_str()).collect();
+        let first = self.rendered;
+
+        popup.buf.set_option_value(lua, "modifiable", true)?;
+        popup
+            .buf
+            .set_lines(lua, first as i32, first as i32, false, &lines)?;
+        popup.buf.set_option_value(lua, "modifiable", false)?;
+        popup.buf.set_option_value(lua, "modified", false)?;
+
+        for (idx, entry) in entries.iter().enumerate() {
+            popup.buf.add_highlight(
+                lua,
+                self.ns_id as i32,
+                entry.hl_group(),
+                first + idx,
+                0,
+                -1,
+            )?;
+        }
+
+        self.rendered += lines.len();
+
+        self.scroll_to_end(lua)
+    }
+
+    fn scroll_to_end(&self, lua: &Lua) -> LuaResult<()> {
+        match self.popup {
+            Some(popup) if self.follow && 0 < self.rendered => popup
+                .win
+                .set_cursor(lua, WinCursor::from_one_indexed(self.rendered as u32, 0)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Interactive viewer of the `NeoLogger` log file.
+///
+/// Every plugin writes its own log, `p` switches to the other `*.log` files in the same
+/// directory.
+///
+/// Keymaps:
+/// * `q` / `<Esc>` close
+/// * `l` cycle minimal level, `p` cycle log file
+/// * `f` toggle follow mode, new entries are appended live
+/// * `r` reload, `c` clear the log
+/// * `y` yank the entry under the cursor, `Y` yank all visible entries
+pub struct NeoLogViewer;
+
+impl NeoLogViewer {
+    pub fn open(lua: &Lua) -> LuaResult<()> {
+        if LogViewer::lock()?.popup.is_some() {
+            Self::close(lua)?;
+        }
+
+        Self::init_hl(lua)?;
+
+        let buf = NeoBuffer::create(lua, false, true)?;
+        buf.set_option_value(lua, "bufhidden", "wipe")?;
+        buf.set_option_value(lua, "readonly", true)?;
+        buf.set_option_value(lua, "modifiable", false)?;
+
+        let popup = NeoPopup::open_win(
+            lua,
+            &buf,
+            true,
+            WinOptions {
+                relative: PopupRelative::Editor,
+                width: Some(PopupSize::Percentage(0.8)),
+                height: Some(PopupSize::Percentage(0.8)),
+                col: Some(PopupSize::Percentage(0.1)),
+                row: Some(PopupSize::Percentage(0.1)),
+                style: None,
+                border: PopupBorder::Rounded,
+                title: Some(TextType::Tuples(vec![HLText::new(" Logs ", "Debug")])),
+                title_pos: PopupAlign::Left,
+                anchor: crate::Anchor::NorthWest,
+                ..Default::default()
+            },
+        )?;
+
+        popup.win.set_option_value(lua, "wrap", false)?;
+        popup.win.set_option_value(lua, "cursorline", true)?;
+
+        NeoScheduler::init(lua)?;
+
+        {
+            let mut viewer = LogViewer::lock()?;
+            viewer.popup = Some(popup);
+            viewer.ns_id = NeoTheme::create_namespace(lua, "NeoLogViewer")?;
+            viewer.file = NeoLogger::log_file();
+            viewer.read(true);
+            viewer.render(lua)?;
+        }
+
+        Self::set_keymaps(lua, buf)
+    }
+
+    pub fn close(lua: &Lua) -> LuaResult<()> {
+        let mut viewer = LogViewer::lock()?;
+
+        if viewer.follow {
+            viewer.follow = false;
+            NeoApi::stop_interval(lua, TIMER_ID)?;
+        }
+
+        if let Some(popup) = viewer.popup.take() {
+            // Buffer gets wiped because of bufhidden=wipe
+            if popup.win.is_valid(lua)? {
+                popup.win.close(lua, true)?;
+            }
+        }
+
+        viewer.clear_entries();
+        viewer.rendered = 0;
+
+        Ok(())
+    }
+
+    fn init_hl(lua: &Lua) -> LuaResult<()> {
+        let groups = [
+            ("NeoLogTrace", "Comment"),
+            ("NeoLogDebug", "DiagnosticHint"),
+            ("NeoLogInfo", "DiagnosticInfo"),
+            ("NeoLogWarn", "DiagnosticWarn"),
+            ("NeoLogError", "DiagnosticError"),
+        ];
+
+        for (group, link) in groups {
+            NeoTheme::set_hl(
+                lua,
+                0,
+                group,
+                HLOpts {
+                    link: Some(link.to_string()),
+                    default: true,
+                    ..Default::default()
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn set_keymaps(lua: &Lua, buf: NeoBuffer) -> LuaResult<()> {
+        let close = lua.create_function(|lua, ()| Self::close(lua))?;
+        buf.set_keymap(lua, Mode::Normal, "q", close.clone())?;
+        buf.set_keymap(lua, Mode::Normal, "<Esc>", close)?;
+
+        let reload = lua.create_function(|_, ()| {
+            LogViewer::lock()?.read(true);
+            Ok(())
+        })?;
+        buf.set_keymap(lua, Mode::Normal, "r", reload)?;
+
+        // Only the log of this plugin can be cleared
+        let clear = lua.create_function(|_, ()| {
+            RTM.spawn(async {
+                let cleared = NeoLogger::clear().await;
+
+                NeoScheduler::schedule(move |lua| match cleared {
+                    Ok(()) => {
+                        let mut viewer = LogViewer::lock()?;
+                        viewer.read(true);
+                        viewer.render(lua)
+                    }
+                    Err(err) => NeoApi::notify_level(lua, &err, LogLevel::Error),
+                });
+            });
+
+            Ok(())
+        })?;
+        buf.set_keymap(lua, Mode::Normal, "c", clear)?;
+
+        let cycle_level = lua.create_function(|lua, ()| {
+            let mut viewer = LogViewer::lock()?;
+
+            viewer.min_level = match viewer.min_level {
+                None => Some(LEVELS[0]),
+                Some(level) => LEVELS
+                    .iter()
+                    .position(|item| *item == level)
+                    .and_then(|idx| LEVELS.get(idx + 1))
+                    .copied(),
+            };
+
+            viewer.render(lua)
+        })?;
+        buf.set_keymap(lua, Mode::Normal, "l", cycle_level)?;
+
+        let cycle_file = lua.create_function(|lua, ()| {
+            let mut viewer = LogViewer::lock()?;
+
+            let next = viewer
+                .files
+                .iter()
+                .position(|file| *file == viewer.file)
+                .map(|idx| (idx + 1) % viewer.files.len())
+                .and_then(|idx| viewer.files.get(idx))
+                .cloned();
+
+            if let Some(next) = next {
+                viewer.file = next;
+                viewer.read(true);
+                viewer.render(lua)?;
+            }
+
+            Ok(())
+        })?;
+        buf.set_keymap(lua, Mode::Normal, "p", cycle_file)?;
+
+        let toggle_follow = lua.create_function(|lua, ()| {
+            let mut viewer = LogViewer::lock()?;
+            viewer.follow = !viewer.follow;
+
+            if viewer.follow {
+                let tail = lua.create_function(follow_tail)?;
+                NeoApi::start_interval(lua, TIMER_ID, FOLLOW_MS, tail)?;
+            } else {
+                NeoApi::stop_interval(lua, TIMER_ID)?;
+            }
+
+            viewer.render(lua)
+        })?;
+        buf.set_keymap(lua, Mode::Normal, "f", toggle_follow)?;
+
+        let yank_entry = lua.create_function(|lua, ()| {
+            let viewer = LogViewer::lock()?;
+
+            let Some(popup) = viewer.popup.as_ref() else {
+                return Ok(());
+            };
+
+            let row = popup.win.get_cursor(lua)?.row_zero_indexed() as usize;
+
+            if let Some(entry) = viewer.visible().nth(row) {
+                set_register(lua, &entry.text)?;
+            }
+
+            Ok(())
+        })?;
+        buf.set_keymap(lua, Mode::Normal, "y", yank_entry)?;
+
+        let yank_all = lua.create_function(|lua, ()| {
+            let viewer = LogViewer::lock()?;
+            let lines: Vec<&str> = viewer.visible().map(|entry| entry.text.as_str()).collect();
+
+            set_register(lua, &lines.join("\n"))
+        })?;
+        buf.set_keymap(lua, Mode::Normal, "Y", yank_all)?;
+
+        Ok(())
+    }
+}
+
+fn follow_tail(lua: &Lua, _: ()) -> LuaResult<()> {
+    // Skip this tick when a keymap is using the viewer
+    let Ok(mut viewer) = VIEWER.try_lock() else {
+        return Ok(());
+    };
+
+    let Some(win) = viewer.popup.as_ref().map(|popup| popup.win) else {
+        return Ok(());
+    };
+
+    if !win.is_valid(lua)? {
+        drop(viewer);
+        return NeoLogViewer::close(lua);
+    }
+
+    viewer.read(false);
+
+    Ok(())
+}
+
+fn set_register(lua: &Lua, text: &str) -> LuaResult<()> {
+    let lfn: LuaFunction = lua.neo_fn("vim.fn.setreg")?;
+
+    lfn.call::<()>(("\"", text))
+}
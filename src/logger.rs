@@ -0,0 +1,482 @@
+use mlua::{prelude::LuaResult, Lua};
+use std::fmt::Display;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{LazyLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, io};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{LogLevel, NeoApi, StdpathType, RTM};
+
+static LOGGER: LazyLock<NeoLogger> = LazyLock::new(NeoLogger::start);
+
+#[derive(Clone, Debug)]
+pub struct LoggerConfig {
+    /// Name of the plugin, is used as file name and as tag on every line
+    pub plugin: String,
+    /// Directory of the log files, defaults to `stdpath("log")`
+    pub dir: Option<PathBuf>,
+    /// Minimal level which gets written
+    pub level: LogLevel,
+    /// File size in bytes after which the log gets rotated
+    pub max_size: u64,
+    /// Amount of rotated files that are kept (`<plugin>.log.1` .. `<plugin>.log.n`)
+    pub max_files: usize,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            plugin: "neo-api-rs".to_string(),
+            dir: None,
+            level: LogLevel::Debug,
+            max_size: 1024 * 1024,
+            max_files: 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct LogTarget {
+    plugin: String,
+    file: PathBuf,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl LogTarget {
+    fn new(config: &LoggerConfig, dir: &Path) -> Self {
+        Self {
+            plugin: config.plugin.clone(),
+            file: dir.join(format!("{}.log", config.plugin)),
+            max_size: config.max_size,
+            max_files: config.max_files,
+        }
+    }
+
+    fn rotated(&self, idx: usize) -> PathBuf {
+        let mut name = self.file.clone().into_os_string();
+        name.push(format!(".{idx}"));
+        name.into()
+    }
+}
+
+enum LogMsg {
+    Record {
+        level: LogLevel,
+        source: Option<String>,
+        message: String,
+        time: SystemTime,
+    },
+    Configure(LogTarget),
+    Clear(oneshot::Sender<io::Result<()>>),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Levelled logger, lines are written by a background task so logging never blocks the
+/// Neovim main thread.
+///
+/// Line format: `2024-05-01T12:00:00.000Z INFO  [plugin] src/file.rs:12: message`
+pub struct NeoLogger {
+    level: AtomicU8,
+    target: RwLock<LogTarget>,
+    sender: mpsc::UnboundedSender<LogMsg>,
+}
+
+impl NeoLogger {
+    fn start() -> Self {
+        let config = LoggerConfig::default();
+        let target = LogTarget::new(&config, &env::temp_dir().join("neo-api-rs"));
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        RTM.spawn(LogWriter::new(target.clone()).run(receiver));
+
+        Self {
+            level: AtomicU8::new(config.level as u8),
+            target: RwLock::new(target),
+            sender,
+        }
+    }
+
+    /// Configures the logger, when no directory is set the log is written to
+    /// `stdpath("log")`, falling back to the temp directory.
+    pub fn init(lua: &Lua, config: LoggerConfig) -> LuaResult<()> {
+        let dir = match &config.dir {
+            Some(dir) => dir.clone(),
+            None => NeoApi::stdpath(lua, StdpathType::Log)
+                .unwrap_or_else(|_| env::temp_dir().join("neo-api-rs")),
+        };
+
+        let target = LogTarget::new(&config, &dir);
+
+        Self::set_level(config.level);
+
+        if let Ok(mut current) = LOGGER.target.write() {
+            *current = target.clone();
+        }
+
+        let _ = LOGGER.sender.send(LogMsg::Configure(target));
+
+        Ok(())
+    }
+
+    pub fn level() -> LogLevel {
+        LogLevel::from_u8(LOGGER.level.load(Ordering::Relaxed))
+    }
+
+    pub fn set_level(level: LogLevel) {
+        LOGGER.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    pub fn enabled(level: LogLevel) -> bool {
+        level != LogLevel::Off && level as u8 >= LOGGER.level.load(Ordering::Relaxed)
+    }
+
+    /// Path of the active log file.
+    pub fn log_file() -> PathBuf {
+        match LOGGER.target.read() {
+            Ok(target) => target.file.clone(),
+            Err(_) => env::temp_dir().join("neo-api-rs/neo-api-rs.log"),
+        }
+    }
+
+    pub fn plugin() -> String {
+        match LOGGER.target.read() {
+            Ok(target) => target.plugin.clone(),
+            Err(_) => String::new(),
+        }
+    }
+
+    pub fn write(level: LogLevel, source: Option<String>, message: impl Display) {
+        if !Self::enabled(level) {
+            return;
+        }
+
+        let _ = LOGGER.sender.send(LogMsg::Record {
+            level,
+            source,
+            message: message.to_string(),
+            time: SystemTime::now(),
+        });
+    }
+
+    #[track_caller]
+    pub fn write_here(level: LogLevel, message: impl Display) {
+        let caller = Location::caller();
+        Self::write(
+            level,
+            Some(format!("{}:{}", caller.file(), caller.line())),
+            message,
+        );
+    }
+
+    /// Waits until all queued lines are written.
+    pub async fn flush() {
+        let (tx, rx) = oneshot::channel();
+
+        if LOGGER.sender.send(LogMsg::Flush(tx)).is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    /// Truncates the active log file, rotated files are removed.
+    pub async fn clear() -> io::Result<()> {
+        let (tx, rx) = oneshot::channel();
+
+        if LOGGER.sender.send(LogMsg::Clear(tx)).is_err() {
+            return Err(io::Error::other("Log writer has stopped"));
+        }
+
+        rx.await
+            .unwrap_or_else(|_| Err(io::Error::other("Log writer has stopped")))
+    }
+}
+
+struct LogWriter {
+    target: LogTarget,
+    file: Option<File>,
+    size: u64,
+}
+
+impl LogWriter {
+    fn new(target: LogTarget) -> Self {
+        Self {
+            target,
+            file: None,
+            size: 0,
+        }
+    }
+
+    async fn run(mut self, mut receiver: mpsc::UnboundedReceiver<LogMsg>) {
+        while let Some(msg) = receiver.recv().await {
+            match msg {
+                LogMsg::Record {
+                    level,
+                    source,
+                    message,
+                    time,
+                } => {
+                    let line = format_line(&self.target.plugin, level, source, &message, time);
+                    let _ = self.write(line.as_bytes()).await;
+                }
+                LogMsg::Configure(target) => {
+                    self.target = target;
+                    self.file = None;
+                }
+                LogMsg::Clear(tx) => {
+                    let _ = tx.send(self.clear().await);
+                }
+                LogMsg::Flush(tx) => {
+                    if let Some(file) = self.file.as_mut() {
+                        let _ = file.flush().await;
+                    }
+
+                    let _ = tx.send(());
+                }
+            }
+        }
+    }
+
+    async fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            if let Some(dir) = self.target.file.parent() {
+                fs::create_dir_all(dir).await?;
+            }
+
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.target.file)
+                .await?;
+
+            self.size = file.metadata().await?.len();
+            self.file = Some(file);
+        }
+
+        Ok(self.file.as_mut().expect("Log file is opened"))
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.open().await?;
+
+        if 0 < self.size && self.target.max_size < self.size + bytes.len() as u64 {
+            self.rotate().await?;
+        }
+
+        let file = self.open().await?;
+        file.write_all(bytes).await?;
+        self.size += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush().await?;
+        }
+
+        if self.target.max_files == 0 {
+            return fs::remove_file(&self.target.file).await;
+        }
+
+        for idx in (1..self.target.max_files).rev() {
+            let from = self.target.rotated(idx);
+
+            if fs::try_exists(&from).await.unwrap_or(false) {
+                fs::rename(from, self.target.rotated(idx + 1)).await?;
+            }
+        }
+
+        fs::rename(&self.target.file, self.target.rotated(1)).await
+    }
+
+    async fn clear(&mut self) -> io::Result<()> {
+        self.file = None;
+
+        for idx in 1..=self.target.max_files {
+            let _ = fs::remove_file(self.target.rotated(idx)).await;
+        }
+
+        fs::write(&self.target.file, b"").await?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+fn format_line(
+    plugin: &str,
+    level: LogLevel,
+    source: Option<String>,
+    message: &str,
+    time: SystemTime,
+) -> String {
+    let ts = format_timestamp(time);
+    let level = level.as_str();
+
+    match source {
+        Some(source) => format!("{ts} {level:5} [{plugin}] {source}: {message}\n"),
+        None => format!("{ts} {level:5} [{plugin}] {message}\n"),
+    }
+}
+
+/// Formats as RFC 3339 in UTC, e.g. `2024-05-01T12:00:00.000Z`.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let millis = since_epoch.subsec_millis();
+
+    let days = (secs / 86_400) as i64;
+    let secs_of_day = secs % 86_400;
+
+    // Civil from days (Howard Hinnant)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{millis:03}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+    )
+}
+
+#[cfg(feature = "tracing")]
+mod tracing_layer {
+    use super::NeoLogger;
+    use crate::LogLevel;
+    use std::fmt::Write;
+    use std::time::Instant;
+    use tracing::{field::Field, span, Event, Subscriber};
+    use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+    /// `tracing` layer which writes events to the `NeoLogger`, closed spans are logged with
+    /// their duration.
+    ///
+    /// ```ignore
+    /// tracing_subscriber::registry().with(NeoTracingLayer).init();
+    /// ```
+    pub struct NeoTracingLayer;
+
+    struct SpanStart(Instant);
+
+    #[derive(Default)]
+    struct FieldVisitor {
+        text: String,
+        /// Duration measured before the span was created, see `NeoDebug::log_duration`
+        elapsed_ms: Option<u64>,
+    }
+
+    impl tracing::field::Visit for FieldVisitor {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "elapsed_ms" {
+                self.elapsed_ms = Some(value);
+            } else {
+                self.record_debug(field, &value);
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                let _ = write!(self.text, "{value:?} ");
+            } else {
+                let _ = write!(self.text, "{}={value:?} ", field.name());
+            }
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "message" {
+                let _ = write!(self.text, "{value} ");
+            } else {
+                let _ = write!(self.text, "{}={value} ", field.name());
+            }
+        }
+    }
+
+    fn level(level: &tracing::Level) -> LogLevel {
+        match *level {
+            tracing::Level::TRACE => LogLevel::Trace,
+            tracing::Level::DEBUG => LogLevel::Debug,
+            tracing::Level::INFO => LogLevel::Info,
+            tracing::Level::WARN => LogLevel::Warn,
+            tracing::Level::ERROR => LogLevel::Error,
+        }
+    }
+
+    fn source(meta: &tracing::Metadata<'_>) -> String {
+        match (meta.file(), meta.line()) {
+            (Some(file), Some(line)) => format!("{file}:{line}"),
+            _ => meta.target().to_string(),
+        }
+    }
+
+    impl<S> Layer<S> for NeoTracingLayer
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+            if let Some(span) = ctx.span(id) {
+                let mut fields = FieldVisitor::default();
+                attrs.record(&mut fields);
+
+                let mut extensions = span.extensions_mut();
+                extensions.insert(SpanStart(Instant::now()));
+                extensions.insert(fields);
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let meta = event.metadata();
+            let level = level(meta.level());
+
+            if !NeoLogger::enabled(level) {
+                return;
+            }
+
+            let mut fields = FieldVisitor::default();
+            event.record(&mut fields);
+
+            NeoLogger::write(level, Some(source(meta)), fields.text.trim_end());
+        }
+
+        fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+            let Some(span) = ctx.span(&id) else {
+                return;
+            };
+
+            let meta = span.metadata();
+            let level = level(meta.level());
+            let extensions = span.extensions();
+
+            if let Some(SpanStart(start)) = extensions.get::<SpanStart>() {
+                let fields = extensions.get::<FieldVisitor>();
+                let text = fields
+                    .map(|fields| fields.text.trim_end())
+                    .unwrap_or_default();
+                let elapsed_ms = fields
+                    .and_then(|fields| fields.elapsed_ms)
+                    .unwrap_or(start.elapsed().as_millis() as u64);
+
+                NeoLogger::write(
+                    level,
+                    Some(source(meta)),
+                    format!("{} {text} took {elapsed_ms}ms", meta.name()),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub use tracing_layer::NeoTracingLayer;
@@ -0,0 +1,75 @@
+use mlua::prelude::{LuaError, LuaFunction, LuaRegistryKey, LuaResult, LuaValue};
+use mlua::{FromLua, Lua};
+use std::collections::HashMap;
+
+/// Resolved `vim.api` and `vim.fn` functions of a Lua state, stored as app data.
+#[derive(Default)]
+struct NeoFnCache(HashMap<&'static str, LuaRegistryKey>);
+
+/// Resolves Lua functions by path (e.g. `vim.api.nvim_buf_set_lines`) without compiling a
+/// chunk for every call.
+pub trait NeoLuaExt {
+    /// Functions under `vim.api` and `vim.fn` are resolved once per Lua state and then kept in
+    /// the registry. Other paths like `vim.notify` are commonly overridden by plugins, so these
+    /// are looked up on every call.
+    fn neo_fn(&self, path: &'static str) -> LuaResult<LuaFunction>;
+
+    /// Looks up any value by path, this is never cached.
+    fn neo_get<V: FromLua>(&self, path: &str) -> LuaResult<V>;
+
+    /// Drops all cached functions, e.g. after replacing the `vim` global.
+    fn clear_neo_fns(&self);
+}
+
+impl NeoLuaExt for Lua {
+    fn neo_fn(&self, path: &'static str) -> LuaResult<LuaFunction> {
+        if !path.starts_with("vim.api.") && !path.starts_with("vim.fn.") {
+            return self.neo_get(path);
+        }
+
+        if let Some(cache) = self.app_data_ref::<NeoFnCache>() {
+            if let Some(key) = cache.0.get(path) {
+                return self.registry_value(key);
+            }
+        }
+
+        let lfn: LuaFunction = self.neo_get(path)?;
+        let key = self.create_registry_value(lfn.clone())?;
+
+        if let Some(mut cache) = self.app_data_mut::<NeoFnCache>() {
+            cache.0.insert(path, key);
+            return Ok(lfn);
+        }
+
+        let mut cache = NeoFnCache::default();
+        cache.0.insert(path, key);
+        self.set_app_data(cache);
+
+        Ok(lfn)
+    }
+
+    fn neo_get<V: FromLua>(&self, path: &str) -> LuaResult<V> {
+        let mut value = LuaValue::Table(self.globals());
+
+        for segment in path.split('.') {
+            value = match value {
+                LuaValue::Table(table) => table.get(segment)?,
+                _ => {
+                    return Err(LuaError::runtime(format!(
+                        "Can't resolve {path}, {segment} has no parent table"
+                    )))
+                }
+            };
+        }
+
+        V::from_lua(value, self)
+    }
+
+    fn clear_neo_fns(&self) {
+        if let Some(cache) = self.remove_app_data::<NeoFnCache>() {
+            for key in cache.0.into_values() {
+                let _ = self.remove_registry_value(key);
+            }
+        }
+    }
+}
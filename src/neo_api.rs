@@ -1,9 +1,13 @@
 use crate::neo_api_types::{
-    AutoCmd, AutoCmdEvent, AutoCmdOpts, ExtmarkOpts, LogLevel, Mode, OpenIn, OptValueType,
-    StdpathType, Ui,
+    AutoCmd, AutoCmdEvent, AutoCmdInfo, AutoCmdOpts, ClearAutoCmdOpts, ExecAutoCmdOpts,
+    ExtmarkOpts, GetAutoCmdOpts, LogLevel, Mode, OpenIn, OptValueType, OptionInfo, StdpathType,
+    Ui, UserCommandArgs, UserCommandComplete, UserCommandOpts, VarScope,
 };
-use crate::{BufInfo, BufInfoOpts, CmdOpts, FileTypeMatch, KeymapOpts, NeoDebug};
-use crate::{NeoWindow, RTM};
+use crate::{
+    BufInfo, BufInfoOpts, CmdExecOpts, CmdNargs, CmdOpts, FileTypeMatch, KeymapInfo, KeymapOpts,
+    KeymapRhs, LoggerConfig, NeoDebug, NeoLogViewer, NeoLogger,
+};
+use crate::{NeoBuffer, NeoLuaExt, NeoScheduler, NeoWindow, RTM};
 use mlua::{
     prelude::{LuaFunction, LuaResult, LuaTable, LuaValue},
     FromLua, IntoLua, Lua,
@@ -18,45 +22,121 @@ impl NeoApi {
     pub fn init(lua: &Lua) -> LuaResult<()> {
         //DevIcon::init(lua)?;
 
-        let cb = lua.create_async_function(|lua, ()| async {
-            RTM.block_on(NeoDebug::display(lua));
+        NeoScheduler::init(lua)?;
+
+        // Keeps the plugin name of `#[neo_plugin]`
+        NeoLogger::init(
+            lua,
+            LoggerConfig {
+                plugin: NeoLogger::plugin(),
+                ..Default::default()
+            },
+        )?;
+
+        let cb = lua.create_function(|lua, ()| NeoLogViewer::open(lua))?;
+
+        Self::create_user_command(lua, "NeoApiShowLogs", cb, UserCommandOpts::default())?;
+
+        let cb = lua.create_function(|_, ()| {
+            RTM.spawn(async {
+                if let Err(err) = NeoDebug::clear_logs().await {
+                    NeoScheduler::schedule(move |lua| {
+                        Self::notify_level(lua, &err, LogLevel::Error)
+                    });
+                }
+            });
 
             Ok(())
         })?;
 
-        Self::create_user_command(lua, "NeoApiShowLogs", cb, false)?;
+        Self::create_user_command(lua, "NeoApiClearLogs", cb, UserCommandOpts::default())?;
+
+        let cb = lua.create_function(|lua, args: UserCommandArgs| {
+            if args.args.is_empty() {
+                return Self::notify(lua, &NeoLogger::level());
+            }
 
-        let cb = lua.create_async_function(|lua, ()| async {
-            RTM.block_on(NeoDebug::clear_logs());
+            match args.args.parse::<LogLevel>() {
+                Ok(level) => NeoLogger::set_level(level),
+                Err(err) => Self::notify_level(lua, &err, LogLevel::Error)?,
+            }
 
             Ok(())
         })?;
 
-        Self::create_user_command(lua, "NeoApiClearLogs", cb, false)
+        let complete = UserCommandComplete::from_fn(lua, |_, args| {
+            let levels = ["trace", "debug", "info", "warn", "error", "off"];
+
+            Ok(levels
+                .into_iter()
+                .filter(|level| level.starts_with(&args.arg_lead))
+                .map(String::from)
+                .collect())
+        })?;
+
+        Self::create_user_command(
+            lua,
+            "NeoApiLogLevel",
+            cb,
+            UserCommandOpts {
+                nargs: Some(CmdNargs::ZeroOrOne),
+                complete: Some(complete),
+                desc: Some("Show or set the log level".to_string()),
+                ..Default::default()
+            },
+        )
     }
 
+    /**
+    Creates a global |user-commands| command, or a buffer-local one when
+    `opts.buffer` is set.
+
+    The callback receives a table which can be read as `UserCommandArgs`.
+
+    Parameters: ~
+      • {name}     Name of the new user command. Must begin with an uppercase
+                   letter.
+      • {command}  Lua function that is executed when the command is invoked.
+      • {opts}     Optional |command-attributes|.
+    */
     pub fn create_user_command(
         lua: &Lua,
         name: &str,
         callback: LuaFunction,
-        bang: bool,
+        opts: UserCommandOpts,
     ) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_create_user_command").eval()?;
+        if let Some(buf) = opts.buffer {
+            let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_create_user_command")?;
 
-        let opts = lua.create_table()?;
-        opts.set("bang", bang)?;
+            lfn.call((buf.id(), name, callback, opts))
+        } else {
+            let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_create_user_command")?;
 
-        lfn.call((name, callback, opts))
+            lfn.call((name, callback, opts))
+        }
+    }
+
+    /// Delete a user-defined command, pass a buffer to delete a buffer-local command.
+    pub fn del_user_command(lua: &Lua, name: &str, buffer: Option<NeoBuffer>) -> LuaResult<()> {
+        if let Some(buf) = buffer {
+            let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_del_user_command")?;
+
+            lfn.call((buf.id(), name))
+        } else {
+            let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_del_user_command")?;
+
+            lfn.call(name)
+        }
     }
 
     pub fn delay(lua: &Lua, ms: u32, callback: LuaFunction) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.defer_fn").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.defer_fn")?;
 
         lfn.call((callback, ms))
     }
 
     pub fn schedule_wrap(lua: &Lua, callback: LuaFunction) -> LuaResult<LuaFunction> {
-        let lfn: LuaFunction = lua.load("vim.schedule_wrap").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.schedule_wrap")?;
 
         lfn.call(callback)
     }
@@ -69,11 +149,11 @@ impl NeoApi {
         ms: u32,
         callback: LuaFunction,
     ) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.uv.new_timer").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.uv.new_timer")?;
 
         let timer: LuaValue = lfn.call(())?;
 
-        let lfn: LuaFunction = lua.load("vim.uv.timer_start").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.uv.timer_start")?;
 
         let callback = Self::schedule_wrap(lua, callback)?;
 
@@ -86,7 +166,7 @@ impl NeoApi {
     pub fn stop_interval(lua: &Lua, timer_id: &str) -> LuaResult<()> {
         let timer: LuaValue = lua.globals().get(format!("neo_timer_{timer_id}"))?;
 
-        let lfn: LuaFunction = lua.load("vim.uv.timer_stop").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.uv.timer_stop")?;
 
         lfn.call(timer)
     }
@@ -102,13 +182,13 @@ impl NeoApi {
       • {msg}    Content of the notification to show to the user.
     */
     pub fn notify(lua: &Lua, display: &impl fmt::Display) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.notify").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.notify")?;
 
         lfn.call(display.to_string())
     }
 
     pub fn get_current_line(lua: &Lua) -> LuaResult<String> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_get_current_line").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_current_line")?;
 
         lfn.call(())
     }
@@ -124,7 +204,7 @@ impl NeoApi {
       • {msg}    Content of the notification to show to the user.
     */
     pub fn notify_dbg(lua: &mlua::Lua, debug: &impl fmt::Debug) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.notify").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.notify")?;
 
         lfn.call(format!("{debug:?}"))
     }
@@ -141,14 +221,14 @@ impl NeoApi {
       • {level}  A log level
     */
     pub fn notify_level(lua: &Lua, display: &impl fmt::Display, level: LogLevel) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.notify").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.notify")?;
 
         lfn.call((display.to_string(), level as usize))
     }
 
     /// Gets a human-readable representation of the given object.
     pub fn inspect<V: IntoLua>(lua: &Lua, table: V) -> LuaResult<()> {
-        let ltb: LuaTable = lua.load("vim.inspect").eval()?;
+        let ltb: LuaTable = lua.neo_get("vim.inspect")?;
 
         let lfn: LuaFunction = ltb.get("inspect")?;
         let result: String = lfn.call(table)?;
@@ -178,16 +258,9 @@ impl NeoApi {
         value: V,
         opt_type: OptValueType,
     ) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_set_option_value").eval()?;
-
-        let opts = lua.create_table()?;
-
-        match opt_type {
-            OptValueType::Window(window) => opts.set("win", window.id())?,
-            OptValueType::Buffer(buffer) => opts.set("buf", buffer.id())?,
-        }
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_set_option_value")?;
 
-        lfn.call((key, value, opts))
+        lfn.call((key, value, opt_type))
     }
 
     pub fn get_option_value<V: FromLua>(
@@ -195,16 +268,70 @@ impl NeoApi {
         key: &str,
         opt_type: OptValueType,
     ) -> LuaResult<V> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_get_option_value").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_option_value")?;
 
-        let opts = lua.create_table()?;
+        lfn.call((key, opt_type))
+    }
 
-        match opt_type {
-            OptValueType::Window(window) => opts.set("win", window.id())?,
-            OptValueType::Buffer(buffer) => opts.set("buf", buffer.id())?,
-        }
+    /**
+    Gets the option information for one option from arbitrary buffer or window
+
+    Resulting dictionary has keys:
+    • name: Name of the option (like 'filetype')
+    • shortname: Shortened name of the option (like 'ft')
+    • type: type of option ("string", "number" or "boolean")
+    • default: The default value for the option
+    • was_set: Whether the option was set.
+    • last_set_sid: Last set script id (if any)
+    • last_set_linenr: line number where option was set
+    • last_set_chan: Channel where option was set (0 for local)
+    • scope: one of "global", "win", or "buf"
+    • global_local: whether win or buf option has a global value
+    • commalist: List of comma separated values
+    • flaglist: List of single char flags
+
+    When {scope} is not provided, the last set information applies to the
+    local value in the current buffer or window if it is available, otherwise
+    the global value information is returned. This behavior can be disabled by
+    explicitly specifying {scope} in the {opts} table.
+
+    Parameters: ~
+      • {name}  Option name
+      • {opts}  Optional parameters
+                • scope: One of "global" or "local". Analogous to
+                  |:setglobal| and |:setlocal|, respectively.
+                • win: |window-ID|. Used for getting window local options.
+                • buf: Buffer number. Used for getting buffer local options.
+                  Implies {scope} is "local".
+    */
+    pub fn get_option_info(
+        lua: &Lua,
+        name: &str,
+        opt_type: OptValueType,
+    ) -> LuaResult<OptionInfo> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_option_info2")?;
+
+        lfn.call((name, opt_type))
+    }
+
+    /// Gets a variable of the scope, read it as `Option<V>` when it may not exist
+    pub fn get_var<V: FromLua>(lua: &Lua, scope: VarScope, name: &str) -> LuaResult<V> {
+        scope.table(lua)?.get(name)
+    }
+
+    /// Sets a variable of the scope, tables are copied into Vimscript
+    pub fn set_var<V: IntoLua>(
+        lua: &Lua,
+        scope: VarScope,
+        name: &str,
+        value: V,
+    ) -> LuaResult<()> {
+        scope.table(lua)?.set(name, value)
+    }
 
-        lfn.call((key, opts))
+    /// Removes a variable of the scope, does nothing when it doesn't exist
+    pub fn del_var(lua: &Lua, scope: VarScope, name: &str) -> LuaResult<()> {
+        scope.table(lua)?.set(name, LuaValue::Nil)
     }
 
     /**
@@ -225,26 +352,26 @@ impl NeoApi {
     of this function. Example: >lua
     */
     pub fn filetype_match(lua: &Lua, opts: FileTypeMatch) -> LuaResult<Option<String>> {
-        let lfn: LuaFunction = lua.load("vim.filetype.match").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.filetype.match")?;
 
         lfn.call(opts)
     }
 
     pub fn get_current_win(lua: &Lua) -> LuaResult<NeoWindow> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_get_current_win").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_current_win")?;
         let win_id = lfn.call(())?;
 
         Ok(NeoWindow::new(win_id))
     }
 
     pub fn get_buf_info(lua: &Lua, opts: BufInfoOpts) -> LuaResult<Vec<BufInfo>> {
-        let lfn: LuaFunction = lua.load("vim.fn.getbufinfo").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.fn.getbufinfo")?;
 
         lfn.call(opts)
     }
 
     pub fn set_current_buf(lua: &Lua, buf_id: u32) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_set_current_buf").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_set_current_buf")?;
 
         lfn.call(buf_id)
     }
@@ -256,7 +383,7 @@ impl NeoApi {
     &emsp; not allowed when |textlock| is active or in the |cmdwin|
     */
     pub fn set_current_win(lua: &Lua, win_id: u32) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_set_current_win").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_set_current_win")?;
 
         lfn.call(win_id)
     }
@@ -266,65 +393,113 @@ impl NeoApi {
         let table = lua.create_table()?;
         table.set("bang", bang);
 
-        let lfn: LuaFunction = lua.load("vim.cmd.enew").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.cmd.enew")?;
 
         lfn.call(table)
     }
 
     pub fn stop_lsp(lua: &Lua) -> LuaResult<()> {
-        let lfn_gc: LuaFunction = lua.load("vim.lsp.get_clients").eval()?;
-        let lfn_sc: LuaFunction = lua.load("vim.lsp.stop_client").eval()?;
+        let lfn_gc: LuaFunction = lua.neo_fn("vim.lsp.get_clients")?;
+        let lfn_sc: LuaFunction = lua.neo_fn("vim.lsp.stop_client")?;
 
         let clients: LuaValue = lfn_gc.call(())?;
 
         lfn_sc.call(clients)
     }
 
-    pub fn cmd(lua: &Lua, opts: CmdOpts<'_>) -> LuaResult<()> {
-        let cmd = format!("vim.cmd.{}", opts.cmd);
-        let lfn: LuaFunction = lua.load(cmd).eval()?;
+    /// Executes an Ex command, see `exec_cmd` to capture the output.
+    pub fn cmd(lua: &Lua, opts: CmdOpts) -> LuaResult<()> {
+        Self::exec_cmd(lua, opts, CmdExecOpts::default())?;
 
-        lfn.call(opts)
+        Ok(())
+    }
+
+    /**
+    Executes an Ex command.
+
+    Unlike |nvim_command()| this command takes a structured Dictionary instead
+    of a String. This allows for easier construction and manipulation of an Ex
+    command. This also allows for things such as having spaces inside a
+    command argument, expanding filenames in a command that otherwise doesn't
+    expand filenames, etc. Command arguments may also be Number, Boolean or
+    String.
+
+    On execution error: fails with Vimscript error, updates v:errmsg.
+
+    Parameters: ~
+      • {cmd}   Command to execute. Must be a Dictionary that can contain the
+                same values as the return value of |nvim_parse_cmd()| except
+                "addr", "nargs" and "nextcmd" which are ignored if provided.
+                All values except for "cmd" are optional.
+      • {opts}  Optional parameters.
+                • output: (boolean, default false) Whether to return command
+                  output.
+
+    Return: ~
+        Command output (non-error, non-shell |:!|) if `output` is true, else
+        empty string.
+    */
+    pub fn exec_cmd(lua: &Lua, opts: CmdOpts, exec_opts: CmdExecOpts) -> LuaResult<String> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_cmd")?;
+
+        lfn.call((opts, exec_opts))
+    }
+
+    /**
+    Parse command line.
+
+    Doesn't check the validity of command arguments.
+
+    Attributes: ~
+        |api-fast|
+
+    Parameters: ~
+      • {str}   Command line string to parse. Cannot contain "\n".
+    */
+    pub fn parse_cmd(lua: &Lua, cmd: &str) -> LuaResult<CmdOpts> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_parse_cmd")?;
+
+        lfn.call((cmd, lua.create_table()?))
     }
 
     pub fn open_file(lua: &Lua, open_in: OpenIn, path: &str) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load(format!("vim.cmd.{open_in}")).eval()?;
+        let lfn: LuaFunction = lua.neo_get(&format!("vim.cmd.{open_in}"))?;
 
         lfn.call(path)
     }
 
     pub fn set_cwd(lua: &Lua, path: &Path) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_set_current_dir").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_set_current_dir")?;
 
         lfn.call(path.to_string_lossy().to_string())
     }
 
     pub fn get_cwd(lua: &Lua) -> LuaResult<PathBuf> {
-        let lfn: LuaFunction = lua.load("vim.fn.getcwd").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.fn.getcwd")?;
 
         Ok(lfn.call::<String>(())?.into())
     }
 
     pub fn get_filepath(lua: &Lua) -> LuaResult<PathBuf> {
-        let lfn: LuaFunction = lua.load("vim.fn.expand").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.fn.expand")?;
 
         Ok(lfn.call::<String>("%:p")?.into())
     }
 
     pub fn get_filename(lua: &Lua) -> LuaResult<String> {
-        let lfn: LuaFunction = lua.load("vim.fn.expand").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.fn.expand")?;
 
         lfn.call::<String>("%:p:t")
     }
 
     pub fn get_filedir(lua: &Lua) -> LuaResult<PathBuf> {
-        let lfn: LuaFunction = lua.load("vim.fn.expand").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.fn.expand")?;
 
         Ok(lfn.call::<String>("%:p:h")?.into())
     }
 
     pub fn set_cmd_file(lua: &Lua, name: impl Into<String>) -> LuaResult<()> {
-        let lfn: mlua::Function = lua.load("vim.cmd.file").eval()?;
+        let lfn: mlua::Function = lua.neo_fn("vim.cmd.file")?;
 
         lfn.call(name.into())
     }
@@ -343,13 +518,13 @@ impl NeoApi {
     state         String   Session state directory: storage for file drafts, swap, undo, |shada|.
     */
     pub fn stdpath(lua: &Lua, stdpath: StdpathType) -> LuaResult<PathBuf> {
-        let lfn: LuaFunction = lua.load("vim.fn.stdpath").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.fn.stdpath")?;
 
         Ok(lfn.call::<String>(stdpath.to_string())?.into())
     }
 
     pub fn list_uis(lua: &Lua) -> LuaResult<Vec<Ui>> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_list_uis").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_list_uis")?;
 
         lfn.call(())
     }
@@ -386,26 +561,83 @@ impl NeoApi {
         col: u32,
         opts: ExtmarkOpts,
     ) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_buf_set_extmark").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_buf_set_extmark")?;
         let opts: LuaValue = opts.into_lua(lua)?;
 
         lfn.call((buf_id, ns_id, line, col, opts))
     }
 
+    /**
+    Adds a new |mapping|.
+
+    Parameters: ~
+      • {modes}  Mode short-names, see |nvim_set_keymap()|.
+      • {lhs}    Left-hand side |{lhs}| of the mapping.
+      • {rhs}    Right-hand side |{rhs}| of the mapping, can be a Lua function or keys.
+      • {opts}   Table of |:map-arguments|.
+    */
     pub fn set_keymap(
         lua: &Lua,
-        mode: Mode,
+        modes: &[Mode],
+        lhs: &str,
+        rhs: impl Into<KeymapRhs>,
+        mut keymap_opts: KeymapOpts,
+    ) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.keymap.set")?;
+
+        if keymap_opts.remap.is_none() {
+            keymap_opts.remap = keymap_opts.noremap.map(|noremap| !noremap);
+        }
+
+        let modes: Vec<&str> = modes.iter().map(Mode::get_str).collect();
+
+        lfn.call((modes, lhs, rhs.into(), keymap_opts))
+    }
+
+    /**
+    Remove an existing mapping.
+
+    Parameters: ~
+      • {modes}   Mode short-names
+      • {lhs}     Left-hand side |{lhs}| of the mapping.
+      • {buffer}  Remove a mapping from the given buffer.
+    */
+    pub fn del_keymap(
+        lua: &Lua,
+        modes: &[Mode],
         lhs: &str,
-        rhs: LuaFunction,
-        keymap_opts: KeymapOpts,
+        buffer: Option<NeoBuffer>,
     ) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.keymap.set").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.keymap.del")?;
+
+        let modes: Vec<&str> = modes.iter().map(Mode::get_str).collect();
+        let opts = lua.create_table()?;
+
+        if let Some(buf) = buffer {
+            opts.set("buffer", buf.id())?;
+        }
+
+        lfn.call((modes, lhs, opts))
+    }
+
+    /**
+    Gets a list of global (non-buffer-local) |mapping| definitions.
+
+    Parameters: ~
+      • {mode}  Mode short-name ("n", "i", "v", ...)
+
+    Return: ~
+        Array of |maparg()|-like dictionaries describing mappings. The
+        "buffer" key is always zero.
+    */
+    pub fn get_keymaps(lua: &Lua, mode: Mode) -> LuaResult<Vec<KeymapInfo>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_keymap")?;
 
-        lfn.call((mode.get_str(), lhs, rhs, keymap_opts))
+        lfn.call(mode.get_str())
     }
 
     pub fn create_augroup(lua: &Lua, name: &str, clear: bool) -> LuaResult<u32> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_create_augroup").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_create_augroup")?;
 
         let opts = lua.create_table()?;
         opts.set("clear", clear)?;
@@ -414,7 +646,7 @@ impl NeoApi {
     }
 
     pub fn del_augroup_by_name(lua: &Lua, name: &str) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_del_augroup_by_name").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_del_augroup_by_name")?;
 
         lfn.call(name)
     }
@@ -425,7 +657,7 @@ impl NeoApi {
         events: &[AutoCmdEvent],
         opts: AutoCmdOpts,
     ) -> LuaResult<AutoCmd> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_create_autocmd").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_create_autocmd")?;
 
         let events: Vec<String> = events.iter().map(|e| e.to_string()).collect();
 
@@ -434,13 +666,77 @@ impl NeoApi {
         Ok(AutoCmd::new(id))
     }
 
+    /**
+    Execute all autocommands for {event} that match the corresponding {opts}
+    |autocmd-execute|.
+
+    Parameters: ~
+      • {event}  The event or events to execute
+      • {opts}   Dictionary of autocommand options:
+                 • group (string|integer) optional: the autocommand group name
+                   or id to match against. |autocmd-groups|.
+                 • pattern (string|array) optional: defaults to "*"
+                   |autocmd-pattern|. Cannot be used with {buffer}.
+                 • buffer (integer) optional: buffer number
+                   |autocmd-buflocal|. Cannot be used with {pattern}.
+                 • modeline (bool) optional: defaults to true. Process the
+                   modeline after the autocommands |<nomodeline>|.
+                 • data (any): arbitrary data to send to the autocommand
+                   callback. See |nvim_create_autocmd()| for details.
+    */
+    pub fn exec_autocmds<D: IntoLua>(
+        lua: &Lua,
+        events: &[AutoCmdEvent],
+        opts: ExecAutoCmdOpts<D>,
+    ) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_exec_autocmds")?;
+
+        let events: Vec<String> = events.iter().map(|e| e.to_string()).collect();
+
+        lfn.call((events, opts))
+    }
+
+    /**
+    Get all autocommands that match the corresponding {opts}.
+
+    Note that when multiple patterns or events are provided, it will find all
+    the autocommands that match any combination of them.
+    */
+    pub fn get_autocmds(lua: &Lua, opts: GetAutoCmdOpts) -> LuaResult<Vec<AutoCmdInfo>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_autocmds")?;
+
+        lfn.call(opts)
+    }
+
+    /**
+    Deletes an autocommand by id.
+
+    Parameters: ~
+      • {id}  Integer Autocommand id returned by |nvim_create_autocmd()|
+    */
+    pub fn del_autocmd(lua: &Lua, autocmd: AutoCmd) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_del_autocmd")?;
+
+        lfn.call(autocmd.id())
+    }
+
+    /**
+    Clears all autocommands selected by {opts}. To delete autocmds see
+    |nvim_del_autocmd()|.
+    */
+    pub fn clear_autocmds(lua: &Lua, opts: ClearAutoCmdOpts) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_clear_autocmds")?;
+
+        lfn.call(opts)
+    }
+
     pub fn set_insert_mode(lua: &Lua, insert: bool) -> LuaResult<()> {
         if insert {
-            let lfn: LuaFunction = lua.load("vim.cmd.startinsert").eval()?;
+            let lfn: LuaFunction = lua.neo_fn("vim.cmd.startinsert")?;
 
             lfn.call(())
         } else {
-            let lfn: LuaFunction = lua.load("vim.cmd.stopinsert").eval()?;
+            let lfn: LuaFunction = lua.neo_fn("vim.cmd.stopinsert")?;
 
             lfn.call(())
         }
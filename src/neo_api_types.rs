@@ -1,10 +1,14 @@
 use crate::buffer::NeoBuffer;
 use crate::window::NeoWindow;
-use macros::{FromTable, IntoEnum, IntoEnumSC, IntoTable};
+use crate::{NeoLuaExt, NeoTabpage};
+use macros::{FromLuaSerde, FromTable, IntoEnum, IntoEnumSC, IntoLuaSerde, IntoTable};
 use mlua::prelude::*;
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::fmt::{self, Display};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoEnumSC)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoEnumSC, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum VirtTextPos {
     Eol,
     Overlay,
@@ -12,14 +16,17 @@ pub enum VirtTextPos {
     Inline,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoEnumSC)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoEnumSC, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum HLMode {
     Replace,
     Combine,
     Blend,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A `[text, highlight]` tuple
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, IntoLuaSerde, FromLuaSerde)]
+#[serde(from = "(String, String)", into = "(String, String)")]
 pub struct HLText {
     pub text: String,
     pub highlight: String,
@@ -34,14 +41,15 @@ impl HLText {
     }
 }
 
-impl IntoLua for HLText {
-    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
-        let table = lua.create_table()?;
-
-        table.push(self.text)?;
-        table.push(self.highlight)?;
+impl From<(String, String)> for HLText {
+    fn from((text, highlight): (String, String)) -> Self {
+        Self { text, highlight }
+    }
+}
 
-        Ok(LuaValue::Table(table))
+impl From<HLText> for (String, String) {
+    fn from(hl_text: HLText) -> Self {
+        (hl_text.text, hl_text.highlight)
     }
 }
 
@@ -64,24 +72,133 @@ where
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// String or list of `[text, highlight]` tuples
+#[derive(Debug, Clone, PartialEq, Eq, IntoEnum, Serialize, Deserialize)]
+#[lua(untagged)]
+#[serde(untagged)]
 pub enum TextType {
     String(String),
     Tuples(Vec<HLText>),
 }
 
-impl IntoLua for TextType {
+/// Opts of |nvim_get_option_value()| and |nvim_set_option_value()|
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OptValueType {
+    /// No opts, reads the value of the current window or buffer. Setting a global-local option
+    /// sets both values like |:set|
+    #[default]
+    Current,
+    /// `{ scope = "global" }` like |:setglobal|
+    Global,
+    /// `{ scope = "local" }` like |:setlocal|
+    Local,
+    /// `{ win = id }` window local option
+    Window(NeoWindow),
+    /// `{ buf = id }` buffer local option
+    Buffer(NeoBuffer),
+}
+
+impl IntoLua for OptValueType {
     fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let out = lua.create_table()?;
+
         match self {
-            Self::String(str) => Ok(LuaValue::String(lua.create_string(str)?)),
-            Self::Tuples(tuples) => tuples.into_lua(lua),
+            Self::Current => {}
+            Self::Global => out.set("scope", "global")?,
+            Self::Local => out.set("scope", "local")?,
+            Self::Window(win) => out.set("win", win)?,
+            Self::Buffer(buf) => out.set("buf", buf)?,
         }
+
+        Ok(LuaValue::Table(out))
     }
 }
 
-pub enum OptValueType {
-    Window(NeoWindow),
+/// Value of an option, options are either a boolean, number or string
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, IntoLuaSerde, FromLuaSerde)]
+#[serde(untagged)]
+pub enum OptionValue {
+    Boolean(bool),
+    Number(i64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionType {
+    Boolean,
+    Number,
+    String,
+}
+
+/// Where the local value of an option is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionScope {
+    Global,
+    #[serde(rename = "win")]
+    Window,
+    #[serde(rename = "buf")]
+    Buffer,
+}
+
+/// Option information as returned by |nvim_get_option_info2()|
+#[derive(Debug, Clone, Serialize, Deserialize, FromLuaSerde)]
+pub struct OptionInfo {
+    /// Name of the option (like 'filetype')
+    pub name: String,
+    /// Shortened name of the option (like 'ft')
+    pub shortname: String,
+    #[serde(rename = "type")]
+    pub value_type: OptionType,
+    /// Default value for the option
+    pub default: OptionValue,
+    pub was_set: bool,
+    /// Last set script id (if any)
+    pub last_set_sid: i64,
+    /// line number where option was set
+    pub last_set_linenr: u32,
+    /// Channel where option was set (0 for local)
+    pub last_set_chan: i64,
+    pub scope: OptionScope,
+    /// true if the win or buf option also has a global value, see |global-local|
+    pub global_local: bool,
+    /// List of comma separated values
+    pub commalist: bool,
+    /// List of single char flags
+    pub flaglist: bool,
+    pub allows_duplicates: bool,
+}
+
+/// Scope of a variable, see |lua-vim-variables|
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarScope {
+    /// `vim.g` global (g:) variables
+    Global,
+    /// `vim.b` buffer (b:) variables, `NeoBuffer::ZERO` for the current buffer
     Buffer(NeoBuffer),
+    /// `vim.w` window (w:) variables, `NeoWindow::CURRENT` for the current window
+    Window(NeoWindow),
+    /// `vim.t` tabpage (t:) variables, `NeoTabpage::CURRENT` for the current tabpage
+    Tabpage(NeoTabpage),
+    /// `vim.v` predefined |v:| variables
+    Vim,
+    /// `vim.env` environment variables
+    Env,
+}
+
+impl VarScope {
+    /// Lua table of the scope, missing variables are nil
+    pub fn table(&self, lua: &Lua) -> LuaResult<LuaTable> {
+        match self {
+            Self::Global => lua.neo_get("vim.g"),
+            Self::Buffer(buf) => lua.neo_get::<LuaTable>("vim.b")?.get(buf.id()),
+            Self::Window(win) => lua.neo_get::<LuaTable>("vim.w")?.get(win.id()),
+            Self::Tabpage(tab) => lua.neo_get::<LuaTable>("vim.t")?.get(tab.id()),
+            Self::Vim => lua.neo_get("vim.v"),
+            Self::Env => lua.neo_get("vim.env"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -94,6 +211,52 @@ pub enum LogLevel {
     Off = 5,
 }
 
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+            Self::Off => "OFF",
+        }
+    }
+
+    pub fn from_u8(level: u8) -> Self {
+        match level {
+            0 => Self::Trace,
+            1 => Self::Debug,
+            2 => Self::Info,
+            3 => Self::Warn,
+            4 => Self::Error,
+            _ => Self::Off,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            "off" => Ok(Self::Off),
+            _ => Err(format!("Unknown log level: {s}")),
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, IntoEnumSC)]
 pub enum StdpathType {
     /// Cache directory: arbitrary temporary storage for plugins, etc.
@@ -130,7 +293,7 @@ impl Display for OpenIn {
 }
 
 /// Pleas help to add more and test
-#[derive(Debug, Default, Clone, IntoTable)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IntoLuaSerde, FromLuaSerde)]
 pub struct ExtmarkOpts {
     // virt_lines : virtual lines to add next to this mark This
     // should be an array over lines, where each line in turn is
@@ -260,10 +423,29 @@ pub struct ExtmarkOpts {
     pub virt_lines_above: Option<bool>,
 }
 
-#[derive(Debug, Default, IntoTable, Clone, Copy)]
+/// Options of |vim.keymap.set()|, which also accepts all the |:map-arguments|
+#[derive(Debug, Default, IntoTable, Clone)]
 pub struct KeymapOpts {
     pub silent: Option<bool>,
+    /// Creates buffer-local mapping, `0` for current buffer
     pub buffer: Option<u32>,
+    /// The rhs is an expression |:map-expression|
+    pub expr: Option<bool>,
+    /// Do not remap keys in the rhs. Not sent, `vim.keymap.set` only uses `remap` so when this
+    /// is set and `remap` isn't, `remap` will be derived from it.
+    #[lua(skip)]
+    pub noremap: Option<bool>,
+    /// Make the mapping recursive. Inverse of `noremap`. Defaults to false
+    pub remap: Option<bool>,
+    /// Don't wait for more characters |:map-nowait|
+    pub nowait: Option<bool>,
+    /// Human-readable description
+    pub desc: Option<String>,
+    /// When `expr` is true, replace keycodes in the resulting string (see
+    /// |nvim_replace_termcodes()|). Defaults to true
+    pub replace_keycodes: Option<bool>,
+    /// Fail if the mapping already exists |:map-unique|
+    pub unique: Option<bool>,
 }
 
 impl KeymapOpts {
@@ -271,7 +453,115 @@ impl KeymapOpts {
         Self {
             buffer: Some(buf_id),
             silent: Some(silent),
+            ..Default::default()
+        }
+    }
+}
+
+/// Right-hand side of a keymap
+#[derive(Debug, Clone)]
+pub enum KeymapRhs {
+    Function(LuaFunction),
+    /// Keys or an expression (when `expr` is set) as string
+    Keys(String),
+}
+
+impl From<LuaFunction> for KeymapRhs {
+    fn from(lfn: LuaFunction) -> Self {
+        Self::Function(lfn)
+    }
+}
+
+impl From<String> for KeymapRhs {
+    fn from(keys: String) -> Self {
+        Self::Keys(keys)
+    }
+}
+
+impl From<&str> for KeymapRhs {
+    fn from(keys: &str) -> Self {
+        Self::Keys(keys.to_string())
+    }
+}
+
+impl IntoLua for KeymapRhs {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        match self {
+            Self::Function(lfn) => Ok(LuaValue::Function(lfn)),
+            Self::Keys(keys) => keys.into_lua(lua),
+        }
+    }
+}
+
+/// A mapping as returned by |nvim_get_keymap()|, see |maparg()| for the fields
+#[derive(Debug, Clone)]
+pub struct KeymapInfo {
+    /// The {lhs} of the mapping as it would be typed
+    pub lhs: String,
+    /// The {lhs} of the mapping as raw bytes, special keys aren't valid UTF-8
+    pub lhsraw: Option<Vec<u8>>,
+    /// The {rhs} of the mapping as typed, not set when a callback is used
+    pub rhs: Option<String>,
+    /// Lua function, if the mapping was defined with one
+    pub callback: Option<LuaFunction>,
+    /// Modes for which the mapping is defined, " " for Normal, Visual and Operator-pending
+    pub mode: String,
+    /// Description of the mapping, if any
+    pub desc: Option<String>,
+    pub expr: bool,
+    pub noremap: bool,
+    pub silent: bool,
+    pub nowait: bool,
+    /// Mapping is local to the script
+    pub script: bool,
+    /// Buffer for a buffer-local mapping
+    pub buffer: Option<NeoBuffer>,
+    /// The script ID where the mapping was defined
+    pub sid: i64,
+    /// The line number in the script where the mapping was defined
+    pub lnum: u32,
+}
+
+impl FromLua for KeymapInfo {
+    fn from_lua(value: LuaValue, _lua: &Lua) -> LuaResult<Self> {
+        // maparg() returns 0 / 1 instead of booleans
+        fn flag(table: &LuaTable, key: &str) -> LuaResult<bool> {
+            match table.get::<LuaValue>(key)? {
+                LuaValue::Boolean(flag) => Ok(flag),
+                LuaValue::Integer(flag) => Ok(flag != 0),
+                LuaValue::Number(flag) => Ok(flag != 0.0),
+                _ => Ok(false),
+            }
         }
+
+        let LuaValue::Table(table) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "KeymapInfo",
+                message: None,
+            });
+        };
+
+        let buf_id: u32 = table.get::<Option<u32>>("buffer")?.unwrap_or(0);
+
+        Ok(Self {
+            lhs: table.get("lhs")?,
+            lhsraw: table
+                .get::<Option<LuaString>>("lhsraw")?
+                .map(|lhsraw| lhsraw.as_bytes().to_vec()),
+            rhs: table.get("rhs")?,
+            callback: table.get("callback")?,
+            mode: table.get("mode")?,
+            desc: table.get("desc")?,
+            expr: flag(&table, "expr")?,
+            noremap: flag(&table, "noremap")?,
+            silent: flag(&table, "silent")?,
+            nowait: flag(&table, "nowait")?,
+            script: flag(&table, "script")?,
+            buffer: (buf_id != 0).then(|| NeoBuffer::from_id(buf_id)),
+            sid: table.get::<Option<i64>>("sid")?.unwrap_or(0),
+            lnum: table.get::<Option<u32>>("lnum")?.unwrap_or(0),
+        })
     }
 }
 
@@ -281,7 +571,9 @@ pub struct BufferDeleteOpts {
     pub unload: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A `[row, column]` tuple, row starts from 1 and column from 0
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, IntoLuaSerde, FromLuaSerde)]
+#[serde(from = "(u32, u32)", into = "(u32, u32)")]
 pub struct WinCursor {
     row: u32,
     pub column: u32,
@@ -312,31 +604,42 @@ impl WinCursor {
     }
 }
 
-impl FromLua for WinCursor {
-    fn from_lua(value: LuaValue, _lua: &Lua) -> LuaResult<Self> {
-        match value {
-            LuaValue::Table(table) => {
-                let row: u32 = table.get(1)?;
-                let column: u32 = table.get(2)?;
+impl From<(u32, u32)> for WinCursor {
+    fn from((row, column): (u32, u32)) -> Self {
+        Self { row, column }
+    }
+}
 
-                Ok(Self { row, column })
-            }
-            _ => Err(LuaError::runtime("Supposed to be a table".to_string())),
-        }
+impl From<WinCursor> for (u32, u32) {
+    fn from(cursor: WinCursor) -> Self {
+        (cursor.row, cursor.column)
     }
 }
 
-impl IntoLua for WinCursor {
-    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
-        let table = lua.create_table()?;
-        table.set(1, self.row)?;
-        table.set(2, self.column)?;
+/// Range of |nvim_win_text_height()|, the whole buffer when empty
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, IntoLuaSerde)]
+pub struct TextHeightOpts {
+    /// Starting line index, 0-based inclusive. When negative counts from the bottom
+    pub start_row: Option<i32>,
+    /// Ending line index, 0-based inclusive. When negative counts from the bottom
+    pub end_row: Option<i32>,
+    /// Starting virtual column index on "start_row", 0-based inclusive, rounded down to
+    /// the start of a full character
+    pub start_vcol: Option<u32>,
+    /// Ending virtual column index on "end_row", 0-based exclusive, rounded up to the end
+    /// of a full character
+    pub end_vcol: Option<u32>,
+}
 
-        Ok(LuaValue::Table(table))
-    }
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromLuaSerde)]
+pub struct TextHeight {
+    /// Total number of screen lines occupied by the range
+    pub all: u32,
+    /// Number of diff filler or virtual lines among them
+    pub fill: u32,
 }
 
-#[derive(Debug, FromTable, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromLuaSerde)]
 pub struct Ui {
     pub chan: u32,
     pub ext_cmdline: bool,
@@ -359,12 +662,33 @@ pub struct Ui {
     pub width: u32,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Map modes, see |map-modes|
+#[derive(Clone, Copy, Debug, PartialEq, Eq, IntoEnum)]
 pub enum Mode {
+    #[lua(name = "n")]
     Normal,
+    #[lua(name = "i")]
     Insert,
+    /// Visual and Select
+    #[lua(name = "v")]
     Visual,
+    #[lua(name = "s")]
     Select,
+    /// Visual only, without Select
+    #[lua(name = "x")]
+    VisualOnly,
+    #[lua(name = "c")]
+    CmdLine,
+    #[lua(name = "o")]
+    OperatorPending,
+    #[lua(name = "t")]
+    Terminal,
+    /// Insert and Command-line
+    #[lua(name = "!")]
+    InsertCmdLine,
+    /// Insert, Command-line and Lang-Arg |language-mapping|
+    #[lua(name = "l")]
+    LangArg,
 }
 
 impl Mode {
@@ -374,6 +698,12 @@ impl Mode {
             Mode::Normal => 'n',
             Mode::Visual => 'v',
             Mode::Select => 's',
+            Mode::VisualOnly => 'x',
+            Mode::CmdLine => 'c',
+            Mode::OperatorPending => 'o',
+            Mode::Terminal => 't',
+            Mode::InsertCmdLine => '!',
+            Mode::LangArg => 'l',
         }
     }
 
@@ -383,19 +713,16 @@ impl Mode {
             Mode::Normal => "n",
             Mode::Visual => "v",
             Mode::Select => "s",
+            Mode::VisualOnly => "x",
+            Mode::CmdLine => "c",
+            Mode::OperatorPending => "o",
+            Mode::Terminal => "t",
+            Mode::InsertCmdLine => "!",
+            Mode::LangArg => "l",
         }
     }
 }
 
-//impl<'lua> FromLua<'lua> for Ui {
-//fn from_lua(
-//value: LuaValue<'lua>,
-//lua: &'lua mlua::prelude::Lua,
-//) -> mlua::prelude::LuaResult<Self> {
-//lua.from_value(value)
-//}
-//}
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct AutoCmd(u32);
 
@@ -409,10 +736,12 @@ impl AutoCmd {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Group name or id, numbers are read as id because Lua converts them into strings too
+#[derive(Clone, Debug, IntoEnum)]
+#[lua(untagged)]
 pub enum AutoCmdGroup {
-    String(String),
     Integer(u32),
+    String(String),
 }
 
 #[derive(Debug, Clone, Copy, IntoEnum, PartialEq, Eq)]
@@ -463,16 +792,16 @@ pub enum AutoCmdEvent {
     ChanOpen,
     /// Command undefined.
     CmdUndefined,
-    /// After entering the cmdline window.
-    CmdWinEnter,
-    /// Before leaving the cmdline window.
-    CmdWinLeave,
     /// Command line was modified.
     CmdlineChanged,
     /// After entering cmdline mode.
     CmdlineEnter,
     /// Before leaving cmdline mode.
     CmdlineLeave,
+    /// After entering the cmdline window.
+    CmdwinEnter,
+    /// Before leaving the cmdline window.
+    CmdwinLeave,
     /// After loading a colorscheme.
     ColorScheme,
     /// Before loading a colorscheme.
@@ -489,6 +818,8 @@ pub enum AutoCmdEvent {
     CursorHoldI,
     /// Cursor was moved.
     CursorMoved,
+    /// Cursor was moved in Command-line mode.
+    CursorMovedC,
     /// Cursor was moved in Insert mode.
     CursorMovedI,
     /// Diagnostics in a buffer were modified.
@@ -677,6 +1008,8 @@ pub enum AutoCmdEvent {
     WinLeave,
     /// When entering a new window.
     WinNew,
+    /// Before creating a new window.
+    WinNewPre,
     /// After a window was resized.
     WinResized,
     /// After a window was scrolled or resized.
@@ -720,11 +1053,9 @@ impl IntoLua for AutoCmdOpts {
     fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
         let table = lua.create_table()?;
 
-        match self.group {
-            Some(AutoCmdGroup::String(name)) => table.set("group", name)?,
-            Some(AutoCmdGroup::Integer(id)) => table.set("group", id)?,
-            None => {}
-        };
+        if let Some(group) = self.group {
+            table.set("group", group)?;
+        }
 
         if !self.pattern.is_empty() {
             table.set("pattern", self.pattern)?;
@@ -745,6 +1076,194 @@ impl IntoLua for AutoCmdOpts {
     }
 }
 
+/// Use `D` to send typed data with the event, other plugins receive it as `data` in their
+/// callback (see `AutoCmdCbEvent::parse_data`).
+#[derive(Debug, Clone)]
+pub struct ExecAutoCmdOpts<D = ()> {
+    /// Autocommand group name or id to match against. |autocmd-groups|.
+    pub group: Option<AutoCmdGroup>,
+
+    /// Optional: defaults to "*" |autocmd-pattern|. Cannot be used with {buffer}.
+    pub pattern: Vec<String>,
+
+    /// Buffer number |autocmd-buflocal|. Cannot be used with {pattern}.
+    pub buffer: Option<u32>,
+
+    /// Defaults to true. Process the modeline after the autocommands |<nomodeline>|.
+    pub modeline: Option<bool>,
+
+    /// Arbitrary data to send to the autocommand callback.
+    pub data: Option<D>,
+}
+
+impl<D> Default for ExecAutoCmdOpts<D> {
+    fn default() -> Self {
+        Self {
+            group: None,
+            pattern: vec![],
+            buffer: None,
+            modeline: None,
+            data: None,
+        }
+    }
+}
+
+impl<D: IntoLua> IntoLua for ExecAutoCmdOpts<D> {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let table = lua.create_table()?;
+
+        if let Some(group) = self.group {
+            table.set("group", group)?;
+        }
+
+        if !self.pattern.is_empty() {
+            table.set("pattern", self.pattern)?;
+        }
+
+        if let Some(buf_id) = self.buffer {
+            table.set("buffer", buf_id)?;
+        }
+
+        if let Some(modeline) = self.modeline {
+            table.set("modeline", modeline)?;
+        }
+
+        if let Some(data) = self.data {
+            table.set("data", data)?;
+        }
+
+        Ok(LuaValue::Table(table))
+    }
+}
+
+/// Filters for |nvim_get_autocmds()|, all the filters are combined (AND).
+#[derive(Debug, Default, Clone)]
+pub struct GetAutoCmdOpts {
+    /// Autocommand group name or id to match against.
+    pub group: Option<AutoCmdGroup>,
+
+    /// Event or events to match against |autocmd-events|.
+    pub events: Vec<AutoCmdEvent>,
+
+    /// Pattern or patterns to match against |autocmd-pattern|. Cannot be used with {buffer}
+    pub pattern: Vec<String>,
+
+    /// Buffer number or list of buffer numbers for buffer local autocommands
+    /// |autocmd-buflocal|. Cannot be used with {pattern}
+    pub buffer: Vec<u32>,
+
+    /// Autocommand id to match.
+    pub id: Option<u32>,
+}
+
+impl IntoLua for GetAutoCmdOpts {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let table = lua.create_table()?;
+
+        if let Some(group) = self.group {
+            table.set("group", group)?;
+        }
+
+        if !self.events.is_empty() {
+            let events: Vec<String> = self.events.iter().map(|e| e.to_string()).collect();
+            table.set("event", events)?;
+        }
+
+        if !self.pattern.is_empty() {
+            table.set("pattern", self.pattern)?;
+        }
+
+        if !self.buffer.is_empty() {
+            table.set("buffer", self.buffer)?;
+        }
+
+        if let Some(id) = self.id {
+            table.set("id", id)?;
+        }
+
+        Ok(LuaValue::Table(table))
+    }
+}
+
+/// Options for |nvim_clear_autocmds()|, without any options all autocommands are cleared.
+#[derive(Debug, Default, Clone)]
+pub struct ClearAutoCmdOpts {
+    /// Event or events to clear |autocmd-events|.
+    pub events: Vec<AutoCmdEvent>,
+
+    /// Pattern or patterns to match exactly. Cannot be used with {buffer}
+    pub pattern: Vec<String>,
+
+    /// Buffer number for buffer local autocommands |autocmd-buflocal|.
+    /// Cannot be used with {pattern}
+    pub buffer: Option<u32>,
+
+    /// Autocommand group name or id. If omitted only autocommands without a group are cleared.
+    pub group: Option<AutoCmdGroup>,
+}
+
+impl IntoLua for ClearAutoCmdOpts {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let table = lua.create_table()?;
+
+        if !self.events.is_empty() {
+            let events: Vec<String> = self.events.iter().map(|e| e.to_string()).collect();
+            table.set("event", events)?;
+        }
+
+        if !self.pattern.is_empty() {
+            table.set("pattern", self.pattern)?;
+        }
+
+        if let Some(buf_id) = self.buffer {
+            table.set("buffer", buf_id)?;
+        }
+
+        if let Some(group) = self.group {
+            table.set("group", group)?;
+        }
+
+        Ok(LuaValue::Table(table))
+    }
+}
+
+/// A single autocommand as returned by |nvim_get_autocmds()|
+#[derive(Debug, Clone, FromTable)]
+pub struct AutoCmdInfo {
+    /// Autocommand id (only when defined with the API).
+    pub id: Option<u32>,
+
+    /// Autocommand group id.
+    pub group: Option<u32>,
+
+    /// Autocommand group name.
+    pub group_name: Option<String>,
+
+    /// Autocommand description.
+    pub desc: Option<String>,
+
+    /// Autocommand event.
+    pub event: String,
+
+    /// Autocommand command. Empty string if a callback is set.
+    pub command: String,
+
+    /// Lua callback, if any.
+    pub callback: Option<LuaFunction>,
+
+    /// Whether the autocommand is only run once.
+    pub once: bool,
+
+    /// Autocommand pattern.
+    pub pattern: String,
+
+    /// Whether the autocommand is buffer local.
+    pub buflocal: bool,
+
+    /// Buffer number, only set for buffer local autocommands.
+    pub buffer: Option<u32>,
+}
+
 #[derive(Debug, Clone, FromTable)]
 pub struct AutoCmdCbEvent {
     /// Autocommand id
@@ -765,48 +1284,156 @@ pub struct AutoCmdCbEvent {
     /// Expanded value of <afile>
     pub file: String,
 
-    ///  (Any) arbitrary data passed from |nvim_exec_autocmds()|
-    /// You can use CbDataFiller type in the callback function if you don't need any data
-    pub data: Option<usize>,
+    /// (Any) arbitrary data passed from |nvim_exec_autocmds()|. Use `parse_data` or one of the
+    /// typed helpers (e.g. `lsp_attach`) to read it.
+    pub data: LuaValue,
+}
+
+impl AutoCmdCbEvent {
+    /// Converts the data payload into a Rust type
+    pub fn parse_data<T: FromLua>(&self, lua: &Lua) -> LuaResult<T> {
+        T::from_lua(self.data.clone(), lua)
+    }
+
+    /// Data of the `LspAttach` event
+    pub fn lsp_attach(&self, lua: &Lua) -> LuaResult<LspClientEvent> {
+        self.lsp_client_event(lua, AutoCmdEvent::LspAttach)
+    }
+
+    /// Data of the `LspDetach` event
+    pub fn lsp_detach(&self, lua: &Lua) -> LuaResult<LspClientEvent> {
+        self.lsp_client_event(lua, AutoCmdEvent::LspDetach)
+    }
+
+    /// Data of the `TermResponse` event, this is the escape sequence send by the terminal
+    pub fn term_response(&self) -> LuaResult<String> {
+        self.term_sequence(AutoCmdEvent::TermResponse)
+    }
+
+    /// Data of the `TermRequest` event, this is the escape sequence send by the process
+    pub fn term_request(&self) -> LuaResult<String> {
+        self.term_sequence(AutoCmdEvent::TermRequest)
+    }
+
+    fn lsp_client_event(&self, lua: &Lua, event: AutoCmdEvent) -> LuaResult<LspClientEvent> {
+        self.expect_event(event)?;
+
+        let data: LspClientData = self.parse_data(lua)?;
+
+        let buf = match self.buf {
+            Some(buf_id) => NeoBuffer::from_id(buf_id),
+            None => NeoBuffer::ZERO,
+        };
+
+        Ok(LspClientEvent {
+            client_id: data.client_id,
+            buf,
+        })
+    }
+
+    /// Older Neovim versions pass the sequence as string, newer versions as table
+    fn term_sequence(&self, event: AutoCmdEvent) -> LuaResult<String> {
+        self.expect_event(event)?;
+
+        match &self.data {
+            LuaValue::String(sequence) => Ok(sequence.to_string_lossy().into()),
+            LuaValue::Table(table) => table.get("sequence"),
+            value => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "String",
+                message: Some("Expected a terminal sequence".to_string()),
+            }),
+        }
+    }
+
+    fn expect_event(&self, event: AutoCmdEvent) -> LuaResult<()> {
+        if self.event == event.to_string() {
+            Ok(())
+        } else {
+            Err(LuaError::runtime(format!(
+                "Expected {event} event, received {}",
+                self.event
+            )))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, FromTable)]
+struct LspClientData {
+    client_id: u32,
 }
 
-#[derive(Default, Clone)]
+/// Data passed with the `LspAttach` and `LspDetach` events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LspClientEvent {
+    /// Id of the attached / detached client, see |vim.lsp.get_client_by_id()|
+    pub client_id: u32,
+    /// The buffer the client attached to / detached from
+    pub buf: NeoBuffer,
+}
+
+#[derive(Debug, Default, Clone, IntoTable, FromTable)]
 pub struct CmdOptsMagic {
+    /// Whether '|' is treated as a command separator
     pub bar: bool,
+    /// Whether special characters like % are expanded |cmdline-special|
     pub file: bool,
 }
 
-#[derive(Default, Clone)]
-pub struct CmdOptsModsFilter<'a> {
+#[derive(Debug, Default, Clone, IntoTable, FromTable)]
+pub struct CmdOptsModsFilter {
+    /// Whether filter is inverted or not.
     pub force: bool,
-    pub pattern: &'a str,
+    /// Filter pattern, empty string if there is no filter.
+    pub pattern: String,
 }
 
-#[derive(Clone)]
-pub struct CmdOptsMods<'a> {
+#[derive(Debug, Clone, IntoTable, FromTable)]
+pub struct CmdOptsMods {
+    /// |:browse|.
     pub browse: bool,
+    /// |:confirm|.
     pub confirm: bool,
+    /// |:silent!|.
     pub emsg_silent: bool,
-    pub filter: CmdOptsModsFilter<'a>,
+    /// |:filter|.
+    pub filter: CmdOptsModsFilter,
+    /// |:hide|.
     pub hide: bool,
+    /// |:horizontal|.
     pub horizontal: bool,
+    /// |:keepalt|.
     pub keepalt: bool,
+    /// |:keepjumps|.
     pub keepjumps: bool,
+    /// |:keepmarks|.
     pub keepmarks: bool,
+    /// |:keeppatterns|.
     pub keeppatterns: bool,
+    /// |:lockmarks|.
     pub lockmarks: bool,
+    /// |:noautocmd|.
     pub noautocmd: bool,
+    /// |:noswapfile|.
     pub noswapfile: bool,
+    /// |:sandbox|.
     pub sandbox: bool,
+    /// |:silent|.
     pub silent: bool,
-    pub split: &'a str,
-    pub tab: i32, // -1
+    /// Split modifier string, is an empty string when there's no split modifier. If there is a
+    /// split modifier it can be one of: "aboveleft", "belowright", "topleft" or "botright".
+    pub split: String,
+    /// |:tab|. -1 when omitted.
+    pub tab: i32,
+    /// |:unsilent|.
     pub unsilent: bool,
-    pub verbose: i32, // -1
+    /// |:verbose|. -1 when omitted.
+    pub verbose: i32,
+    /// |:vertical|.
     pub vertical: bool,
 }
 
-impl Default for CmdOptsMods<'_> {
+impl Default for CmdOptsMods {
     fn default() -> Self {
         Self {
             browse: false,
@@ -824,7 +1451,7 @@ impl Default for CmdOptsMods<'_> {
             noswapfile: false,
             sandbox: false,
             silent: false,
-            split: "",
+            split: "".to_string(),
             tab: -1,
             unsilent: false,
             verbose: -1,
@@ -833,61 +1460,180 @@ impl Default for CmdOptsMods<'_> {
     }
 }
 
-impl<'opts> IntoLua for CmdOpts<'opts> {
-    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
-        let out = lua.create_table()?;
-        out.set("cmd", self.cmd)?;
-        // TODO make IntoTable complient to this structure
-        out.set("args", self.args)?;
-        out.set("bang", self.bang)?;
+/// Value of the command's |:command-addr| property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmdAddr {
+    Line,
+    Arg,
+    Buf,
+    Load,
+    Win,
+    Tab,
+    Quickfix,
+    None,
+    /// Address type is not known (`?`)
+    Other,
+}
 
-        Ok(LuaValue::Table(out))
+impl Display for CmdAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Line => f.write_str("line"),
+            Self::Arg => f.write_str("arg"),
+            Self::Buf => f.write_str("buf"),
+            Self::Load => f.write_str("load"),
+            Self::Win => f.write_str("win"),
+            Self::Tab => f.write_str("tab"),
+            Self::Quickfix => f.write_str("quickfix"),
+            Self::None => f.write_str("none"),
+            Self::Other => f.write_str("?"),
+        }
     }
 }
 
-#[derive(Clone)]
-pub struct CmdOpts<'a> {
-    pub cmd: &'a str,
-    pub args: &'a [&'a str],
-    pub bang: bool,
+impl IntoLua for CmdAddr {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        self.to_string().into_lua(lua)
+    }
 }
 
-impl<'a> CmdOpts<'a> {
-    pub fn simple(cmd: &'a str) -> Self {
-        Self {
-            cmd,
-            args: &[],
-            bang: false,
+impl FromLua for CmdAddr {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let addr = String::from_lua(value, lua)?;
+
+        match addr.as_str() {
+            "line" => Ok(Self::Line),
+            "arg" => Ok(Self::Arg),
+            "buf" => Ok(Self::Buf),
+            "load" => Ok(Self::Load),
+            "win" => Ok(Self::Win),
+            "tab" => Ok(Self::Tab),
+            "quickfix" => Ok(Self::Quickfix),
+            "none" => Ok(Self::None),
+            "?" => Ok(Self::Other),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: "string",
+                to: "CmdAddr",
+                message: Some(format!("Unknown address type: {addr}")),
+            }),
         }
     }
 }
 
-pub struct FileTypeMatch {
-    pub buf: Option<u32>,
-    pub filename: Option<String>,
-    pub contents: Option<Vec<String>>,
+/// Value of the command's |:command-nargs| property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmdNargs {
+    /// No arguments are allowed (the default)
+    Zero,
+    /// Exactly one argument is required, it includes spaces
+    One,
+    /// Any number of arguments are allowed (0, 1, or many), separated by white space
+    Any,
+    /// 0 or 1 arguments are allowed
+    ZeroOrOne,
+    /// Arguments must be supplied, but any number are allowed
+    OneOrMore,
+}
+
+impl Display for CmdNargs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Zero => f.write_str("0"),
+            Self::One => f.write_str("1"),
+            Self::Any => f.write_str("*"),
+            Self::ZeroOrOne => f.write_str("?"),
+            Self::OneOrMore => f.write_str("+"),
+        }
+    }
 }
 
-impl IntoLua for FileTypeMatch {
+impl IntoLua for CmdNargs {
     fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
-        let out = lua.create_table()?;
+        self.to_string().into_lua(lua)
+    }
+}
 
-        if let Some(buf) = self.buf {
-            out.set("buf", buf)?;
-        }
+impl FromLua for CmdNargs {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        // nvim_parse_cmd returns a string, but 0 and 1 are allowed as integer as well
+        let nargs = match value {
+            LuaValue::Integer(num) => num.to_string(),
+            value => String::from_lua(value, lua)?,
+        };
 
-        if let Some(filename) = self.filename {
-            out.set("filename", filename)?;
+        match nargs.as_str() {
+            "0" => Ok(Self::Zero),
+            "1" => Ok(Self::One),
+            "*" => Ok(Self::Any),
+            "?" => Ok(Self::ZeroOrOne),
+            "+" => Ok(Self::OneOrMore),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: "string",
+                to: "CmdNargs",
+                message: Some(format!("Unknown nargs value: {nargs}")),
+            }),
         }
+    }
+}
+
+/// Command in the same shape as |nvim_cmd()| expects and |nvim_parse_cmd()| returns.
+#[derive(Debug, Default, Clone, IntoTable, FromTable)]
+pub struct CmdOpts {
+    /// Command name.
+    pub cmd: String,
+    /// Command arguments.
+    pub args: Vec<String>,
+    /// Whether command contains a <bang> (!) modifier.
+    pub bang: bool,
+    /// Command range (<line1> <line2>). Omitted if command doesn't accept a range.
+    pub range: Option<Vec<u32>>,
+    /// Command <count>. Omitted if command cannot take a count.
+    pub count: Option<u32>,
+    /// Command <register>. Omitted if command cannot take a register.
+    pub reg: Option<String>,
+    /// The |:command-addr| of the command, ignored by |nvim_cmd()|.
+    pub addr: Option<CmdAddr>,
+    /// Value of |:command-nargs|, ignored by |nvim_cmd()|.
+    pub nargs: Option<CmdNargs>,
+    /// Which characters have special meaning in the command arguments.
+    pub magic: Option<CmdOptsMagic>,
+    /// |:command-modifiers|.
+    pub mods: Option<CmdOptsMods>,
+}
 
-        if let Some(contents) = self.contents {
-            out.set("contents", contents)?;
+impl CmdOpts {
+    pub fn simple(cmd: impl Into<String>) -> Self {
+        Self {
+            cmd: cmd.into(),
+            ..Default::default()
         }
+    }
 
-        out.into_lua(lua)
+    pub fn with_args<S: Into<String>>(
+        cmd: impl Into<String>,
+        args: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self {
+            cmd: cmd.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, IntoTable)]
+pub struct CmdExecOpts {
+    /// Whether to return command output. Default false
+    pub output: bool,
+}
+
+#[derive(Debug, Default, Clone, Serialize, IntoLuaSerde)]
+pub struct FileTypeMatch {
+    pub buf: Option<u32>,
+    pub filename: Option<String>,
+    pub contents: Option<Vec<String>>,
+}
+
 #[derive(Clone, Copy)]
 pub enum BufInfoOpts {
     BufListed,
@@ -911,19 +1657,22 @@ impl IntoLua for BufInfoOpts {
     }
 }
 
-#[derive(FromTable, Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromLuaSerde)]
 pub struct BufInfo {
     /// Buffer number
     pub bufnr: u32,
     /// TRUE if the buffer is modified.
+    #[serde(deserialize_with = "vim_bool")]
     pub changed: bool,
     /// Number of changes made to the buffer.
     pub changedtick: u32,
     /**
     TRUE if the buffer belongs to the command-line window cmdwin.
     */
+    #[serde(deserialize_with = "vim_bool")]
     pub command: bool,
     /// TRUE if the buffer is hidden.
+    #[serde(deserialize_with = "vim_bool")]
     pub hidden: bool,
     /**
     Timestamp in seconds, like
@@ -931,6 +1680,7 @@ pub struct BufInfo {
     */
     pub lastused: u32,
     /// TRUE if the buffer is listed.
+    #[serde(deserialize_with = "vim_bool")]
     pub listed: bool,
     /**
     Line number used for the buffer when
@@ -944,11 +1694,545 @@ pub struct BufInfo {
     /// Number of lines in the buffer (only valid when loaded)
     pub linecount: u32,
     /// TRUE if the buffer is loaded.
+    #[serde(deserialize_with = "vim_bool")]
     pub loaded: bool,
     /// Full path to the file in the buffer.
     pub name: String,
-    /// A reference to the dictionary with buffer-local variables.
+    /// Names of the buffer-local variables, Neovim returns them as dictionary.
+    #[serde(deserialize_with = "var_names")]
     pub variables: Vec<String>,
     /// List of |window-ID|s that display this buffer
     pub windows: Vec<u32>,
 }
+
+/// Booleans of Vimscript functions are the numbers 0 and 1
+fn vim_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    struct VimBool;
+
+    impl de::Visitor<'_> for VimBool {
+        type Value = bool;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a boolean or a number")
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+            Ok(value)
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+            Ok(value != 0)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+            Ok(value != 0)
+        }
+    }
+
+    deserializer.deserialize_any(VimBool)
+}
+
+/// Keys of a dictionary or a list of names
+fn var_names<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    struct VarNames;
+
+    impl<'de> de::Visitor<'de> for VarNames {
+        type Value = Vec<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a dictionary of variables or a list of names")
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut names = Vec::new();
+
+            while let Some((name, _)) = map.next_entry::<String, de::IgnoredAny>()? {
+                names.push(name);
+            }
+
+            Ok(names)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut names = Vec::new();
+
+            while let Some(name) = seq.next_element()? {
+                names.push(name);
+            }
+
+            Ok(names)
+        }
+    }
+
+    deserializer.deserialize_any(VarNames)
+}
+
+/// Value of the |:command-range| attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserCommandRange {
+    /// Range allowed, default is current line
+    CurrentLine,
+    /// Range allowed, default is whole file (1,$)
+    WholeFile,
+    /// A count (default N) which is specified in the line number position (like |:Next|)
+    Count(u32),
+}
+
+impl IntoLua for UserCommandRange {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        match self {
+            Self::CurrentLine => Ok(LuaValue::Boolean(true)),
+            Self::WholeFile => "%".into_lua(lua),
+            Self::Count(count) => count.into_lua(lua),
+        }
+    }
+}
+
+/// Arguments passed to a custom completion function |:command-completion-customlist|
+#[derive(Debug, Clone)]
+pub struct UserCommandCompleteArgs {
+    /// The leading portion of the argument currently being completed on
+    pub arg_lead: String,
+    /// The entire command line
+    pub cmd_line: String,
+    /// The cursor position in it (byte index)
+    pub cursor_pos: usize,
+}
+
+/// Value of the |:command-complete| attribute
+#[derive(Debug, Clone)]
+pub enum UserCommandComplete {
+    /// One of the builtin completion types, e.g. "file", "buffer" or "shellcmd"
+    Builtin(String),
+    /// Lua function that receives `(ArgLead, CmdLine, CursorPos)` and returns a list of strings
+    Function(LuaFunction),
+}
+
+impl UserCommandComplete {
+    /// Creates a completion function from a Rust closure
+    pub fn from_fn<F>(lua: &Lua, complete: F) -> LuaResult<Self>
+    where
+        F: Fn(&Lua, UserCommandCompleteArgs) -> LuaResult<Vec<String>> + 'static,
+    {
+        let lfn = lua.create_function(
+            move |lua, (arg_lead, cmd_line, cursor_pos): (String, String, usize)| {
+                complete(
+                    lua,
+                    UserCommandCompleteArgs {
+                        arg_lead,
+                        cmd_line,
+                        cursor_pos,
+                    },
+                )
+            },
+        )?;
+
+        Ok(Self::Function(lfn))
+    }
+}
+
+impl IntoLua for UserCommandComplete {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        match self {
+            Self::Builtin(name) => name.into_lua(lua),
+            Self::Function(lfn) => Ok(LuaValue::Function(lfn)),
+        }
+    }
+}
+
+/// Options for |nvim_create_user_command()|, see |command-attributes|
+#[derive(Debug, Default, Clone)]
+pub struct UserCommandOpts {
+    /// The number of arguments the command takes |:command-nargs|
+    pub nargs: Option<CmdNargs>,
+    /// Allow a range |:command-range|
+    pub range: Option<UserCommandRange>,
+    /// Allow a count with default N |:command-count|
+    pub count: Option<u32>,
+    /// Completion for the arguments |:command-complete|
+    pub complete: Option<UserCommandComplete>,
+    /// Used for listing the command when a Lua function is used for {command}
+    pub desc: Option<String>,
+    /// The command can take a ! modifier |:command-bang|
+    pub bang: bool,
+    /// The command can be followed by a "|" and another command |:command-bar|
+    pub bar: bool,
+    /// The first argument to the command can be an optional register |:command-register|
+    pub register: bool,
+    /// Do not use the location of where the user command was defined for verbose messages
+    pub keepscript: bool,
+    /// Override any previous definition. Defaults to true
+    pub force: Option<bool>,
+    /// Preview callback for 'inccommand' |:command-preview|
+    pub preview: Option<LuaFunction>,
+    /// Create a buffer-local command |:command-buffer|
+    pub buffer: Option<NeoBuffer>,
+}
+
+impl IntoLua for UserCommandOpts {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let out = lua.create_table()?;
+
+        if let Some(nargs) = self.nargs {
+            out.set("nargs", nargs)?;
+        }
+
+        if let Some(range) = self.range {
+            out.set("range", range)?;
+        }
+
+        if let Some(count) = self.count {
+            out.set("count", count)?;
+        }
+
+        if let Some(complete) = self.complete {
+            out.set("complete", complete)?;
+        }
+
+        if let Some(desc) = self.desc {
+            out.set("desc", desc)?;
+        }
+
+        if let Some(force) = self.force {
+            out.set("force", force)?;
+        }
+
+        if let Some(preview) = self.preview {
+            out.set("preview", preview)?;
+        }
+
+        out.set("bang", self.bang)?;
+        out.set("bar", self.bar)?;
+        out.set("register", self.register)?;
+        out.set("keepscript", self.keepscript)?;
+
+        Ok(LuaValue::Table(out))
+    }
+}
+
+/// The table a user command callback receives, see |nvim_create_user_command()|
+#[derive(Debug, Clone, FromTable)]
+pub struct UserCommandArgs {
+    /// Command name
+    pub name: String,
+    /// The args passed to the command, if any <args>
+    pub args: String,
+    /// The args split by unescaped whitespace (when more than one argument is allowed), if any
+    /// <f-args>
+    pub fargs: Vec<String>,
+    /// Number of arguments |:command-nargs|
+    pub nargs: String,
+    /// "true" if the command was executed with a ! modifier <bang>
+    pub bang: bool,
+    /// The starting line of the command range <line1>
+    pub line1: u32,
+    /// The final line of the command range <line2>
+    pub line2: u32,
+    /// The number of items in the command range: 0, 1, or 2 <range>
+    pub range: u32,
+    /// Any count supplied <count>
+    pub count: i32,
+    /// The optional register, if specified <reg>
+    pub reg: String,
+    /// Command modifiers, if any <mods>
+    pub mods: String,
+    /// Command modifiers in a structured format
+    pub smods: CmdOptsMods,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lists the variants, the match fails to compile when a variant is missing
+    macro_rules! all_events {
+        ($($event:ident),* $(,)?) => {{
+            #[allow(dead_code)]
+            fn exhaustive(event: AutoCmdEvent) {
+                match event {
+                    $(AutoCmdEvent::$event)|* => {}
+                }
+            }
+
+            vec![$(AutoCmdEvent::$event),*]
+        }};
+    }
+
+    #[test]
+    fn autocmd_events_match_help() {
+        let events = all_events![
+            BufAdd,
+            BufDelete,
+            BufEnter,
+            BufFilePost,
+            BufFilePre,
+            BufHidden,
+            BufLeave,
+            BufModifiedSet,
+            BufNew,
+            BufNewFile,
+            BufReadCmd,
+            BufReadPost,
+            BufReadPre,
+            BufUnload,
+            BufWinEnter,
+            BufWinLeave,
+            BufWipeout,
+            BufWriteCmd,
+            BufWritePost,
+            BufWritePre,
+            ChanInfo,
+            ChanOpen,
+            CmdUndefined,
+            CmdlineChanged,
+            CmdlineEnter,
+            CmdlineLeave,
+            CmdwinEnter,
+            CmdwinLeave,
+            ColorScheme,
+            ColorSchemePre,
+            CompleteChanged,
+            CompleteDone,
+            CompleteDonePre,
+            CursorHold,
+            CursorHoldI,
+            CursorMoved,
+            CursorMovedC,
+            CursorMovedI,
+            DiagnosticChanged,
+            DiffUpdated,
+            DirChanged,
+            DirChangedPre,
+            EncodingChanged,
+            ExitPre,
+            FileAppendCmd,
+            FileAppendPost,
+            FileAppendPre,
+            FileChangedRO,
+            FileChangedShell,
+            FileChangedShellPost,
+            FileReadCmd,
+            FileReadPost,
+            FileReadPre,
+            FileType,
+            FileWriteCmd,
+            FileWritePost,
+            FileWritePre,
+            FilterReadPost,
+            FilterReadPre,
+            FilterWritePost,
+            FilterWritePre,
+            FocusGained,
+            FocusLost,
+            FuncUndefined,
+            GUIEnter,
+            GUIFailed,
+            InsertChange,
+            InsertCharPre,
+            InsertEnter,
+            InsertLeave,
+            InsertLeavePre,
+            LspAttach,
+            LspDetach,
+            LspRequest,
+            LspNotify,
+            LspTokenUpdate,
+            LspProgress,
+            MenuPopup,
+            ModeChanged,
+            OptionSet,
+            QuickFixCmdPost,
+            QuickFixCmdPre,
+            QuitPre,
+            RecordingEnter,
+            RecordingLeave,
+            RemoteReply,
+            SafeState,
+            SearchWrapped,
+            SessionLoadPost,
+            SessionWritePost,
+            ShellCmdPost,
+            ShellFilterPost,
+            Signal,
+            SourceCmd,
+            SourcePost,
+            SourcePre,
+            SpellFileMissing,
+            StdinReadPost,
+            StdinReadPre,
+            SwapExists,
+            Syntax,
+            TabClosed,
+            TabEnter,
+            TabLeave,
+            TabNew,
+            TabNewEntered,
+            TermChanged,
+            TermClose,
+            TermEnter,
+            TermLeave,
+            TermOpen,
+            TermRequest,
+            TermResponse,
+            TextChanged,
+            TextChangedI,
+            TextChangedP,
+            TextChangedT,
+            TextYankPost,
+            UIEnter,
+            UILeave,
+            User,
+            VimEnter,
+            VimLeave,
+            VimLeavePre,
+            VimResized,
+            VimResume,
+            VimSuspend,
+            WinClosed,
+            WinEnter,
+            WinLeave,
+            WinNew,
+            WinNewPre,
+            WinResized,
+            WinScrolled,
+        ];
+
+        let mut names: Vec<String> = events.iter().map(|event| event.to_string()).collect();
+        names.sort();
+
+        let mut expected: Vec<&str> = include_str!("snapshots/autocmd_events.txt")
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        expected.sort();
+
+        assert_eq!(names, expected);
+
+        for event in events {
+            assert_eq!(event.to_string().parse::<AutoCmdEvent>(), Ok(event));
+        }
+    }
+
+    fn buf_info(variables: Vec<String>) -> BufInfo {
+        BufInfo {
+            bufnr: 1,
+            changed: true,
+            changedtick: 3,
+            command: false,
+            hidden: false,
+            lastused: 1_700_000_000,
+            listed: true,
+            lnum: 1,
+            linecount: 10,
+            loaded: true,
+            name: "/tmp/main.rs".to_string(),
+            variables,
+            windows: vec![1000],
+        }
+    }
+
+    #[test]
+    fn tuples_are_json_arrays() {
+        let text = HLText::new("foo", "Comment");
+        let json = serde_json::to_string(&text).unwrap();
+
+        assert_eq!(json, r#"["foo","Comment"]"#);
+        assert_eq!(serde_json::from_str::<HLText>(&json).unwrap(), text);
+
+        let cursor = WinCursor::from_one_indexed(3, 5);
+        let json = serde_json::to_string(&cursor).unwrap();
+
+        assert_eq!(json, "[3,5]");
+
+        let cursor: WinCursor = serde_json::from_str(&json).unwrap();
+        assert_eq!(cursor.row_one_indexed(), 3);
+        assert_eq!(cursor.column, 5);
+    }
+
+    #[test]
+    fn buf_info_survives_json() {
+        let info = buf_info(vec!["changedtick".to_string()]);
+        let json = serde_json::to_string(&info).unwrap();
+
+        // Variables are written as a list of names and the booleans as booleans
+        let read: BufInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&read).unwrap(), json);
+
+        let read: BufInfo = serde_json::from_str(
+            r#"{
+                "bufnr": 1, "changed": 1, "changedtick": 3, "command": 0, "hidden": 0,
+                "lastused": 1700000000, "listed": 1, "lnum": 1, "linecount": 10, "loaded": 1,
+                "name": "/tmp/main.rs", "variables": { "changedtick": 3 }, "windows": [1000]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(serde_json::to_string(&read).unwrap(), json);
+    }
+
+    #[cfg(feature = "test-harness")]
+    mod lua {
+        use super::*;
+        use crate::NeoMock;
+
+        fn bufinfo_lua(variables: &str) -> String {
+            format!(
+                r#"return {{
+                    bufnr = 1, changed = 1, changedtick = 3, command = 0, hidden = 0,
+                    lastused = 1700000000, listed = 1, lnum = 1, linecount = 10, loaded = 1,
+                    name = "/tmp/main.rs", variables = {variables}, windows = {{ 1000 }},
+                }}"#
+            )
+        }
+
+        #[test]
+        fn hl_text_is_a_pair() -> LuaResult<()> {
+            let mock = NeoMock::new()?;
+
+            let value = HLText::new("foo", "Comment").into_lua(&mock)?;
+            assert_eq!(Vec::<String>::from_lua(value, &mock)?, ["foo", "Comment"]);
+
+            let value = mock.load("return { 'bar', 'Error' }").eval()?;
+            assert_eq!(HLText::from_lua(value, &mock)?, HLText::new("bar", "Error"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn win_cursor_is_row_col() -> LuaResult<()> {
+            let mock = NeoMock::new()?;
+
+            let value = WinCursor::from_zero_indexed(2, 7).into_lua(&mock)?;
+            assert_eq!(Vec::<u32>::from_lua(value, &mock)?, [3, 7]);
+
+            let value = mock.load("return { 4, 1 }").eval()?;
+            let cursor = WinCursor::from_lua(value, &mock)?;
+            assert_eq!(cursor.row_zero_indexed(), 3);
+            assert_eq!(cursor.column, 1);
+
+            Ok(())
+        }
+
+        #[test]
+        fn buf_info_variables_are_a_dict_or_a_list() -> LuaResult<()> {
+            let mock = NeoMock::new()?;
+
+            let value = mock
+                .load(bufinfo_lua("{ changedtick = 3, undo_ftplugin = 'setl' }"))
+                .eval()?;
+            let info = BufInfo::from_lua(value, &mock)?;
+
+            let mut variables = info.variables.clone();
+            variables.sort();
+            assert_eq!(variables, ["changedtick", "undo_ftplugin"]);
+            assert!(info.changed && info.listed && info.loaded);
+            assert!(!info.command && !info.hidden);
+            assert_eq!(info.windows, [1000]);
+
+            let value = mock.load(bufinfo_lua("{ 'changedtick' }")).eval()?;
+            let info = BufInfo::from_lua(value, &mock)?;
+            assert_eq!(info.variables, ["changedtick"]);
+
+            Ok(())
+        }
+    }
+}
@@ -1,14 +1,17 @@
-use crate::{HLText, NeoApi, NeoBuffer, NeoWindow, TextType};
+use crate::{HLText, NeoApi, NeoBuffer, NeoLuaExt, NeoWindow, TextType};
+use macros::{FromLuaSerde, IntoEnumSC, IntoLuaSerde};
 use mlua::{
     prelude::{LuaFunction, LuaResult, LuaValue},
-    FromLua, IntoLua, Lua,
+    IntoLua, Lua,
 };
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display},
     time::Duration,
 };
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PopupRelative {
     #[default]
     Win,
@@ -28,12 +31,16 @@ impl fmt::Display for PopupRelative {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Anchor {
     #[default]
+    #[serde(rename = "NW")]
     NorthWest,
+    #[serde(rename = "NE")]
     NorthEast,
+    #[serde(rename = "SW")]
     SouthWest,
+    #[serde(rename = "SE")]
     SouthEast,
 }
 
@@ -48,7 +55,8 @@ impl fmt::Display for Anchor {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PopupStyle {
     #[default]
     Minimal,
@@ -60,7 +68,9 @@ impl fmt::Display for PopupStyle {
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, IntoEnumSC, Serialize, Deserialize)]
+#[lua(untagged)]
+#[serde(into = "BorderRepr", try_from = "BorderRepr")]
 pub enum PopupBorder {
     #[default]
     None,
@@ -69,24 +79,45 @@ pub enum PopupBorder {
     Rounded,
     Solid,
     Shadow,
+    /// Border chars clockwise from the top-left corner
     Custom(Vec<String>),
 }
 
-impl fmt::Display for PopupBorder {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::None => f.write_str("none"),
-            Self::Single => f.write_str("single"),
-            Self::Double => f.write_str("double"),
-            Self::Rounded => f.write_str("rounded"),
-            Self::Solid => f.write_str("solid"),
-            Self::Shadow => f.write_str("shadow"),
-            Self::Custom(cus) => f.write_str(&cus.join(",")),
+/// Same representation as in Lua, a name or the border chars
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BorderRepr {
+    Name(String),
+    Custom(Vec<String>),
+    /// `[char, highlight]` tuples, the highlights are dropped
+    Highlighted(Vec<(String, String)>),
+}
+
+impl From<PopupBorder> for BorderRepr {
+    fn from(border: PopupBorder) -> Self {
+        match border {
+            PopupBorder::Custom(chars) => Self::Custom(chars),
+            border => Self::Name(border.to_string()),
+        }
+    }
+}
+
+impl TryFrom<BorderRepr> for PopupBorder {
+    type Error = String;
+
+    fn try_from(repr: BorderRepr) -> Result<Self, Self::Error> {
+        match repr {
+            BorderRepr::Name(name) => name.parse(),
+            BorderRepr::Custom(chars) => Ok(Self::Custom(chars)),
+            BorderRepr::Highlighted(chars) => Ok(Self::Custom(
+                chars.into_iter().map(|(char, _)| char).collect(),
+            )),
         }
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PopupAlign {
     #[default]
     Left,
@@ -104,7 +135,8 @@ impl fmt::Display for PopupAlign {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PopupSplit {
     #[default]
     Left,
@@ -124,14 +156,43 @@ impl fmt::Display for PopupSplit {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Serialized as `{ fixed = n }` or `{ percent = f }`. A plain number (e.g. from Neovim) is
+/// fixed when it's whole and a percentage otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SizeRepr")]
 pub enum PopupSize {
+    #[serde(rename = "fixed")]
     Fixed(u32),
     /// Between 0 and 1
+    #[serde(rename = "percent")]
     Percentage(f32),
 }
 
-#[derive(Default)]
+/// LuaJIT passes whole floats as integers, so only the tagged form keeps `Percentage(1.0)`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeRepr {
+    Number(f64),
+    Fixed { fixed: u32 },
+    Percent { percent: f32 },
+}
+
+impl From<SizeRepr> for PopupSize {
+    fn from(repr: SizeRepr) -> Self {
+        match repr {
+            SizeRepr::Number(size) if size >= 0.0 && size.fract() == 0.0 => {
+                Self::Fixed(size as u32)
+            }
+            SizeRepr::Number(size) => Self::Percentage(size as f32),
+            SizeRepr::Fixed { fixed } => Self::Fixed(fixed),
+            SizeRepr::Percent { percent } => Self::Percentage(percent),
+        }
+    }
+}
+
+/// Serialized without resolving the sizes, IntoLua resolves them against the UI
+#[derive(Debug, Default, Clone, Serialize, Deserialize, FromLuaSerde)]
+#[serde(default)]
 pub struct WinOptions {
     /// width: Window width (in character cells). Minimum of 1.
     pub width: Option<PopupSize>,
@@ -151,6 +212,8 @@ pub struct WinOptions {
     • "mouse" Mouse position
     */
     pub relative: PopupRelative,
+    /// bufpos: Places float relative to buffer text (only when relative="win").
+    #[serde(rename = "bufpos")]
     pub buf_pos: Option<(u32, u32)>,
     ///  win: |window-ID| window to split, or relative window when creating a float (relative="win").
     pub win: Option<u32>,
@@ -325,7 +388,7 @@ impl IntoLua for WinOptions {
         out.set("row", raw_row)?;
         out.set("col", raw_col)?;
         out.set("anchor", self.anchor.to_string())?;
-        out.set("border", self.border.to_string())?;
+        out.set("border", self.border)?;
         out.set("noautocmd", self.noautocmd)?;
 
         if let Some(style) = self.style {
@@ -346,44 +409,74 @@ impl IntoLua for WinOptions {
             out.set("footer_pos", self.footer_pos.to_string())?;
         }
 
-        Ok(LuaValue::Table(out))
-    }
-}
+        if let Some(win) = self.win {
+            out.set("win", win)?;
+        }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
-pub struct NeoPopup {
-    pub win: NeoWindow,
-    pub buf: NeoBuffer,
-}
+        if let Some((line, column)) = self.buf_pos {
+            out.set("bufpos", [line, column])?;
+        }
 
-impl IntoLua for NeoPopup {
-    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
-        let out = lua.create_table()?;
-        out.set("win", self.win.id())?;
-        out.set("buf", self.buf.id())?;
+        // Zero isn't a valid zindex, Neovim uses 50 by default
+        if 0 < self.zindex {
+            out.set("zindex", self.zindex)?;
+        }
+
+        if self.external {
+            out.set("external", true)?;
+        }
+
+        if self.fixed {
+            out.set("fixed", true)?;
+        }
+
+        if self.hide {
+            out.set("hide", true)?;
+        }
 
         Ok(LuaValue::Table(out))
     }
 }
 
-impl FromLua for NeoPopup {
-    fn from_lua(value: LuaValue, _lua: &Lua) -> LuaResult<Self> {
-        if let LuaValue::Table(table) = value {
-            let win_id = table.get("win")?;
-            let buf_id = table.get("buf")?;
-
-            Ok(NeoPopup {
-                win: NeoWindow::new(win_id),
-                buf: NeoBuffer::from_id(buf_id),
-            })
-        } else {
-            Err(mlua::Error::FromLuaConversionError {
-                from: value.type_name(),
-                to: "NeoPopup",
-                message: None,
-            })
-        }
-    }
+/// Config of an existing window, see |nvim_win_get_config()| and |nvim_win_set_config()|.
+///
+/// Unlike `WinOptions` the sizes and positions are in cells and only the fields which are set
+/// are sent, so the other fields keep their value.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, IntoLuaSerde, FromLuaSerde)]
+#[serde(default)]
+pub struct WinConfig {
+    /// None for splits, must be set together with `row` and `col`
+    pub relative: Option<PopupRelative>,
+    /// Relative window of a float or the window to split
+    pub win: Option<u32>,
+    pub anchor: Option<Anchor>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Position of a float in cells, may be fractional
+    pub row: Option<f64>,
+    pub col: Option<f64>,
+    pub bufpos: Option<(u32, u32)>,
+    pub focusable: Option<bool>,
+    pub external: Option<bool>,
+    pub zindex: Option<u32>,
+    pub style: Option<PopupStyle>,
+    pub border: Option<PopupBorder>,
+    pub title: Option<TextType>,
+    pub title_pos: Option<PopupAlign>,
+    pub footer: Option<TextType>,
+    pub footer_pos: Option<PopupAlign>,
+    pub fixed: Option<bool>,
+    pub hide: Option<bool>,
+    pub vertical: Option<bool>,
+    pub split: Option<PopupSplit>,
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize, IntoLuaSerde, FromLuaSerde,
+)]
+pub struct NeoPopup {
+    pub win: NeoWindow,
+    pub buf: NeoBuffer,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -424,7 +517,7 @@ impl NeoPopup {
         enter: bool,
         config: WinOptions,
     ) -> LuaResult<NeoWindow> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_open_win").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_open_win")?;
 
         let win_id = lfn.call((buf.id(), enter, config))?;
 
@@ -462,3 +555,200 @@ impl NeoPopup {
         NeoApi::delay(lua, options.duration.as_millis() as u32, close_popup)
     }
 }
+
+#[cfg(all(test, feature = "test-harness"))]
+mod tests {
+    use super::*;
+    use crate::NeoMock;
+    use mlua::prelude::{LuaTable, LuaValue};
+    use mlua::{FromLua, LuaSerdeExt};
+
+    fn mock_ui(mock: &NeoMock, width: u32, height: u32) -> LuaResult<()> {
+        let uis: LuaTable = mock
+            .load(format!(
+                r#"return {{ {{
+                    chan = 1, ext_cmdline = false, ext_hlstate = false, ext_linegrid = true,
+                    ext_messages = false, ext_multigrid = false, ext_popupmenu = false,
+                    ext_tabline = false, ext_termcolors = true, ext_wildmenu = false,
+                    ["override"] = false, rgb = true, stdin_tty = true, stdout_tty = true,
+                    term_background = "dark", term_colors = 256, term_name = "xterm",
+                    width = {width}, height = {height},
+                }} }}"#
+            ))
+            .eval()?;
+
+        mock.returns("vim.api.nvim_list_uis", uis)
+    }
+
+    fn into_table(mock: &NeoMock, opts: WinOptions) -> LuaResult<LuaTable> {
+        LuaTable::from_lua(opts.into_lua(mock)?, mock)
+    }
+
+    #[test]
+    fn sizes_resolve_against_the_ui() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        mock_ui(&mock, 200, 50)?;
+
+        let opts = into_table(
+            &mock,
+            WinOptions {
+                relative: PopupRelative::Editor,
+                width: Some(PopupSize::Percentage(0.5)),
+                height: Some(PopupSize::Fixed(10)),
+                row: Some(PopupSize::Percentage(0.1)),
+                col: Some(PopupSize::Fixed(3)),
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(opts.get::<String>("relative")?, "editor");
+        assert_eq!(opts.get::<u32>("width")?, 100);
+        assert_eq!(opts.get::<u32>("height")?, 10);
+        assert_eq!(opts.get::<u32>("row")?, 5);
+        assert_eq!(opts.get::<u32>("col")?, 3);
+        assert_eq!(mock.call_count("vim.api.nvim_list_uis"), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unset_options_are_left_out() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        mock_ui(&mock, 200, 50)?;
+
+        let opts = into_table(&mock, WinOptions::default())?;
+
+        for key in ["style", "focusable", "title", "footer", "win", "bufpos"] {
+            assert!(!opts.contains_key(key)?, "{key} is set");
+        }
+
+        // Zero isn't a valid zindex
+        assert!(!opts.contains_key("zindex")?);
+
+        let opts = into_table(
+            &mock,
+            WinOptions {
+                style: Some(PopupStyle::Minimal),
+                title: Some(TextType::String("title".to_string())),
+                title_pos: PopupAlign::Center,
+                zindex: 60,
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(opts.get::<String>("style")?, "minimal");
+        assert_eq!(opts.get::<String>("title")?, "title");
+        assert_eq!(opts.get::<String>("title_pos")?, "center");
+        assert_eq!(opts.get::<u32>("zindex")?, 60);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sizes_keep_their_kind() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+
+        for size in [
+            PopupSize::Fixed(0),
+            PopupSize::Fixed(10),
+            PopupSize::Percentage(0.0),
+            PopupSize::Percentage(0.5),
+            PopupSize::Percentage(1.0),
+        ] {
+            let value = mock.to_value(&size)?;
+            assert_eq!(mock.from_value::<PopupSize>(value)?, size);
+
+            let json = serde_json::to_string(&size).unwrap();
+            assert_eq!(serde_json::from_str::<PopupSize>(&json).unwrap(), size);
+        }
+
+        assert_eq!(
+            serde_json::to_string(&PopupSize::Percentage(1.0)).unwrap(),
+            r#"{"percent":1.0}"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn plain_numbers_are_sizes() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+
+        assert_eq!(
+            mock.from_value::<PopupSize>(LuaValue::Integer(3))?,
+            PopupSize::Fixed(3)
+        );
+        assert_eq!(
+            mock.from_value::<PopupSize>(LuaValue::Number(0.5))?,
+            PopupSize::Percentage(0.5)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn border_is_a_name_or_chars() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        let chars = ["╭", "─", "╮", "│", "╯", "─", "╰", "│"]
+            .map(String::from)
+            .to_vec();
+
+        let border: PopupBorder = mock.from_value(mock.load("'rounded'").eval()?)?;
+        assert_eq!(border, PopupBorder::Rounded);
+
+        let value = mock
+            .load("{ '╭', '─', '╮', '│', '╯', '─', '╰', '│' }")
+            .eval()?;
+        let border: PopupBorder = mock.from_value(value)?;
+        assert_eq!(border, PopupBorder::Custom(chars.clone()));
+
+        // Highlights of the chars are dropped
+        let value = mock
+            .load(
+                r#"
+                local chars = { '╭', '─', '╮', '│', '╯', '─', '╰', '│' }
+                local border = {}
+
+                for i, char in ipairs(chars) do
+                    border[i] = { char, 'FloatBorder' }
+                end
+
+                return border
+                "#,
+            )
+            .eval()?;
+        let border: PopupBorder = mock.from_value(value)?;
+        assert_eq!(border, PopupBorder::Custom(chars.clone()));
+
+        for border in [PopupBorder::Single, PopupBorder::Custom(chars)] {
+            let value = mock.to_value(&border)?;
+            assert_eq!(mock.from_value::<PopupBorder>(value)?, border);
+        }
+
+        assert!(mock
+            .from_value::<PopupBorder>(mock.load("'dotted'").eval()?)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn win_options_survive_json() {
+        let opts = WinOptions {
+            width: Some(PopupSize::Percentage(1.0)),
+            height: Some(PopupSize::Fixed(10)),
+            relative: PopupRelative::Editor,
+            border: PopupBorder::Custom(vec!["+".to_string()]),
+            title: Some(TextType::String("title".to_string())),
+            title_pos: PopupAlign::Center,
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&opts).unwrap();
+        let back: WinOptions = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+        assert_eq!(back.width, Some(PopupSize::Percentage(1.0)));
+        assert_eq!(back.border, PopupBorder::Custom(vec!["+".to_string()]));
+    }
+}
@@ -0,0 +1,223 @@
+use mlua::prelude::{LuaRegistryKey, LuaResult, LuaValue};
+use mlua::Lua;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex, PoisonError};
+use tokio::sync::oneshot;
+
+use crate::{NeoApi, NeoDebug, NeoError, NeoLuaExt, NeoResult};
+
+type Job = Box<dyn FnOnce(&Lua) -> LuaResult<()> + Send>;
+
+static SCHEDULER: LazyLock<Scheduler> = LazyLock::new(|| Scheduler {
+    jobs: Mutex::new(VecDeque::new()),
+    waker: Mutex::new(None),
+    wake_pending: AtomicBool::new(false),
+});
+
+struct Scheduler {
+    jobs: Mutex<VecDeque<Job>>,
+    waker: Mutex<Option<Waker>>,
+    /// Jobs are queued and no drain is scheduled yet, also prevents filling the socket buffer
+    /// when many jobs are scheduled before a drain
+    wake_pending: AtomicBool,
+}
+
+/// Keeps the uv handle of the Lua state alive
+struct SchedulerHandle(#[allow(dead_code)] LuaRegistryKey);
+
+/// Runs closures from background (tokio) tasks on the Neovim main thread.
+///
+/// On unix a byte written to a socket pair wakes the `vim.uv` loop, other platforms poll the
+/// queue with a `vim.uv` timer. The callback is wrapped with `vim.schedule_wrap` so the jobs
+/// can use the whole API.
+///
+/// ```ignore
+/// RTM.spawn(async move {
+///     let lines = fetch().await;
+///     NeoScheduler::schedule(move |lua| buf.set_lines(lua, 0, -1, false, &lines));
+/// });
+/// ```
+pub struct NeoScheduler;
+
+impl NeoScheduler {
+    /// Starts watching for scheduled jobs, jobs scheduled before are run after this call.
+    /// Calling it again on the same Lua state does nothing.
+    pub fn init(lua: &Lua) -> LuaResult<()> {
+        if lua.app_data_ref::<SchedulerHandle>().is_some() {
+            return Ok(());
+        }
+
+        let (handle, waker) = Waker::start(lua)?;
+
+        lua.set_app_data(SchedulerHandle(lua.create_registry_value(handle)?));
+
+        *SCHEDULER
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(waker);
+        SCHEDULER.wake_pending.store(false, Ordering::Release);
+
+        if !Self::is_empty() {
+            Self::wake();
+        }
+
+        Ok(())
+    }
+
+    /// Queues a closure which runs on the main thread, errors are logged.
+    pub fn schedule<F>(job: F)
+    where
+        F: FnOnce(&Lua) -> LuaResult<()> + Send + 'static,
+    {
+        SCHEDULER
+            .jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(Box::new(job));
+
+        Self::wake();
+    }
+
+    /// Runs the closure on the main thread and waits for its result.
+    pub async fn call<F, R>(job: F) -> NeoResult<R>
+    where
+        F: FnOnce(&Lua) -> LuaResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        Self::schedule(move |lua| {
+            let _ = tx.send(job(lua));
+            Ok(())
+        });
+
+        match rx.await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(NeoError::Cancelled),
+        }
+    }
+
+    fn is_empty() -> bool {
+        SCHEDULER
+            .jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_empty()
+    }
+
+    fn wake() {
+        if SCHEDULER.wake_pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let waker = SCHEDULER
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match waker.as_ref() {
+            Some(waker) => waker.wake(),
+            // Not initialized, init will wake
+            None => SCHEDULER.wake_pending.store(false, Ordering::Release),
+        }
+    }
+
+    fn drain(lua: &Lua) {
+        SCHEDULER.wake_pending.store(false, Ordering::Release);
+
+        loop {
+            let job = SCHEDULER
+                .jobs
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .pop_front();
+
+            let Some(job) = job else {
+                return;
+            };
+
+            if let Err(err) = job(lua) {
+                NeoDebug::error(err);
+            }
+        }
+    }
+}
+
+/// Write end of a socket pair, the read end is watched by a `vim.uv` pipe
+#[cfg(unix)]
+struct Waker(std::os::unix::net::UnixStream);
+
+#[cfg(unix)]
+impl Waker {
+    /// Returns the uv pipe and the waker
+    fn start(lua: &Lua) -> LuaResult<(LuaValue, Self)> {
+        use std::os::fd::IntoRawFd;
+        use std::os::unix::net::UnixStream;
+
+        let (reader, writer) = UnixStream::pair()?;
+        writer.set_nonblocking(true)?;
+        reader.set_nonblocking(true)?;
+
+        let new_pipe = lua.neo_fn("vim.uv.new_pipe")?;
+        let pipe: LuaValue = new_pipe.call(false)?;
+
+        // The uv pipe takes ownership of the file descriptor
+        let pipe_open = lua.neo_fn("vim.uv.pipe_open")?;
+        pipe_open.call::<()>((pipe.clone(), reader.into_raw_fd()))?;
+
+        let drain = lua.create_function(|lua, _: mlua::MultiValue| {
+            NeoScheduler::drain(lua);
+            Ok(())
+        })?;
+
+        let read_start = lua.neo_fn("vim.uv.read_start")?;
+        read_start.call::<()>((pipe.clone(), NeoApi::schedule_wrap(lua, drain)?))?;
+
+        Ok((pipe, Self(writer)))
+    }
+
+    fn wake(&self) {
+        use std::io::Write;
+
+        // WouldBlock means the loop has unread bytes, so it wakes anyway
+        let _ = (&self.0).write(&[1]);
+    }
+}
+
+/// A `vim.uv` timer polls for pending jobs, the socket pair needs unix domain sockets
+#[cfg(not(unix))]
+struct Waker;
+
+#[cfg(not(unix))]
+impl Waker {
+    const POLL_MS: u32 = 10;
+
+    /// Returns the uv timer and the waker
+    fn start(lua: &Lua) -> LuaResult<(LuaValue, Self)> {
+        let new_timer = lua.neo_fn("vim.uv.new_timer")?;
+        let timer: LuaValue = new_timer.call(())?;
+
+        let drain = lua.create_function(|lua, ()| {
+            NeoScheduler::drain(lua);
+            Ok(())
+        })?;
+        let drain = NeoApi::schedule_wrap(lua, drain)?;
+
+        // Runs in a fast event, so the drain itself is scheduled
+        let poll = lua.create_function(move |_, ()| {
+            if SCHEDULER.wake_pending.load(Ordering::Acquire) {
+                drain.call::<()>(())?;
+            }
+
+            Ok(())
+        })?;
+
+        let timer_start = lua.neo_fn("vim.uv.timer_start")?;
+        timer_start.call::<()>((timer.clone(), Self::POLL_MS, Self::POLL_MS, poll))?;
+
+        Ok((timer, Self))
+    }
+
+    fn wake(&self) {}
+}
@@ -5,9 +5,9 @@ use std::{
 };
 
 use crate::{
-    search::{FuzzyTab, TaskResult},
+    search::{FuzzyTab, SearchUpdate, TaskKind},
     web_devicons::DevIcon,
-    BufInfo, BufInfoOpts, ExecuteTask, LineOut, NeoApi, NeoDebug, NeoUtils, CONTAINER,
+    BufInfo, BufInfoOpts, ExecuteTask, LineOut, NeoApi, NeoDebug, NeoResult, NeoUtils, CONTAINER,
 };
 
 pub struct BufferSearch {
@@ -31,7 +31,7 @@ impl BufferSearch {
         })
     }
 
-    async fn init(&self) -> TaskResult {
+    async fn init(&self) -> NeoResult<SearchUpdate> {
         let mut new_lines = vec![];
         let mut tabs: Vec<Box<dyn FuzzyTab>> = vec![];
 
@@ -49,13 +49,18 @@ impl BufferSearch {
             let buf_path: PathBuf = buf_info.name.as_str().into();
             let dev_icon = DevIcon::get_icon(&buf_path);
 
-            if let Some(git_root) = NeoUtils::git_root(&buf_path) {
+            // git prints the resolved root, a buffer opened through a symlink isn't below it
+            let in_git_root = NeoUtils::git_root(&buf_path).and_then(|git_root| {
                 let path_suffix: Box<str> = buf_path
                     .strip_prefix(&git_root)
-                    .unwrap()
+                    .ok()?
                     .to_string_lossy()
                     .into();
 
+                Some((git_root, path_suffix))
+            });
+
+            if let Some((git_root, path_suffix)) = in_git_root {
                 let git_root_str: Box<str> = git_root.to_string_lossy().into();
                 let tab = Box::new(git_root);
 
@@ -83,29 +88,26 @@ impl BufferSearch {
             tabs.push(Box::new(" other ".to_string()));
         }
 
-        if let Err(e) = CONTAINER.db.insert_all(&new_lines).await {
-            NeoDebug::log_dbg(e).await;
-        }
+        let db = CONTAINER.db().await?;
+        db.insert_all(&new_lines).await?;
 
-        let new_lines = CONTAINER
-            .db
+        let new_lines = db
             .search_project_lines("", tabs[self.selected_tab].full())
             .await;
 
-        NeoDebug::log_dbg(&new_lines).await;
-        let db_count = new_lines.len();
-        *CONTAINER.search_lines.write().await = new_lines;
+        NeoDebug::log_dbg(&new_lines);
 
-        TaskResult {
-            db_count: Some(db_count),
+        Ok(SearchUpdate {
+            db_count: Some(new_lines.len()),
+            lines: Some(new_lines),
             tabs: Some(tabs),
             selected_tab: Some(0),
             selected_idx: Some(0),
             update: true,
-        }
+        })
     }
 
-    async fn search(&self) -> TaskResult {
+    async fn search(&self) -> NeoResult<SearchUpdate> {
         let search_state = CONTAINER.search_state.read().await;
 
         let other_tab = search_state.tabs.len() - 1 == search_state.selected_tab;
@@ -117,28 +119,36 @@ impl BufferSearch {
         };
 
         let lines = CONTAINER
-            .db
+            .db()
+            .await?
             .search_project_lines(&self.search_query, tab)
             .await;
 
-        *CONTAINER.search_lines.write().await = lines;
-
-        TaskResult {
+        Ok(SearchUpdate {
+            lines: Some(lines),
             update: true,
             ..Default::default()
-        }
+        })
     }
 }
 
 #[async_trait::async_trait]
 impl ExecuteTask for BufferSearch {
-    async fn execute(&self) -> TaskResult {
+    fn kind(&self) -> TaskKind {
+        TaskKind::Search
+    }
+
+    async fn populate(&self) -> NeoResult<SearchUpdate> {
         if self.all_lines_is_empty().await {
             self.init().await
         } else {
-            self.search().await
+            Ok(SearchUpdate::default())
         }
     }
+
+    async fn execute(&self) -> NeoResult<SearchUpdate> {
+        self.search().await
+    }
 }
 
 pub struct RemoveBuffer {
@@ -147,5 +157,5 @@ pub struct RemoveBuffer {
 
 //#[async_trait::async_trait]
 //impl ExecuteTask for RemoveBuffer {
-//async fn execute(&self) -> TaskResult {}
+//async fn execute(&self) -> SearchUpdate {}
 //}
@@ -4,20 +4,45 @@
 // Create Linked list with actions
 // Try to lock do something then next
 
-use std::{borrow::Cow, fmt, path::PathBuf, sync::LazyLock};
-use tokio::sync::Mutex;
-
-use crate::{NeoDebug, CONTAINER, RTM};
-
-static DIFFUSER: LazyLock<Mutex<Diffuse>> = LazyLock::new(|| Diffuse::default().into());
+use std::{
+    borrow::Cow,
+    fmt,
+    future::Future,
+    path::PathBuf,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use crate::{
+    FuzzyRender, JobOpts, JobQueue, LineOut, NeoDebug, NeoError, NeoResult, CONTAINER, RTM,
+};
+
+static SEARCH_JOBS: LazyLock<JobQueue> = LazyLock::new(JobQueue::default);
+
+/// Queued tasks of the same kind (except `Populate` and `Other`) are superseded by newer ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TaskKind {
+    /// Queued after a `Populate` task that fills the database first
+    Search,
+    Preview,
+    /// `ExecuteTask::populate` of a search, typing must not interrupt it halfway
+    Populate,
+    Other,
+}
 
-#[derive(Default)]
-pub struct Diffuse {
-    queue: Vec<Box<dyn ExecuteTask>>,
-    is_running: bool,
+impl TaskKind {
+    fn key(&self) -> Option<&'static str> {
+        match self {
+            Self::Search => Some("search"),
+            Self::Preview => Some("preview"),
+            Self::Populate | Self::Other => None,
+        }
+    }
 }
 
-unsafe impl Send for Diffuse {}
+/// Fuzzy finder tasks on top of a `JobQueue`, the `SearchUpdate` of a task is written into
+/// `CONTAINER.search_state`.
+pub struct Diffuse;
 
 pub trait FuzzyTab: Send + Sync {
     fn name(&self) -> Cow<'_, str>;
@@ -53,7 +78,10 @@ impl FuzzyTab for String {
 
 impl FuzzyTab for PathBuf {
     fn name(&self) -> Cow<'_, str> {
-        self.file_name().expect("Has no filename").to_string_lossy()
+        match self.file_name() {
+            Some(file_name) => file_name.to_string_lossy(),
+            None => self.to_string_lossy(),
+        }
     }
 
     fn full(&self) -> Cow<'_, str> {
@@ -62,7 +90,9 @@ impl FuzzyTab for PathBuf {
 }
 
 #[derive(Default)]
-pub struct TaskResult {
+pub struct SearchUpdate {
+    /// Written into `CONTAINER.search_lines`, only when the task wasn't superseded
+    pub lines: Option<Vec<LineOut>>,
     pub db_count: Option<usize>,
     pub selected_idx: Option<usize>,
     pub selected_tab: Option<usize>,
@@ -70,9 +100,10 @@ pub struct TaskResult {
     pub update: bool,
 }
 
-impl TaskResult {
+impl SearchUpdate {
     pub fn has_changes(&self) -> bool {
         self.update
+            || self.lines.is_some()
             || self.db_count.is_some()
             || self.selected_idx.is_some()
             || self.selected_tab.is_some()
@@ -81,71 +112,291 @@ impl TaskResult {
 }
 
 #[async_trait::async_trait]
-pub trait ExecuteTask: Send {
-    async fn execute(&self) -> TaskResult;
+pub trait ExecuteTask: Send + Sync {
+    async fn execute(&self) -> NeoResult<SearchUpdate>;
+
+    fn kind(&self) -> TaskKind {
+        TaskKind::Other
+    }
+
+    /// Fills the database when it is empty, runs before a `TaskKind::Search` task.
+    async fn populate(&self) -> NeoResult<SearchUpdate> {
+        Ok(SearchUpdate::default())
+    }
 
     async fn all_lines_is_empty(&self) -> bool {
-        CONTAINER.db.all_lines_is_empty().await
+        match CONTAINER.db().await {
+            Ok(db) => db.all_lines_is_empty().await,
+            Err(err) => {
+                NeoDebug::log(err);
+                true
+            }
+        }
     }
 }
 
 impl Diffuse {
+    /// Queues the tasks, a task supersedes queued tasks of the same kind and cancels the
+    /// running one.
     pub async fn queue<const ARR_SIZE: usize>(task_list: [Box<dyn ExecuteTask>; ARR_SIZE]) {
-        let mut diffuser = DIFFUSER.lock().await;
+        for task in task_list {
+            Self::push(task);
+        }
+    }
+
+    pub fn push(task: Box<dyn ExecuteTask>) {
+        let task: Arc<dyn ExecuteTask> = task.into();
+        let kind = task.kind();
+
+        if kind == TaskKind::Search {
+            let populate = task.clone();
 
-        for new_task in task_list {
-            diffuser.queue.push(new_task);
+            Self::push_job(TaskKind::Populate, async move { populate.populate().await });
         }
 
-        if !diffuser.is_running {
-            diffuser.is_running = true;
-            Self::start();
+        Self::push_job(kind, async move { task.execute().await });
+    }
+
+    fn push_job<F>(kind: TaskKind, execute: F)
+    where
+        F: Future<Output = NeoResult<SearchUpdate>> + Send + 'static,
+    {
+        let opts = JobOpts {
+            key: kind.key(),
+            ..Default::default()
+        };
+
+        SEARCH_JOBS.push(
+            opts,
+            move |token| async move {
+                let update = execute.await?;
+
+                // A newer task of the same kind will update the state
+                token.check()?;
+
+                // Spawned, so a cancel while waiting for the locks can't leave the state
+                // half updated
+                RTM.spawn(Self::apply(update))
+                    .await
+                    .map_err(|_| NeoError::Cancelled)
+            },
+            |result| match result {
+                Ok(()) | Err(NeoError::Cancelled) => {}
+                Err(err) => NeoDebug::error(err),
+            },
+        );
+    }
+
+    /// Removes queued tasks of this kind and cancels the running one.
+    pub fn cancel(kind: TaskKind) {
+        if let Some(key) = kind.key() {
+            SEARCH_JOBS.cancel(key);
         }
     }
 
-    pub fn start() {
-        RTM.spawn(async {
-            loop {
-                let mut diffuser = DIFFUSER.lock().await;
+    /// Waits before executing a task of this kind, a newer task arriving in the meantime
+    /// replaces it. Useful for search queries while typing.
+    pub fn set_debounce(kind: TaskKind, debounce: Duration) {
+        if let Some(key) = kind.key() {
+            SEARCH_JOBS.set_debounce(key, debounce);
+        }
+    }
 
-                if diffuser.queue.is_empty() {
-                    diffuser.is_running = false;
-                    return;
-                }
+    async fn apply(update: SearchUpdate) {
+        if !update.has_changes() {
+            return;
+        }
 
-                let queue = std::mem::take(&mut diffuser.queue);
+        if let Some(lines) = update.lines {
+            *CONTAINER.search_lines.write().await = lines;
+        }
 
-                drop(diffuser);
+        let mut search_state = CONTAINER.search_state.write().await;
 
-                for current in queue {
-                    let result = current.execute().await;
+        if let Some(db_count) = update.db_count {
+            search_state.db_count = db_count;
+        }
 
-                    if result.has_changes() {
-                        let mut search_state = CONTAINER.search_state.write().await;
+        if let Some(selected_idx) = update.selected_idx {
+            search_state.selected_idx = selected_idx;
+        }
 
-                        if let Some(db_count) = result.db_count {
-                            search_state.db_count = db_count;
-                        }
+        if let Some(selected_tab) = update.selected_tab {
+            search_state.selected_tab = selected_tab;
+        }
 
-                        if let Some(selected_idx) = result.selected_idx {
-                            search_state.selected_idx = selected_idx;
-                        }
+        if let Some(tabs) = update.tabs {
+            NeoDebug::log_dbg(&tabs);
+            search_state.tabs = tabs;
+        }
 
-                        if let Some(selected_tab) = result.selected_tab {
-                            search_state.selected_tab = selected_tab;
-                        }
+        if update.update {
+            search_state.update = true;
+        }
 
-                        if let Some(tabs) = result.tabs {
-                            NeoDebug::log_dbg(&tabs).await;
-                            search_state.tabs = tabs;
-                        }
+        // Render once the state is complete, e.g. after the preview
+        if search_state.update {
+            search_state.update = false;
+            drop(search_state);
 
-                        if result.update {
-                            search_state.update = true;
-                        }
-                    }
-                }
+            FuzzyRender::schedule().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, PoisonError};
+    use tokio::sync::oneshot;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Tests share `CONTAINER` and `SEARCH_JOBS`
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// Search task, the execute waits for the gate when there is one
+    struct Search {
+        lines: &'static [&'static str],
+        selected_idx: Option<usize>,
+        gate: Mutex<Option<oneshot::Receiver<()>>>,
+    }
+
+    impl Search {
+        fn new(lines: &'static [&'static str], selected_idx: Option<usize>) -> Box<Self> {
+            Box::new(Self {
+                lines,
+                selected_idx,
+                gate: Mutex::new(None),
+            })
+        }
+
+        fn gated(
+            lines: &'static [&'static str],
+            selected_idx: Option<usize>,
+        ) -> (Box<Self>, oneshot::Sender<()>) {
+            let (release, gate) = oneshot::channel();
+            let search = Self::new(lines, selected_idx);
+            *search.gate.lock().unwrap() = Some(gate);
+
+            (search, release)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ExecuteTask for Search {
+        async fn execute(&self) -> NeoResult<SearchUpdate> {
+            let gate = self.gate.lock().unwrap().take();
+
+            if let Some(gate) = gate {
+                let _ = gate.await;
+            }
+
+            Ok(SearchUpdate {
+                lines: Some(
+                    self.lines
+                        .iter()
+                        .map(|line| LineOut::directory(line))
+                        .collect(),
+                ),
+                selected_idx: self.selected_idx,
+                ..Default::default()
+            })
+        }
+
+        fn kind(&self) -> TaskKind {
+            TaskKind::Search
+        }
+    }
+
+    /// Resolves once every task queued before it is done
+    struct Barrier(Mutex<Option<oneshot::Sender<()>>>);
+
+    #[async_trait::async_trait]
+    impl ExecuteTask for Barrier {
+        async fn execute(&self) -> NeoResult<SearchUpdate> {
+            if let Some(done) = self.0.lock().unwrap().take() {
+                let _ = done.send(());
             }
+
+            Ok(SearchUpdate::default())
+        }
+    }
+
+    async fn barrier() {
+        let (done, wait) = oneshot::channel();
+        Diffuse::push(Box::new(Barrier(Mutex::new(Some(done)))));
+
+        tokio::time::timeout(TIMEOUT, wait).await.unwrap().unwrap();
+    }
+
+    async fn search_lines() -> Vec<String> {
+        let lines = CONTAINER.search_lines.read().await;
+
+        lines.iter().map(|line| line.text.to_string()).collect()
+    }
+
+    async fn reset() {
+        *CONTAINER.search_lines.write().await = vec![];
+        CONTAINER.search_state.write().await.selected_idx = 0;
+    }
+
+    #[test]
+    fn superseded_searches_never_overwrite_newer_lines() {
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+
+        RTM.block_on(async {
+            reset().await;
+
+            let (a, release_a) = Search::gated(&["a"], Some(1));
+            let (ab, release_ab) = Search::gated(&["ab"], Some(2));
+
+            Diffuse::push(a);
+            Diffuse::push(ab);
+            Diffuse::push(Search::new(&["abc"], Some(3)));
+
+            // Superseded tasks are dropped, the receivers may be gone
+            let _ = release_a.send(());
+            let _ = release_ab.send(());
+
+            barrier().await;
+
+            assert_eq!(search_lines().await, ["abc"]);
+            assert_eq!(CONTAINER.search_state.read().await.selected_idx, 3);
+        });
+    }
+
+    #[test]
+    fn cancel_during_apply_still_writes_the_whole_update() {
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+
+        RTM.block_on(async {
+            reset().await;
+
+            // The apply of "a" writes the lines and then waits for the state
+            let state = CONTAINER.search_state.write().await;
+
+            Diffuse::push(Search::new(&["a"], Some(1)));
+
+            tokio::time::timeout(TIMEOUT, async {
+                while search_lines().await != ["a"] {
+                    tokio::task::yield_now().await;
+                }
+            })
+            .await
+            .unwrap();
+
+            let (ab, release_ab) = Search::gated(&["ab"], None);
+            Diffuse::push(ab);
+
+            drop(state);
+            release_ab.send(()).unwrap();
+
+            barrier().await;
+
+            // "ab" doesn't select, the selection of "a" must have been applied
+            assert_eq!(search_lines().await, ["ab"]);
+            assert_eq!(CONTAINER.search_state.read().await.selected_idx, 1);
         });
     }
 }
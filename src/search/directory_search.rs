@@ -1,7 +1,10 @@
 use std::path::PathBuf;
 use tokio::{process::Command, time::Instant};
 
-use crate::{search::TaskResult, ExecuteTask, FuzzyTab, LineOut, NeoDebug, CONTAINER};
+use crate::{
+    search::{SearchUpdate, TaskKind},
+    ExecuteTask, FuzzyTab, LineOut, NeoDebug, NeoError, NeoResult, CONTAINER,
+};
 
 fn tabs() -> Option<Vec<Box<dyn FuzzyTab>>> {
     Some(vec![
@@ -19,53 +22,50 @@ impl ExecRecentDirectories {
         Self { search_query }
     }
 
-    async fn search_recent_directories(&self) -> TaskResult {
-        match CONTAINER
-            .db
+    async fn search_recent_directories(&self) -> NeoResult<SearchUpdate> {
+        let new_lines = CONTAINER
+            .db()
+            .await?
             .search_recent_directories(&self.search_query)
-            .await
-        {
-            Ok(new_lines) => {
-                let db_count = new_lines.len();
-                *CONTAINER.search_lines.write().await = new_lines;
-
-                TaskResult {
-                    db_count: Some(db_count),
-                    selected_idx: Some(0),
-                    selected_tab: Some(1),
-                    tabs: tabs(),
-                    ..Default::default()
-                }
-            }
-            Err(err) => {
-                NeoDebug::log(err).await;
-                TaskResult::default()
-            }
-        }
+            .await?;
+
+        Ok(SearchUpdate {
+            db_count: Some(new_lines.len()),
+            lines: Some(new_lines),
+            selected_idx: Some(0),
+            selected_tab: Some(1),
+            tabs: tabs(),
+            ..Default::default()
+        })
     }
 }
 
 // TODO use sqlite
 #[async_trait::async_trait]
 impl ExecuteTask for ExecRecentDirectories {
-    async fn execute(&self) -> TaskResult {
+    fn kind(&self) -> TaskKind {
+        TaskKind::Search
+    }
+
+    async fn execute(&self) -> NeoResult<SearchUpdate> {
         let instant = Instant::now();
 
         let result = self.search_recent_directories().await;
 
         let elapsed_ms = instant.elapsed().as_millis();
-        NeoDebug::log(format!("Elapsed recent search: {}", elapsed_ms)).await;
+        NeoDebug::log(format!("Elapsed recent search: {}", elapsed_ms));
 
         result
     }
 }
 
-async fn db_search(search_query: &str) -> TaskResult {
-    if let Ok(lines) = CONTAINER.db.search_lines(search_query).await {
-        *CONTAINER.search_lines.write().await = lines;
-    }
+async fn db_search(search_query: &str) -> NeoResult<SearchUpdate> {
+    let lines = CONTAINER.db().await?.search_lines(search_query).await?;
 
-    TaskResult::default()
+    Ok(SearchUpdate {
+        lines: Some(lines),
+        ..Default::default()
+    })
 }
 
 pub struct RemoveRecentDirectory {
@@ -80,10 +80,14 @@ impl RemoveRecentDirectory {
 
 #[async_trait::async_trait]
 impl ExecuteTask for RemoveRecentDirectory {
-    async fn execute(&self) -> TaskResult {
-        CONTAINER.db.delete_recent_directory(&self.path).await;
+    async fn execute(&self) -> NeoResult<SearchUpdate> {
+        CONTAINER
+            .db()
+            .await?
+            .delete_recent_directory(&self.path)
+            .await;
 
-        TaskResult::default()
+        Ok(SearchUpdate::default())
     }
 }
 
@@ -95,65 +99,64 @@ pub struct ExecDirectorySearch {
 }
 
 impl ExecDirectorySearch {
-    async fn insert_into_db(&self) -> TaskResult {
-        let out = Command::new(&self.cmd)
+    async fn insert_into_db(&self) -> NeoResult<SearchUpdate> {
+        let out = Command::new(self.cmd)
             .current_dir(&self.cwd)
             .args(&self.args)
             .output()
             .await
-            .unwrap();
-
-        if out.status.success() {
-            let out = String::from_utf8_lossy(&out.stdout);
-            let mut new_lines = Vec::new();
-
-            for line in out.lines() {
-                new_lines.push(LineOut::directory(line));
-            }
-
-            let db_count = new_lines.len();
-
-            match CONTAINER.db.insert_all(&new_lines).await {
-                Ok(_) => match CONTAINER.db.search_lines("").await {
-                    Ok(new_lines) => {
-                        *CONTAINER.search_lines.write().await = new_lines;
-
-                        return TaskResult {
-                            db_count: Some(db_count),
-                            selected_idx: Some(0),
-                            selected_tab: Some(0),
-                            tabs: tabs(),
-                            ..Default::default()
-                        };
-                    }
-                    Err(e) => {
-                        NeoDebug::log(e).await;
-                    }
-                },
-                Err(err) => {
-                    NeoDebug::log(err).await;
-                }
-            }
+            .map_err(|err| NeoError::spawn(self.cmd, err))?;
+
+        if !out.status.success() {
+            return Err(NeoError::CmdFailed {
+                cmd: self.cmd.to_string(),
+                stderr: String::from_utf8_lossy(&out.stderr).into(),
+            });
+        }
+
+        let out = String::from_utf8_lossy(&out.stdout);
+        let mut new_lines = Vec::new();
+
+        for line in out.lines() {
+            new_lines.push(LineOut::directory(line));
         }
 
-        TaskResult::default()
+        let db_count = new_lines.len();
+
+        CONTAINER.db().await?.insert_all(&new_lines).await?;
+
+        Ok(SearchUpdate {
+            db_count: Some(db_count),
+            selected_idx: Some(0),
+            selected_tab: Some(0),
+            tabs: tabs(),
+            ..Default::default()
+        })
     }
 }
 
 #[async_trait::async_trait]
 impl ExecuteTask for ExecDirectorySearch {
-    async fn execute(&self) -> TaskResult {
+    fn kind(&self) -> TaskKind {
+        TaskKind::Search
+    }
+
+    async fn populate(&self) -> NeoResult<SearchUpdate> {
+        if !self.all_lines_is_empty().await {
+            return Ok(SearchUpdate::default());
+        }
+
+        NeoDebug::log("is initial search");
+        self.insert_into_db().await
+    }
+
+    async fn execute(&self) -> NeoResult<SearchUpdate> {
         let instant = Instant::now();
 
-        let result = if self.all_lines_is_empty().await {
-            NeoDebug::log("is initial search").await;
-            self.insert_into_db().await
-        } else {
-            db_search(&self.search_query).await
-        };
+        let result = db_search(&self.search_query).await;
 
         let elapsed_ms = instant.elapsed().as_millis();
-        NeoDebug::log(format!("Elapsed directory search: {}", elapsed_ms)).await;
+        NeoDebug::log(format!("Elapsed directory search: {}", elapsed_ms));
 
         result
     }
@@ -163,20 +166,20 @@ pub struct ClearResultsTask;
 
 #[async_trait::async_trait]
 impl ExecuteTask for ClearResultsTask {
-    async fn execute(&self) -> TaskResult {
-        CONTAINER.db.empty_lines().await;
-        CONTAINER.search_lines.write().await.clear();
+    async fn execute(&self) -> NeoResult<SearchUpdate> {
+        CONTAINER.db().await?.empty_lines().await;
 
         //let mut search_state = CONTAINER.search_state.write().await;
         //search_state.db_count = 0;
         //search_state.selected_idx = 0;
 
-        TaskResult {
+        Ok(SearchUpdate {
+            lines: Some(vec![]),
             db_count: Some(0),
             selected_idx: Some(0),
             selected_tab: Some(0),
             ..Default::default()
-        }
+        })
     }
 }
 
@@ -190,12 +193,13 @@ impl InsertRecentDirectory {
 
 #[async_trait::async_trait]
 impl ExecuteTask for InsertRecentDirectory {
-    async fn execute(&self) -> TaskResult {
+    async fn execute(&self) -> NeoResult<SearchUpdate> {
         CONTAINER
-            .db
+            .db()
+            .await?
             .insert_recent_directory(self.0.to_string_lossy())
             .await;
 
-        TaskResult::default()
+        Ok(SearchUpdate::default())
     }
 }
@@ -2,7 +2,9 @@ use std::{path::PathBuf, time::Instant};
 
 use tokio::process::Command;
 
-use crate::{web_devicons::DevIcon, ExecuteTask, NeoDebug, TaskResult};
+use crate::{
+    web_devicons::DevIcon, ExecuteTask, NeoDebug, NeoError, NeoResult, SearchUpdate, TaskKind,
+};
 
 use super::{LineOut, CONTAINER};
 
@@ -14,75 +16,89 @@ pub struct ExecFileSearch {
 }
 
 impl ExecFileSearch {
-    async fn insert_into_db(&self) -> TaskResult {
-        let out = Command::new(&self.cmd)
+    async fn insert_into_db(&self) -> NeoResult<SearchUpdate> {
+        let out = Command::new(self.cmd)
             .current_dir(&self.cwd)
             .args(&self.args)
             .output()
             .await
-            .unwrap();
-
-        if out.status.success() {
-            let out = String::from_utf8_lossy(&out.stdout);
-            let mut new_lines = Vec::new();
-
-            for line in out.lines() {
-                let path = PathBuf::from(line);
-                let dev_icon = DevIcon::get_icon(&path);
-
-                new_lines.push(LineOut {
-                    text: line.into(),
-                    icon: dev_icon.icon.into(),
-                    hl_group: dev_icon.highlight.into(),
-                    ..Default::default()
-                });
-            }
-
-            match CONTAINER.db.insert_all(&new_lines).await {
-                Ok(_) => {
-                    if let Ok(new_lines) = CONTAINER.db.search_lines("").await {
-                        *CONTAINER.search_lines.write().await = new_lines;
-                    }
-
-                    return TaskResult {
-                        db_count: Some(new_lines.len()),
-                        selected_idx: Some(0),
-                        selected_tab: Some(0),
-                        tabs: Some(vec![]),
-                        ..Default::default()
-                    };
-                }
-                Err(err) => {
-                    NeoDebug::log(err).await;
-                }
-            }
+            .map_err(|err| NeoError::spawn(self.cmd, err))?;
+
+        if !out.status.success() {
+            return Err(NeoError::CmdFailed {
+                cmd: self.cmd.to_string(),
+                stderr: String::from_utf8_lossy(&out.stderr).into(),
+            });
         }
 
-        TaskResult::default()
-    }
+        let out = String::from_utf8_lossy(&out.stdout);
+        let mut new_lines = Vec::new();
+
+        for line in out.lines() {
+            let path = PathBuf::from(line);
+            let dev_icon = DevIcon::get_icon(&path);
 
-    async fn db_search(&self) -> TaskResult {
-        if let Ok(lines) = CONTAINER.db.search_lines(&self.search_query).await {
-            *CONTAINER.search_lines.write().await = lines;
+            new_lines.push(LineOut {
+                text: line.into(),
+                icon: dev_icon.icon.into(),
+                hl_group: dev_icon.highlight.into(),
+                ..Default::default()
+            });
         }
 
-        TaskResult::default()
+        CONTAINER.db().await?.insert_all(&new_lines).await?;
+
+        Ok(SearchUpdate {
+            db_count: Some(new_lines.len()),
+            selected_idx: Some(0),
+            selected_tab: Some(0),
+            tabs: Some(vec![]),
+            ..Default::default()
+        })
+    }
+
+    async fn db_search(&self) -> NeoResult<SearchUpdate> {
+        let lines = CONTAINER
+            .db()
+            .await?
+            .search_lines(&self.search_query)
+            .await?;
+
+        Ok(SearchUpdate {
+            lines: Some(lines),
+            ..Default::default()
+        })
     }
 }
 
 #[async_trait::async_trait]
 impl ExecuteTask for ExecFileSearch {
-    async fn execute(&self) -> TaskResult {
+    fn kind(&self) -> TaskKind {
+        TaskKind::Search
+    }
+
+    async fn populate(&self) -> NeoResult<SearchUpdate> {
+        if !self.all_lines_is_empty().await {
+            return Ok(SearchUpdate::default());
+        }
+
+        let instant = Instant::now();
+
+        let result = self.insert_into_db().await;
+
+        let elapsed_ms = instant.elapsed().as_millis();
+        NeoDebug::log(format!("Elapsed file insert: {}", elapsed_ms));
+
+        result
+    }
+
+    async fn execute(&self) -> NeoResult<SearchUpdate> {
         let instant = Instant::now();
 
-        let result = if self.all_lines_is_empty().await {
-            self.insert_into_db().await
-        } else {
-            self.db_search().await
-        };
+        let result = self.db_search().await;
 
         let elapsed_ms = instant.elapsed().as_millis();
-        NeoDebug::log(format!("Elapsed file search: {}", elapsed_ms)).await;
+        NeoDebug::log(format!("Elapsed file search: {}", elapsed_ms));
 
         result
     }
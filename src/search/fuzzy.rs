@@ -1,16 +1,18 @@
-use mlua::prelude::{LuaError, LuaResult};
+use mlua::prelude::LuaResult;
 use mlua::Lua;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
-use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use tokio::sync::{OnceCell, RwLock};
 
-use crate::search::{Diffuse, ExecuteTask};
+use crate::search::{Diffuse, ExecuteTask, TaskKind};
 use crate::web_devicons::DevIcon;
 use crate::{
-    AutoCmdCbEvent, AutoCmdEvent, AutoCmdGroup, ClearResultsTask, CmdOpts, Database, ExtmarkOpts,
-    FileTypeMatch, HLOpts, HLText, InsertRecentDirectory, Mode, NeoApi, NeoBuffer, NeoDebug,
-    NeoPopup, NeoTheme, NeoUtils, NeoWindow, OpenIn, PopupBorder, PopupRelative, PopupSize,
-    PopupStyle, RemoveRecentDirectory, TextType, VirtTextPos, RTM,
+    AutoCmdCbEvent, AutoCmdEvent, AutoCmdGroup, ClearResultsTask, CmdOpts, ConfigReader, Database,
+    ExtmarkOpts, FileTypeMatch, HLOpts, HLText, InsertRecentDirectory, Mode, NeoApi, NeoBuffer,
+    NeoColor, NeoConfig, NeoPopup, NeoResult, NeoScheduler, NeoTheme, NeoUtils, NeoWindow, OpenIn,
+    PopupBorder, PopupRelative, PopupSize, PopupStyle, RemoveRecentDirectory, TextType,
+    VirtTextPos,
 };
 
 use super::{ChangeTab, SearchState};
@@ -41,7 +43,7 @@ impl LineOut {
 }
 
 pub struct FuzzyContainer {
-    pub db: Database,
+    db: OnceCell<Database>,
     pub fuzzy: RwLock<NeoFuzzy>,
     pub preview: RwLock<Vec<Box<str>>>,
     pub search_lines: RwLock<Vec<LineOut>>,
@@ -54,6 +56,202 @@ pub trait FuzzyConfig: Send + Sync {
     fn search_task(&self, lua: &Lua, search_query: String, tab_idx: usize) -> Box<dyn ExecuteTask>;
     fn preview_task(&self, lua: &Lua, selected_idx: usize, tab_idx: usize) -> Box<dyn ExecuteTask>;
     fn on_enter(&self, lua: &Lua, open_in: OpenIn, item: PathBuf);
+
+    /// Delay before a search task runs, a newer query within this time replaces it
+    fn debounce(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Keymaps, colors and layout, usually read from the setup options of the plugin
+    fn options(&self) -> FuzzyOptions {
+        FuzzyOptions::default()
+    }
+}
+
+/// Options of the fuzzy finder, can be nested in the plugin config with `ConfigReader::nested`.
+///
+/// ```lua
+/// require('plugin').setup({
+///   fuzzy = {
+///     keymaps = { close = '<C-c>' },
+///     colors = { letter = '#ff0000' },
+///     layout = { border = 'single' },
+///   },
+/// })
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FuzzyOptions {
+    pub keymaps: FuzzyKeymaps,
+    pub colors: FuzzyColors,
+    pub layout: FuzzyLayout,
+}
+
+impl NeoConfig for FuzzyOptions {
+    fn merge(&mut self, reader: &mut ConfigReader) {
+        reader.nested("keymaps", &mut self.keymaps);
+        reader.nested("colors", &mut self.colors);
+        reader.nested("layout", &mut self.layout);
+    }
+}
+
+/// Insert mode keymaps of the search prompt
+#[derive(Clone, Debug)]
+pub struct FuzzyKeymaps {
+    pub up: String,
+    pub down: String,
+    pub close: String,
+    pub open_tab: String,
+    pub open_hsplit: String,
+    pub open_vsplit: String,
+    /// Only for directories and buffers
+    pub delete: String,
+    pub open: String,
+    pub next_tab: String,
+    pub prev_tab: String,
+}
+
+impl Default for FuzzyKeymaps {
+    fn default() -> Self {
+        Self {
+            up: "<Up>".to_string(),
+            down: "<Down>".to_string(),
+            close: "<Esc>".to_string(),
+            open_tab: "<C-t>".to_string(),
+            open_hsplit: "<C-s>".to_string(),
+            open_vsplit: "<C-v>".to_string(),
+            delete: "<C-d>".to_string(),
+            open: "<Enter>".to_string(),
+            next_tab: "<Tab>".to_string(),
+            prev_tab: "<S-Tab>".to_string(),
+        }
+    }
+}
+
+impl NeoConfig for FuzzyKeymaps {
+    fn merge(&mut self, reader: &mut ConfigReader) {
+        let keys = [
+            ("up", &mut self.up),
+            ("down", &mut self.down),
+            ("close", &mut self.close),
+            ("open_tab", &mut self.open_tab),
+            ("open_hsplit", &mut self.open_hsplit),
+            ("open_vsplit", &mut self.open_vsplit),
+            ("delete", &mut self.delete),
+            ("open", &mut self.open),
+            ("next_tab", &mut self.next_tab),
+            ("prev_tab", &mut self.prev_tab),
+        ];
+
+        for (key, target) in keys {
+            reader.read_with(key, target, not_empty);
+        }
+    }
+}
+
+/// Colors of the highlight groups, as `#rrggbb` or a color name. Unset colors are derived from
+/// the colorscheme when the picker opens.
+#[derive(Clone, Debug, Default)]
+pub struct FuzzyColors {
+    /// Icon letter of the results, bold
+    pub letter: Option<String>,
+    pub select: Option<String>,
+    pub tab_selected_fg: Option<String>,
+    pub tab_selected_bg: Option<String>,
+    pub tab_fg: Option<String>,
+    pub tab_bg: Option<String>,
+}
+
+impl FuzzyColors {
+    /// Fills the unset colors from the `Normal` and `String` groups, colorschemes without gui
+    /// colors get the previous fixed colors.
+    pub fn resolve(&self, lua: &Lua) -> LuaResult<FuzzyColors> {
+        let normal = NeoTheme::get_hl(lua, 0, "Normal", false)?;
+        let accent = NeoTheme::get_hl(lua, 0, "String", false)?.fg_color();
+
+        let fg = normal.fg_color();
+        let bg = normal.bg_color();
+        let tab_bg = bg.map(|bg| bg.darken(0.3));
+
+        let pick = |color: &Option<String>, derived: Option<NeoColor>, fallback: &str| {
+            color
+                .clone()
+                .or_else(|| derived.map(NeoColor::to_hex))
+                .or_else(|| Some(fallback.to_string()))
+        };
+
+        Ok(FuzzyColors {
+            letter: pick(&self.letter, accent, "#39E75F"),
+            select: pick(&self.select, accent.map(|c| c.lighten(0.6)), "#CEFAD0"),
+            tab_selected_fg: pick(&self.tab_selected_fg, fg, "#ffffff"),
+            tab_selected_bg: pick(&self.tab_selected_bg, tab_bg, "#151515"),
+            tab_fg: pick(
+                &self.tab_fg,
+                fg.zip(bg).map(|(fg, bg)| fg.blend(bg, 0.5)),
+                "#777777",
+            ),
+            tab_bg: pick(&self.tab_bg, tab_bg, "#151515"),
+        })
+    }
+}
+
+impl NeoConfig for FuzzyColors {
+    fn merge(&mut self, reader: &mut ConfigReader) {
+        let colors = [
+            ("letter", &mut self.letter),
+            ("select", &mut self.select),
+            ("tab_selected_fg", &mut self.tab_selected_fg),
+            ("tab_selected_bg", &mut self.tab_selected_bg),
+            ("tab_fg", &mut self.tab_fg),
+            ("tab_bg", &mut self.tab_bg),
+        ];
+
+        for (key, target) in colors {
+            reader.read_with(key, target, is_color);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FuzzyLayout {
+    pub border: PopupBorder,
+    pub title: String,
+}
+
+impl Default for FuzzyLayout {
+    fn default() -> Self {
+        Self {
+            border: PopupBorder::Rounded,
+            title: " Search ".to_string(),
+        }
+    }
+}
+
+impl NeoConfig for FuzzyLayout {
+    fn merge(&mut self, reader: &mut ConfigReader) {
+        reader.read("border", &mut self.border);
+        reader.read("title", &mut self.title);
+    }
+}
+
+/// Hex colors have to parse as `#rrggbb` like `nvim_set_hl` expects, color names are left to
+/// Neovim
+fn is_color(value: &Option<String>) -> Result<(), String> {
+    match value.as_deref() {
+        Some("") => Err("can't be empty".to_string()),
+        Some(hex) if hex.starts_with('#') && hex.len() != 7 => {
+            Err(format!("'{hex}' is not a #rrggbb color"))
+        }
+        Some(hex) if hex.starts_with('#') => hex.parse::<NeoColor>().map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+fn not_empty(value: &String) -> Result<(), String> {
+    if value.is_empty() {
+        Err("can't be empty".to_string())
+    } else {
+        Ok(())
+    }
 }
 
 struct DummyConfig;
@@ -106,9 +304,17 @@ pub static CONTAINER: LazyLock<FuzzyContainer> = LazyLock::new(|| FuzzyContainer
         selected_idx: 0,
     }),
     preview: RwLock::new(Vec::new()),
-    db: Database::new(),
+    db: OnceCell::new(),
 });
 
+impl FuzzyContainer {
+    /// Database gets initialized on first use, a failed initialization will be retried on the
+    /// next call.
+    pub async fn db(&self) -> NeoResult<&Database> {
+        self.db.get_or_try_init(Database::init).await
+    }
+}
+
 #[derive(Debug)]
 pub struct NeoFuzzy {
     pub pop_cmd: NeoPopup,
@@ -117,6 +323,7 @@ pub struct NeoFuzzy {
     pub pop_tabs: NeoPopup,
     pub ns_id: u32,
     pub config: Box<dyn FuzzyConfig>,
+    pub options: FuzzyOptions,
 }
 
 impl Default for NeoFuzzy {
@@ -128,6 +335,7 @@ impl Default for NeoFuzzy {
             pop_tabs: NeoPopup::default(),
             ns_id: 0,
             config: Box::new(DummyConfig),
+            options: FuzzyOptions::default(),
         }
     }
 }
@@ -156,13 +364,15 @@ enum Move {
 }
 
 impl NeoFuzzy {
-    pub fn add_hl_groups(lua: &Lua) -> LuaResult<()> {
+    pub fn add_hl_groups(lua: &Lua, colors: &FuzzyColors) -> LuaResult<()> {
+        let colors = colors.resolve(lua)?;
+
         NeoTheme::set_hl(
             lua,
             0,
             GRP_FUZZY_LETTER,
             HLOpts {
-                fg: Some("#39E75F".to_string()),
+                fg: colors.letter,
                 bold: true,
                 ..Default::default()
             },
@@ -173,7 +383,7 @@ impl NeoFuzzy {
             0,
             GRP_FUZZY_SELECT,
             HLOpts {
-                fg: Some("#CEFAD0".to_string()),
+                fg: colors.select,
                 ..Default::default()
             },
         )?;
@@ -183,9 +393,8 @@ impl NeoFuzzy {
             0,
             TAB_BTN_SELECTED,
             HLOpts {
-                fg: Some("#ffffff".into()),
-                //bg: Some("#1c96c5".into()),
-                bg: Some("#151515".into()),
+                fg: colors.tab_selected_fg,
+                bg: colors.tab_selected_bg,
                 ..Default::default()
             },
         )?;
@@ -195,8 +404,8 @@ impl NeoFuzzy {
             0,
             TAB_BTN,
             HLOpts {
-                fg: Some("#777777".into()),
-                bg: Some("#151515".into()),
+                fg: colors.tab_fg,
+                bg: colors.tab_bg,
                 ..Default::default()
             },
         )?;
@@ -208,46 +417,47 @@ impl NeoFuzzy {
 
     pub fn add_keymaps(&self, lua: &Lua) -> LuaResult<()> {
         let buf = self.pop_cmd.buf;
+        let keymaps = &self.options.keymaps;
 
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<Up>",
+            &keymaps.up,
             lua.create_async_function(|lua, ()| move_selection(lua, Move::Up))?,
         )?;
 
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<Down>",
+            &keymaps.down,
             lua.create_async_function(|lua, ()| move_selection(lua, Move::Down))?,
         )?;
 
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<Esc>",
+            &keymaps.close,
             lua.create_function(close_fuzzy)?,
         )?;
 
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<C-t>",
+            &keymaps.open_tab,
             lua.create_async_function(|lua, ()| open_item(lua, OpenIn::Tab))?,
         )?;
 
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<C-s>",
+            &keymaps.open_hsplit,
             lua.create_async_function(|lua, ()| open_item(lua, OpenIn::HSplit))?,
         )?;
 
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<C-v>",
+            &keymaps.open_vsplit,
             lua.create_async_function(|lua, ()| open_item(lua, OpenIn::VSplit))?,
         )?;
 
@@ -257,7 +467,7 @@ impl NeoFuzzy {
             buf.set_keymap(
                 lua,
                 Mode::Insert,
-                "<C-d>",
+                &keymaps.delete,
                 lua.create_async_function(delete_entry)?,
             )?;
         }
@@ -265,27 +475,29 @@ impl NeoFuzzy {
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<Enter>",
+            &keymaps.open,
             lua.create_async_function(|lua, ()| open_item(lua, OpenIn::Buffer))?,
         )?;
 
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<Tab>",
+            &keymaps.next_tab,
             lua.create_async_function(|lua, ()| SearchState::change_tab(lua, ChangeTab::Next))?,
         )?;
 
         buf.set_keymap(
             lua,
             Mode::Insert,
-            "<S-Tab>",
+            &keymaps.prev_tab,
             lua.create_async_function(|lua, ()| SearchState::change_tab(lua, ChangeTab::Previous))?,
         )
     }
 
     pub async fn open(lua: &Lua, config: Box<dyn FuzzyConfig>) -> LuaResult<()> {
-        Self::add_hl_groups(lua)?;
+        let options = config.options();
+
+        Self::add_hl_groups(lua, &options.colors)?;
 
         let ns_id = NeoTheme::create_namespace(lua, "NeoFuzzy")?;
 
@@ -320,9 +532,9 @@ impl NeoFuzzy {
                 row: Some(PopupSize::Fixed(pop_cmd_row)),
                 col: Some(PopupSize::Fixed(pop_cmd_col)),
                 relative: PopupRelative::Editor,
-                border: PopupBorder::Rounded,
+                border: options.layout.border.clone(),
                 style: Some(PopupStyle::Minimal),
-                title: Some(TextType::String(" Search ".to_string())),
+                title: Some(TextType::String(options.layout.title.clone())),
                 ..Default::default()
             },
         )?;
@@ -337,7 +549,7 @@ impl NeoFuzzy {
                 row: Some(PopupSize::Fixed(pop_cmd_row)),
                 col: Some(PopupSize::Fixed(preview_col)),
                 relative: PopupRelative::Editor,
-                border: PopupBorder::Rounded,
+                border: options.layout.border.clone(),
                 style: Some(PopupStyle::Minimal),
                 ..Default::default()
             },
@@ -353,7 +565,7 @@ impl NeoFuzzy {
                 row: Some(PopupSize::Fixed(out_preview_row)),
                 col: Some(PopupSize::Fixed(out_col)),
                 relative: PopupRelative::Editor,
-                border: crate::PopupBorder::Rounded,
+                border: options.layout.border.clone(),
                 focusable: Some(false),
                 style: Some(PopupStyle::Minimal),
                 ..Default::default()
@@ -372,7 +584,7 @@ impl NeoFuzzy {
                 row: Some(PopupSize::Fixed(out_preview_row)),
                 col: Some(PopupSize::Fixed(preview_col)),
                 relative: PopupRelative::Editor,
-                border: crate::PopupBorder::Rounded,
+                border: options.layout.border.clone(),
                 focusable: Some(false),
                 style: Some(PopupStyle::Minimal),
                 noautocmd: true,
@@ -420,21 +632,25 @@ impl NeoFuzzy {
             pop_tabs,
             ns_id,
             config,
+            options,
         };
 
         fuzzy.add_keymaps(lua)?;
 
-        Diffuse::queue([
+        NeoScheduler::init(lua)?;
+
+        let tasks: [Box<dyn ExecuteTask>; 3] = [
             Box::new(ClearResultsTask),
             fuzzy.config.search_task(lua, "".to_string(), 0),
             fuzzy.config.preview_task(lua, 0, 0),
-        ])
-        .await;
+        ];
 
+        Diffuse::set_debounce(TaskKind::Search, fuzzy.config.debounce());
+
+        // Before queueing, so the results are rendered in the new popups
         *CONTAINER.fuzzy.write().await = fuzzy;
 
-        let interval = lua.create_function(interval_write_out)?;
-        NeoApi::start_interval(lua, "fuzzy", 32, interval)?;
+        Diffuse::queue(tasks).await;
 
         Ok(())
     }
@@ -442,72 +658,6 @@ impl NeoFuzzy {
     pub fn fuzzy_grep(_cwd: &Path, _text: String) {
         //
     }
-
-    fn add_preview_highlight(&self, lua: &Lua, preview: &[Box<str>]) -> LuaResult<()> {
-        if self.config.search_type() == FuzzySearch::Directories {
-            self.pop_preview
-                .buf
-                .clear_namespace(lua, self.ns_id as i32, 0, -1)?;
-
-            for (i, item_name) in preview.iter().enumerate() {
-                if item_name.ends_with('/') {
-                    self.pop_preview.buf.add_highlight(
-                        lua,
-                        self.ns_id as i32,
-                        "Directory",
-                        i,
-                        0,
-                        -1,
-                    )?;
-                }
-
-                if item_name.starts_with("> Empty directory") {
-                    self.pop_preview.buf.add_highlight(
-                        lua,
-                        self.ns_id as i32,
-                        "Comment",
-                        i,
-                        0,
-                        -1,
-                    )?;
-                }
-            }
-        } else if preview.len() == 1 && preview[0].starts_with("> File is a binary") {
-            self.pop_preview
-                .buf
-                .add_highlight(lua, self.ns_id as i32, "Comment", 0, 0, -1)?;
-        }
-
-        Ok(())
-    }
-
-    fn add_out_highlight(
-        &self,
-        lua: &Lua,
-        hl_groups: Vec<&str>,
-        selected_idx: usize,
-    ) -> LuaResult<()> {
-        self.pop_out
-            .buf
-            .clear_namespace(lua, self.ns_id as i32, 0, -1)?;
-
-        for (i, hl_group) in hl_groups.into_iter().enumerate() {
-            self.pop_out
-                .buf
-                .add_highlight(lua, self.ns_id as i32, hl_group, i, 0, 2)?;
-        }
-
-        self.pop_out.buf.add_highlight(
-            lua,
-            self.ns_id as i32,
-            "NeoFuzzySelect",
-            selected_idx,
-            3,
-            -1,
-        )?;
-
-        Ok(())
-    }
 }
 
 async fn open_item(lua: Lua, open_in: OpenIn) -> LuaResult<()> {
@@ -548,119 +698,191 @@ async fn open_item(lua: Lua, open_in: OpenIn) -> LuaResult<()> {
     Ok(())
 }
 
-trait NeoTryLock<'a, T: ?Sized> {
-    fn interval_read(&'a self) -> LuaResult<RwLockReadGuard<'a, T>>;
-    fn interval_write(&'a self) -> LuaResult<RwLockWriteGuard<'a, T>>;
+/// Copy of the fuzzy state, so it can be rendered on the main thread without locking.
+pub struct FuzzyRender {
+    pop_cmd: NeoPopup,
+    pop_out: NeoPopup,
+    pop_preview: NeoPopup,
+    pop_tabs: NeoPopup,
+    ns_id: u32,
+    search_type: FuzzySearch,
+    file_path: String,
+    info_text: String,
+    lines: Vec<String>,
+    hl_groups: Vec<Box<str>>,
+    preview: Vec<Box<str>>,
+    tabs: Vec<HLText>,
+    selected_idx: usize,
 }
 
-impl<'a, T: ?Sized> NeoTryLock<'a, T> for RwLock<T> {
-    fn interval_read(&'a self) -> LuaResult<RwLockReadGuard<'a, T>> {
-        self.try_read().map_err(LuaError::external)
-    }
+impl FuzzyRender {
+    /// Snapshots the current state and renders it on the main thread.
+    pub async fn schedule() {
+        let render = Self::snapshot().await;
 
-    fn interval_write(&'a self) -> LuaResult<RwLockWriteGuard<'a, T>> {
-        self.try_write().map_err(LuaError::external)
+        NeoScheduler::schedule(move |lua| render.render(lua));
     }
-}
 
-fn interval_write_out(lua: &Lua, _: ()) -> LuaResult<()> {
-    fn execute(lua: &Lua) -> LuaResult<()> {
-        let fuzzy = CONTAINER.fuzzy.interval_read()?;
-        let search_lines = CONTAINER.search_lines.interval_read()?;
-        let mut search_state = CONTAINER.search_state.interval_write()?;
-        let mut preview = CONTAINER.preview.interval_write()?;
+    async fn snapshot() -> Self {
+        let fuzzy = CONTAINER.fuzzy.read().await;
+        let search_lines = CONTAINER.search_lines.read().await;
+        let search_state = CONTAINER.search_state.read().await;
+        let preview = CONTAINER.preview.read().await.clone();
 
-        let preview = std::mem::take(&mut *preview);
+        let mut lines = Vec::new();
+        let mut hl_groups = Vec::new();
 
-        if search_state.update {
-            search_state.update = false;
-            let file_path = search_state.file_path.to_string();
-            let info_text = format!(" ({}/{}) ", search_lines.len(), search_state.db_count);
+        for line in search_lines.iter() {
+            lines.push(format!(" {} {}", line.icon, line.text));
+            hl_groups.push(line.hl_group.clone());
+        }
 
-            let mut icon_lines = Vec::new();
-            let mut hl_groups = Vec::new();
+        let mut tabs: Vec<_> = vec![];
 
-            for line in search_lines.iter() {
-                icon_lines.push(format!(" {} {}", line.icon, line.text));
-                hl_groups.push(line.hl_group.as_ref());
+        for (i, tab) in search_state.tabs.iter().enumerate() {
+            if i == search_state.selected_tab {
+                tabs.push(HLText::new(tab.name(), TAB_BTN_SELECTED.into()));
+            } else {
+                tabs.push(HLText::new(tab.name(), TAB_BTN.into()));
             }
 
-            fuzzy
-                .pop_out
-                .buf
-                .set_lines(lua, 0, -1, false, &icon_lines)?;
+            tabs.push(HLText::new(" ", ""));
+        }
 
-            fuzzy.add_out_highlight(lua, hl_groups, search_state.selected_idx)?;
+        Self {
+            pop_cmd: fuzzy.pop_cmd,
+            pop_out: fuzzy.pop_out,
+            pop_preview: fuzzy.pop_preview,
+            pop_tabs: fuzzy.pop_tabs,
+            ns_id: fuzzy.ns_id,
+            search_type: fuzzy.config.search_type(),
+            file_path: search_state.file_path.to_string(),
+            info_text: format!(" ({}/{}) ", search_lines.len(), search_state.db_count),
+            lines,
+            hl_groups,
+            preview,
+            tabs,
+            selected_idx: search_state.selected_idx,
+        }
+    }
+
+    fn render(self, lua: &Lua) -> LuaResult<()> {
+        // Fuzzy finder got closed before the render was scheduled
+        if !self.pop_out.win.is_valid(lua)? {
+            return Ok(());
+        }
+
+        self.pop_out.buf.set_lines(lua, 0, -1, false, &self.lines)?;
 
-            let buf = &fuzzy.pop_preview.buf;
+        self.add_out_highlight(lua)?;
 
-            buf.set_lines(lua, 0, -1, false, &preview)?;
+        let buf = &self.pop_preview.buf;
 
-            if fuzzy.config.search_type().is_file_based() {
-                let ft = NeoApi::filetype_match(
-                    lua,
-                    FileTypeMatch {
-                        filename: Some(file_path),
-                        contents: None,
-                        buf: Some(buf.id()),
-                    },
-                )?;
+        buf.set_lines(lua, 0, -1, false, &self.preview)?;
+
+        if self.search_type.is_file_based() {
+            let ft = NeoApi::filetype_match(
+                lua,
+                FileTypeMatch {
+                    filename: Some(self.file_path.clone()),
+                    contents: None,
+                    buf: Some(buf.id()),
+                },
+            )?;
 
-                if let Some(ft) = ft {
-                    let lang = buf.get_treesitter_lang(lua, &ft)?;
+            if let Some(ft) = ft {
+                let lang = buf.get_treesitter_lang(lua, &ft)?;
 
-                    if let Some(lang) = lang {
-                        buf.start_treesitter(lua, &lang)?;
-                    }
-                } else {
-                    buf.stop_treesitter(lua)?;
+                if let Some(lang) = lang {
+                    buf.start_treesitter(lua, &lang)?;
                 }
+            } else {
+                buf.stop_treesitter(lua)?;
             }
+        }
 
-            fuzzy.add_preview_highlight(lua, &preview)?;
+        self.add_preview_highlight(lua)?;
 
-            let buf = &fuzzy.pop_cmd.buf;
+        let opts = ExtmarkOpts {
+            id: Some(333),
+            virt_text: Some(vec![HLText::new(self.info_text.as_str(), "Comment")]),
+            virt_text_pos: Some(VirtTextPos::RightAlign),
+            ..Default::default()
+        };
 
-            let opts = ExtmarkOpts {
-                id: Some(333),
-                virt_text: Some(vec![HLText::new(info_text, "Comment".to_string())]),
-                virt_text_pos: Some(VirtTextPos::RightAlign),
-                ..Default::default()
-            };
+        self.pop_cmd.buf.set_extmarks(lua, self.ns_id, 0, 0, opts)?;
 
-            buf.set_extmarks(lua, fuzzy.ns_id, 0, 0, opts)?;
+        let opts = ExtmarkOpts {
+            id: Some(333),
+            virt_text: Some(self.tabs),
+            ..Default::default()
+        };
 
-            let buf = &fuzzy.pop_tabs.buf;
+        self.pop_tabs.buf.set_extmarks(lua, self.ns_id, 0, 0, opts)
+    }
 
-            let mut hl_texts: Vec<_> = vec![];
+    fn add_preview_highlight(&self, lua: &Lua) -> LuaResult<()> {
+        let preview = &self.preview;
 
-            for (i, tab) in search_state.tabs.iter().enumerate() {
-                if i == search_state.selected_tab {
-                    hl_texts.push(HLText::new(tab.name(), TAB_BTN_SELECTED.into()));
-                } else {
-                    hl_texts.push(HLText::new(tab.name(), TAB_BTN.into()));
+        if self.search_type == FuzzySearch::Directories {
+            self.pop_preview
+                .buf
+                .clear_namespace(lua, self.ns_id as i32, 0, -1)?;
+
+            for (i, item_name) in preview.iter().enumerate() {
+                if item_name.ends_with('/') {
+                    self.pop_preview.buf.add_highlight(
+                        lua,
+                        self.ns_id as i32,
+                        "Directory",
+                        i,
+                        0,
+                        -1,
+                    )?;
                 }
 
-                hl_texts.push(HLText::new(" ", ""));
+                if item_name.starts_with("> Empty directory") {
+                    self.pop_preview.buf.add_highlight(
+                        lua,
+                        self.ns_id as i32,
+                        "Comment",
+                        i,
+                        0,
+                        -1,
+                    )?;
+                }
             }
-
-            let opts = ExtmarkOpts {
-                id: Some(333),
-                virt_text: Some(hl_texts),
-                ..Default::default()
-            };
-
-            buf.set_extmarks(lua, fuzzy.ns_id, 0, 0, opts)?;
+        } else if preview.len() == 1 && preview[0].starts_with("> File is a binary") {
+            self.pop_preview
+                .buf
+                .add_highlight(lua, self.ns_id as i32, "Comment", 0, 0, -1)?;
         }
 
         Ok(())
     }
 
-    if let Err(err) = execute(lua) {
-        RTM.spawn(NeoDebug::log(err));
-    }
+    fn add_out_highlight(&self, lua: &Lua) -> LuaResult<()> {
+        self.pop_out
+            .buf
+            .clear_namespace(lua, self.ns_id as i32, 0, -1)?;
 
-    Ok(())
+        for (i, hl_group) in self.hl_groups.iter().enumerate() {
+            self.pop_out
+                .buf
+                .add_highlight(lua, self.ns_id as i32, hl_group, i, 0, 2)?;
+        }
+
+        self.pop_out.buf.add_highlight(
+            lua,
+            self.ns_id as i32,
+            "NeoFuzzySelect",
+            self.selected_idx,
+            3,
+            -1,
+        )?;
+
+        Ok(())
+    }
 }
 
 async fn move_selection(lua: Lua, move_sel: Move) -> LuaResult<()> {
@@ -694,9 +916,10 @@ async fn move_selection(lua: Lua, move_sel: Move) -> LuaResult<()> {
             NeoApi::cmd(
                 lua,
                 CmdOpts {
-                    cmd: "normal",
+                    cmd: "normal".to_string(),
                     bang: true,
-                    args: &[&format!("{}G", selected_idx + 1)],
+                    args: vec![format!("{}G", selected_idx + 1)],
+                    ..Default::default()
                 },
             )
         })?,
@@ -725,11 +948,7 @@ async fn delete_entry(lua: Lua, _: ()) -> LuaResult<()> {
 
         NeoApi::cmd(
             &lua,
-            CmdOpts {
-                cmd: "bwipeout",
-                args: &[selected.to_string_lossy().as_ref()],
-                bang: false,
-            },
+            CmdOpts::with_args("bwipeout", [selected.to_string_lossy()]),
         )?;
 
         Diffuse::queue([
@@ -763,7 +982,6 @@ async fn aucmd_close_fuzzy(lua: Lua, _ev: AutoCmdCbEvent) -> LuaResult<()> {
     Diffuse::queue([Box::new(ClearResultsTask)]).await;
 
     NeoApi::del_augroup_by_name(&lua, AUCMD_GRP)?;
-    NeoApi::stop_interval(&lua, "fuzzy")?;
     NeoApi::set_insert_mode(&lua, false)?;
 
     let fuzzy = CONTAINER.fuzzy.read().await;
@@ -785,9 +1003,10 @@ async fn aucmd_text_changed(lua: Lua, _ev: AutoCmdCbEvent) -> LuaResult<()> {
             NeoApi::cmd(
                 lua,
                 CmdOpts {
-                    cmd: "normal",
+                    cmd: "normal".to_string(),
                     bang: true,
-                    args: &[&format!("1G")],
+                    args: vec!["1G".to_string()],
+                    ..Default::default()
                 },
             )
         })?,
@@ -804,3 +1023,100 @@ async fn aucmd_text_changed(lua: Lua, _ev: AutoCmdCbEvent) -> LuaResult<()> {
 
     Ok(())
 }
+
+#[cfg(all(test, feature = "test-harness"))]
+mod tests {
+    use super::*;
+    use crate::NeoMock;
+    use mlua::prelude::LuaTable;
+
+    const OUT_BUF: u32 = 2;
+    const CMD_BUF: u32 = 4;
+
+    fn popup(id: u32) -> NeoPopup {
+        NeoPopup {
+            win: NeoWindow::new(1000 + id),
+            buf: NeoBuffer::from_id(id),
+        }
+    }
+
+    fn render() -> FuzzyRender {
+        FuzzyRender {
+            pop_cmd: popup(CMD_BUF),
+            pop_out: popup(OUT_BUF),
+            pop_preview: popup(6),
+            pop_tabs: popup(8),
+            ns_id: 3,
+            search_type: FuzzySearch::Files,
+            file_path: "src/lib.rs".to_string(),
+            info_text: " (2/10) ".to_string(),
+            lines: vec![" a lib.rs".to_string(), " b main.rs".to_string()],
+            hl_groups: vec!["DevIconRs".into(), "DevIconRs".into()],
+            preview: vec!["mod popup;".into()],
+            tabs: vec![],
+            selected_idx: 1,
+        }
+    }
+
+    #[test]
+    fn render_writes_lines_and_info() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        mock.returns("vim.api.nvim_win_is_valid", true)?;
+        mock.returns("vim.api.nvim_buf_add_highlight", 0)?;
+
+        render().render(&mock)?;
+
+        let set_lines = mock.calls_to("vim.api.nvim_buf_set_lines");
+
+        assert_eq!(set_lines[0].arg::<u32>(&mock, 0)?, OUT_BUF);
+        assert_eq!(
+            set_lines[0].arg::<Vec<String>>(&mock, 4)?,
+            [" a lib.rs", " b main.rs"]
+        );
+
+        // Selection is highlighted after the icons
+        let highlights = mock.calls_to("vim.api.nvim_buf_add_highlight");
+        let select = highlights.last().unwrap();
+
+        assert_eq!(select.arg::<String>(&mock, 2)?, GRP_FUZZY_SELECT);
+        assert_eq!(select.arg::<usize>(&mock, 3)?, 1);
+
+        // No filetype matched
+        assert!(mock.called("vim.treesitter.stop"));
+
+        let extmarks = mock.calls_to("vim.api.nvim_buf_set_extmark");
+        let info = &extmarks[0];
+        let virt_text: LuaTable = info.arg::<LuaTable>(&mock, 4)?.get("virt_text")?;
+
+        assert_eq!(info.arg::<u32>(&mock, 0)?, CMD_BUF);
+        assert_eq!(virt_text.get::<Vec<String>>(1)?, [" (2/10) ", "Comment"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_skips_closed_fuzzy() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        mock.returns("vim.api.nvim_win_is_valid", false)?;
+
+        render().render(&mock)?;
+
+        assert_eq!(mock.calls().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn colors_are_rrggbb_or_names() {
+        let color = |value: &str| is_color(&Some(value.to_string()));
+
+        assert!(color("#00ff00").is_ok());
+        assert!(color("Green").is_ok());
+        assert!(is_color(&None).is_ok());
+
+        // nvim_set_hl() doesn't accept the short form
+        for value in ["#0f0", "#00ff0", "#00ff000", "#gg0000", ""] {
+            assert!(color(value).is_err(), "{value}");
+        }
+    }
+}
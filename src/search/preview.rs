@@ -7,7 +7,10 @@ use std::{
 
 use tokio::{fs, io};
 
-use crate::{search::TaskResult, ExecuteTask, NeoDebug};
+use crate::{
+    search::{SearchUpdate, TaskKind},
+    ExecuteTask, NeoDebug, NeoResult,
+};
 
 use super::CONTAINER;
 
@@ -18,7 +21,11 @@ pub struct ExecPreview {
 
 #[async_trait::async_trait]
 impl ExecuteTask for ExecPreview {
-    async fn execute(&self) -> TaskResult {
+    fn kind(&self) -> TaskKind {
+        TaskKind::Preview
+    }
+
+    async fn execute(&self) -> NeoResult<SearchUpdate> {
         let now = Instant::now();
 
         let path: PathBuf = {
@@ -27,10 +34,10 @@ impl ExecuteTask for ExecPreview {
             if filtered_lines.is_empty() {
                 CONTAINER.preview.write().await.clear();
                 //CONTAINER.search_state.write().await.update = true;
-                return TaskResult {
+                return Ok(SearchUpdate {
                     update: true,
                     ..Default::default()
-                };
+                });
             }
 
             self.cwd
@@ -41,15 +48,15 @@ impl ExecuteTask for ExecPreview {
             || path.is_file() && preview_file(&path).await.is_ok()
         {
             let elapsed_ms = now.elapsed().as_millis();
-            NeoDebug::log(format!("Elapsed preview: {}", elapsed_ms)).await;
+            NeoDebug::log(format!("Elapsed preview: {}", elapsed_ms));
 
-            return TaskResult {
+            return Ok(SearchUpdate {
                 update: true,
                 ..Default::default()
-            };
+            });
         }
 
-        TaskResult::default()
+        Ok(SearchUpdate::default())
     }
 }
 
@@ -129,9 +136,8 @@ fn is_binary(file: &Path) -> bool {
     ]
     .into();
 
-    if let Some(ext) = file.extension() {
-        binaries.contains(ext.to_str().unwrap())
-    } else {
-        false
+    match file.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => binaries.contains(ext),
+        None => false,
     }
 }
@@ -0,0 +1,179 @@
+use crate::{CmdOpts, NeoApi, NeoLuaExt, NeoWindow};
+use mlua::prelude::{IntoLua, Lua, LuaError, LuaFunction, LuaResult, LuaValue};
+use mlua::FromLua;
+use serde::{Deserialize, Serialize};
+
+/// Serialized as the id
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NeoTabpage(u32);
+
+impl FromLua for NeoTabpage {
+    fn from_lua(value: LuaValue, _lua: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Integer(num) => Ok(NeoTabpage::new(num as u32)),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "NeoTabpage",
+                message: Some("Not a number".to_string()),
+            }),
+        }
+    }
+}
+
+impl IntoLua for NeoTabpage {
+    fn into_lua(self, _lua: &Lua) -> LuaResult<LuaValue> {
+        Ok(LuaValue::Integer(self.0 as i64))
+    }
+}
+
+impl NeoTabpage {
+    pub const CURRENT: Self = Self(0);
+
+    pub fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+
+    /**
+    Gets the current list of tabpage handles.
+
+    Return: ~
+        List of tabpage handles
+    */
+    pub fn list_tabpages(lua: &Lua) -> LuaResult<Vec<NeoTabpage>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_list_tabpages")?;
+
+        lfn.call(())
+    }
+
+    /**
+    Gets the current tabpage.
+
+    Return: ~
+        Tabpage handle
+    */
+    pub fn get_current(lua: &Lua) -> LuaResult<NeoTabpage> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_current_tabpage")?;
+
+        lfn.call(())
+    }
+
+    /**
+    Sets the current tabpage.
+
+    Attributes: ~
+        not allowed when |textlock| is active or in the |cmdwin|
+    */
+    pub fn set_current(&self, lua: &Lua) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_set_current_tabpage")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Gets the windows in a tabpage
+
+    Return: ~
+        List of windows in `tabpage`
+    */
+    pub fn list_wins(&self, lua: &Lua) -> LuaResult<Vec<NeoWindow>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_tabpage_list_wins")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Gets the current window in a tabpage
+
+    Return: ~
+        Window handle
+    */
+    pub fn get_win(&self, lua: &Lua) -> LuaResult<NeoWindow> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_tabpage_get_win")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Gets the tabpage number
+
+    Return: ~
+        Tabpage number, starts from 1 like |tabpagenr()|
+    */
+    pub fn get_number(&self, lua: &Lua) -> LuaResult<u32> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_tabpage_get_number")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Gets a tab-scoped (t:) variable
+
+    Parameters: ~
+      • {name}     Variable name
+
+    Return: ~
+        Variable value
+    */
+    pub fn get_var<V: FromLua>(&self, lua: &Lua, name: &str) -> LuaResult<V> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_tabpage_get_var")?;
+
+        lfn.call((self.id(), name))
+    }
+
+    /**
+    Sets a tab-scoped (t:) variable
+
+    Parameters: ~
+      • {name}     Variable name
+      • {value}    Variable value
+    */
+    pub fn set_var<V: IntoLua>(&self, lua: &Lua, name: &str, value: V) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_tabpage_set_var")?;
+
+        lfn.call((self.id(), name, value))
+    }
+
+    /**
+    Removes a tab-scoped (t:) variable
+
+    Parameters: ~
+      • {name}     Variable name
+    */
+    pub fn del_var(&self, lua: &Lua, name: &str) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_tabpage_del_var")?;
+
+        lfn.call((self.id(), name))
+    }
+
+    /**
+    Checks if a tabpage is valid
+
+    Return: ~
+        true if the tabpage is valid, false otherwise
+    */
+    pub fn is_valid(&self, lua: &Lua) -> LuaResult<bool> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_tabpage_is_valid")?;
+
+        lfn.call(self.id())
+    }
+
+    /// Closes the tabpage with |:tabclose|, with force the modified buffers are hidden
+    pub fn close(&self, lua: &Lua, force: bool) -> LuaResult<()> {
+        let number = self.get_number(lua)?;
+
+        NeoApi::cmd(
+            lua,
+            CmdOpts {
+                cmd: "tabclose".to_string(),
+                args: vec![number.to_string()],
+                bang: force,
+                ..Default::default()
+            },
+        )
+    }
+}
@@ -0,0 +1,157 @@
+use rmpv::Value;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::{NeoError, NeoResult};
+
+const REQUEST: u64 = 0;
+const RESPONSE: u64 = 1;
+
+/// Msgpack RPC client of `nvim --headless --embed --clean`, to test against a real Neovim.
+/// The process is killed on drop.
+///
+/// ```ignore
+/// let Some(mut nvim) = NeoHeadless::spawn()? else {
+///     return Ok(()); // nvim is not on PATH
+/// };
+///
+/// let count = nvim.exec_lua("return #vim.api.nvim_list_bufs()", vec![])?;
+/// assert_eq!(count.as_u64(), Some(1));
+/// ```
+pub struct NeoHeadless {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    next_id: u32,
+}
+
+impl NeoHeadless {
+    /// Returns None when `nvim` is not on PATH.
+    pub fn spawn() -> NeoResult<Option<Self>> {
+        let child = Command::new("nvim")
+            .args(["--headless", "--embed", "--clean"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(NeoError::spawn("nvim", err)),
+        };
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        Ok(Some(Self {
+            child,
+            stdin: BufWriter::new(stdin),
+            stdout: BufReader::new(stdout),
+            next_id: 0,
+        }))
+    }
+
+    /// Calls an API function, e.g. `nvim_buf_get_lines`, and waits for its result.
+    pub fn request(&mut self, method: &str, params: Vec<Value>) -> NeoResult<Value> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let msg = Value::Array(vec![
+            REQUEST.into(),
+            id.into(),
+            method.into(),
+            Value::Array(params),
+        ]);
+
+        rmpv::encode::write_value(&mut self.stdin, &msg)
+            .map_err(|err| NeoError::Rpc(err.to_string()))?;
+        self.stdin.flush()?;
+
+        loop {
+            let msg = rmpv::decode::read_value(&mut self.stdout)
+                .map_err(|err| NeoError::Rpc(err.to_string()))?;
+
+            // Notifications and requests of nvim are ignored
+            let Value::Array(mut msg) = msg else {
+                continue;
+            };
+
+            if msg.len() != 4
+                || msg[0].as_u64() != Some(RESPONSE)
+                || msg[1].as_u64() != Some(id as u64)
+            {
+                continue;
+            }
+
+            let result = msg.pop().unwrap_or(Value::Nil);
+            let err = msg.pop().unwrap_or(Value::Nil);
+
+            return match err {
+                Value::Nil => Ok(result),
+                // [error type, message]
+                Value::Array(err) => Err(NeoError::Rpc(
+                    err.get(1)
+                        .and_then(Value::as_str)
+                        .unwrap_or("Unknown error")
+                        .to_string(),
+                )),
+                err => Err(NeoError::Rpc(err.to_string())),
+            };
+        }
+    }
+
+    /// Executes Lua code, the arguments are available as `...`.
+    pub fn exec_lua(&mut self, code: &str, args: Vec<Value>) -> NeoResult<Value> {
+        self.request("nvim_exec_lua", vec![code.into(), Value::Array(args)])
+    }
+
+    /// Executes an Ex command.
+    pub fn command(&mut self, cmd: &str) -> NeoResult<()> {
+        self.request("nvim_command", vec![cmd.into()])?;
+
+        Ok(())
+    }
+}
+
+impl Drop for NeoHeadless {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_lua_returns_the_result() -> NeoResult<()> {
+        let Some(mut nvim) = NeoHeadless::spawn()? else {
+            return Ok(());
+        };
+
+        let count = nvim.exec_lua("return #vim.api.nvim_list_bufs()", vec![])?;
+        assert_eq!(count.as_u64(), Some(1));
+
+        let sum = nvim.exec_lua("local a, b = ... return a + b", vec![2.into(), 3.into()])?;
+        assert_eq!(sum.as_u64(), Some(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_requests_are_rpc_errors() -> NeoResult<()> {
+        let Some(mut nvim) = NeoHeadless::spawn()? else {
+            return Ok(());
+        };
+
+        let result = nvim.command("not_a_command");
+        assert!(matches!(result, Err(NeoError::Rpc(_))));
+
+        // Still usable after an error
+        nvim.command("let g:neo_api = 1")?;
+
+        Ok(())
+    }
+}
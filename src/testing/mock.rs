@@ -0,0 +1,274 @@
+use mlua::prelude::{LuaFunction, LuaMultiValue, LuaResult, LuaTable, LuaValue};
+use mlua::{FromLua, FromLuaMulti, IntoLuaMulti, Lua};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::NeoLuaExt;
+
+/// Tables under `vim` which contain functions, every key is a recording function
+const FN_NAMESPACES: [&str; 11] = [
+    "api",
+    "fn",
+    "uv",
+    "loop",
+    "keymap",
+    "fs",
+    "lsp",
+    "diagnostic",
+    "highlight",
+    "filetype",
+    "treesitter",
+];
+
+/// Tables under `vim` which contain variables or options, e.g. `vim.bo[buf].modifiable`
+const VAR_TABLES: [&str; 10] = ["g", "b", "w", "t", "v", "env", "o", "go", "bo", "wo"];
+
+/// Call of a mocked function, the arguments are valid while the `NeoMock` lives.
+#[derive(Clone, Debug)]
+pub struct MockCall {
+    /// Full path, e.g. `vim.api.nvim_buf_set_lines`
+    pub path: String,
+    pub args: Vec<LuaValue>,
+}
+
+impl MockCall {
+    /// Converts the argument at idx (0 based), missing arguments are nil.
+    pub fn arg<V: FromLua>(&self, lua: &Lua, idx: usize) -> LuaResult<V> {
+        V::from_lua(self.args.get(idx).cloned().unwrap_or(LuaValue::Nil), lua)
+    }
+}
+
+enum Stub {
+    Returns(LuaMultiValue),
+    Calls(LuaFunction),
+}
+
+#[derive(Default)]
+struct MockState {
+    calls: Vec<MockCall>,
+    stubs: HashMap<String, Stub>,
+}
+
+type SharedState = Rc<RefCell<MockState>>;
+
+/// Lua state with a mocked `vim` global, to test wrappers in plain `cargo test`.
+///
+/// Every function under `vim` (e.g. `vim.notify`, `vim.api.*`, `vim.fn.*`) records its calls
+/// and returns nothing, unless a canned value or stub is registered for its path.
+/// `vim.schedule` runs the callback immediately and `vim.schedule_wrap` returns it.
+///
+/// Requires the `test-harness` feature:
+/// `cargo test --no-default-features --features test-harness`
+///
+/// ```ignore
+/// let mock = NeoMock::new()?;
+/// mock.returns("vim.api.nvim_create_buf", 7)?;
+///
+/// let buf = NeoBuffer::create(&mock, false, true)?;
+///
+/// assert_eq!(buf.id(), 7);
+/// assert_eq!(mock.call_count("vim.api.nvim_create_buf"), 1);
+/// ```
+pub struct NeoMock {
+    lua: Lua,
+    state: SharedState,
+}
+
+impl NeoMock {
+    pub fn new() -> LuaResult<Self> {
+        let mock = Self {
+            lua: Lua::new(),
+            state: SharedState::default(),
+        };
+
+        mock.install()?;
+
+        Ok(mock)
+    }
+
+    pub fn lua(&self) -> &Lua {
+        &self.lua
+    }
+
+    /// Function at path returns these values, e.g. a tuple for multiple values.
+    pub fn returns<V: IntoLuaMulti>(&self, path: &str, values: V) -> LuaResult<()> {
+        let values = values.into_lua_multi(&self.lua)?;
+
+        self.state
+            .borrow_mut()
+            .stubs
+            .insert(path.to_string(), Stub::Returns(values));
+
+        Ok(())
+    }
+
+    /// Function at path calls the stub, calls are still recorded.
+    pub fn stub<A, R, F>(&self, path: &str, stub: F) -> LuaResult<()>
+    where
+        A: FromLuaMulti,
+        R: IntoLuaMulti,
+        F: Fn(&Lua, A) -> LuaResult<R> + 'static,
+    {
+        let stub = self.lua.create_function(stub)?;
+
+        self.state
+            .borrow_mut()
+            .stubs
+            .insert(path.to_string(), Stub::Calls(stub));
+
+        Ok(())
+    }
+
+    /// All recorded calls in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state.borrow().calls.clone()
+    }
+
+    /// Recorded calls of the function at path.
+    pub fn calls_to(&self, path: &str) -> Vec<MockCall> {
+        self.state
+            .borrow()
+            .calls
+            .iter()
+            .filter(|call| call.path == path)
+            .cloned()
+            .collect()
+    }
+
+    pub fn call_count(&self, path: &str) -> usize {
+        self.state
+            .borrow()
+            .calls
+            .iter()
+            .filter(|call| call.path == path)
+            .count()
+    }
+
+    pub fn called(&self, path: &str) -> bool {
+        self.call_count(path) != 0
+    }
+
+    pub fn clear_calls(&self) {
+        self.state.borrow_mut().calls.clear();
+    }
+
+    /// Removes the recorded calls, stubs and variables by installing a new `vim` global.
+    pub fn reset(&self) -> LuaResult<()> {
+        {
+            let mut state = self.state.borrow_mut();
+            state.calls.clear();
+            state.stubs.clear();
+        }
+
+        self.install()
+    }
+
+    fn install(&self) -> LuaResult<()> {
+        let lua = &self.lua;
+        let vim = lua.create_table()?;
+        let meta = lua.create_table()?;
+        let state = self.state.clone();
+
+        let index = lua.create_function(move |lua, (vim, key): (LuaTable, String)| {
+            let value = if FN_NAMESPACES.contains(&key.as_str()) {
+                LuaValue::Table(namespace(lua, &state, format!("vim.{key}"))?)
+            } else if VAR_TABLES.contains(&key.as_str()) {
+                LuaValue::Table(var_table(lua)?)
+            } else {
+                LuaValue::Function(recorder(lua, &state, format!("vim.{key}"))?)
+            };
+
+            vim.raw_set(key, value.clone())?;
+
+            Ok(value)
+        })?;
+
+        meta.set("__index", index)?;
+        vim.set_metatable(Some(meta));
+
+        lua.globals().set("vim", vim)?;
+
+        // Cached functions belong to the previous `vim` global
+        lua.clear_neo_fns();
+
+        self.stub("vim.schedule", |_, callback: LuaFunction| {
+            callback.call::<()>(())
+        })?;
+        self.stub("vim.schedule_wrap", |_, callback: LuaFunction| Ok(callback))
+    }
+}
+
+impl Deref for NeoMock {
+    type Target = Lua;
+
+    fn deref(&self) -> &Self::Target {
+        &self.lua
+    }
+}
+
+/// Table which creates a recording function for every key
+fn namespace(lua: &Lua, state: &SharedState, prefix: String) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    let meta = lua.create_table()?;
+    let state = state.clone();
+
+    let index = lua.create_function(move |lua, (table, key): (LuaTable, String)| {
+        let lfn = recorder(lua, &state, format!("{prefix}.{key}"))?;
+        table.raw_set(key, lfn.clone())?;
+
+        Ok(lfn)
+    })?;
+
+    meta.set("__index", index)?;
+    table.set_metatable(Some(meta));
+
+    Ok(table)
+}
+
+/// Plain table, numeric keys (buffer / window ids) create nested tables
+fn var_table(lua: &Lua) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    let meta = lua.create_table()?;
+
+    let index = lua.create_function(|lua, (table, key): (LuaTable, LuaValue)| {
+        if !matches!(key, LuaValue::Integer(_) | LuaValue::Number(_)) {
+            return Ok(LuaValue::Nil);
+        }
+
+        let nested = lua.create_table()?;
+        table.raw_set(key, nested.clone())?;
+
+        Ok(LuaValue::Table(nested))
+    })?;
+
+    meta.set("__index", index)?;
+    table.set_metatable(Some(meta));
+
+    Ok(table)
+}
+
+fn recorder(lua: &Lua, state: &SharedState, path: String) -> LuaResult<LuaFunction> {
+    let state = state.clone();
+
+    lua.create_function(move |_, args: LuaMultiValue| {
+        // Released before calling the stub, it can call other mocked functions
+        let stub = {
+            let mut state = state.borrow_mut();
+
+            state.calls.push(MockCall {
+                path: path.clone(),
+                args: args.iter().cloned().collect(),
+            });
+
+            match state.stubs.get(&path) {
+                Some(Stub::Returns(values)) => return Ok(values.clone()),
+                Some(Stub::Calls(stub)) => stub.clone(),
+                None => return Ok(LuaMultiValue::new()),
+            }
+        };
+
+        stub.call(args)
+    })
+}
@@ -0,0 +1,9 @@
+mod mock;
+
+#[cfg(feature = "headless")]
+mod headless;
+
+pub use mock::*;
+
+#[cfg(feature = "headless")]
+pub use headless::*;
@@ -1,13 +1,20 @@
 use crate::mlua::prelude::{Lua, LuaFunction, LuaResult};
-use macros::IntoTable;
+use crate::{NeoColor, NeoLuaExt};
+use macros::{FromLuaSerde, IntoLuaSerde};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 
-#[derive(Clone, Debug, Default, IntoTable)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, IntoLuaSerde, FromLuaSerde)]
+#[serde(default)]
 pub struct HLOpts {
     /// color name or "#RRGGBB"
+    #[serde(deserialize_with = "hl_color")]
     pub fg: Option<String>,
     /// color name or "#RRGGBB"
+    #[serde(deserialize_with = "hl_color")]
     pub bg: Option<String>,
     /// color name or "#RRGGBB"
+    #[serde(deserialize_with = "hl_color")]
     pub sp: Option<String>,
     /// Between 0 and 100
     pub blend: Option<u32>,
@@ -33,11 +40,58 @@ pub struct HLOpts {
     pub default: bool,
 
     pub ctermfg: Option<u32>,
-    pub ctermbg: Option<String>,
-    // pub cterm todo
+    pub ctermbg: Option<u32>,
+    /// When not set the cterm attributes match the gui attributes
+    pub cterm: Option<HLCterm>,
     pub force: bool,
 }
 
+/// cterm attribute map, like |highlight-args|
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HLCterm {
+    pub bold: bool,
+    pub standout: bool,
+    pub underline: bool,
+    pub undercurl: bool,
+    pub underdouble: bool,
+    pub underdotted: bool,
+    pub underdashed: bool,
+    pub strikethrough: bool,
+    pub italic: bool,
+    pub reverse: bool,
+    pub nocombine: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HLColor {
+    Rgb(u32),
+    Name(String),
+}
+
+/// nvim_get_hl returns the colors as integers, they are turned into "#rrggbb"
+fn hl_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let color = Option::<HLColor>::deserialize(deserializer)?;
+
+    Ok(color.map(|color| match color {
+        HLColor::Rgb(rgb) => NeoColor::from_u32(rgb).to_hex(),
+        HLColor::Name(name) => name,
+    }))
+}
+
+impl HLOpts {
+    /// Parsed foreground, `None` when unset or a color name
+    pub fn fg_color(&self) -> Option<NeoColor> {
+        self.fg.as_deref().and_then(NeoColor::from_hex)
+    }
+
+    /// Parsed background, `None` when unset or a color name
+    pub fn bg_color(&self) -> Option<NeoColor> {
+        self.bg.as_deref().and_then(NeoColor::from_hex)
+    }
+}
+
 pub struct NeoTheme;
 
 impl NeoTheme {
@@ -90,13 +144,55 @@ impl NeoTheme {
                    exists.
     */
     pub fn set_hl(lua: &Lua, ns_id: u32, group_name: &str, opts: HLOpts) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_set_hl").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_set_hl")?;
 
         lfn.call((ns_id, group_name, opts))
     }
 
+    /**
+    Gets a highlight definition by name.
+
+    Parameters: ~
+      • {ns_id}  Get highlight groups for namespace ns_id
+                 |nvim_get_namespaces()|. Use 0 to get global highlight groups
+                 |:highlight|.
+      • {name}   Highlight group name
+      • {link}   Show linked group name instead of effective definition
+
+    Return: ~
+        Highlight definition map as in |nvim_set_hl()|, the colors are
+        "#rrggbb". An undefined group gives the default `HLOpts`.
+    */
+    pub fn get_hl(lua: &Lua, ns_id: u32, name: &str, link: bool) -> LuaResult<HLOpts> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_hl")?;
+
+        let opts = lua.create_table()?;
+        opts.set("name", name)?;
+        opts.set("link", link)?;
+
+        lfn.call((ns_id, opts))
+    }
+
+    /**
+    Gets a highlight group by name
+
+    similar to |hlID()|, but allocates a new ID if not present.
+    */
+    pub fn get_hl_id_by_name(lua: &Lua, name: &str) -> LuaResult<u32> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_hl_id_by_name")?;
+
+        lfn.call(name)
+    }
+
+    /**
+    Set active namespace for highlights defined with |nvim_set_hl()|. This can
+    be set for a single window, see |nvim_win_set_hl_ns()|.
+
+    Parameters: ~
+      • {ns_id}  the namespace to use
+    */
     pub fn set_hl_ns(lua: &Lua, ns_id: u32) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_set_hl_ns").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_set_hl_ns")?;
 
         lfn.call(ns_id)
     }
@@ -118,8 +214,20 @@ impl NeoTheme {
         Namespace id
     */
     pub fn create_namespace(lua: &Lua, ns: &str) -> LuaResult<u32> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_create_namespace").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_create_namespace")?;
 
         lfn.call(ns)
     }
+
+    /**
+    Gets existing, non-anonymous |namespace|s.
+
+    Return: ~
+        dict that maps from names to namespace ids.
+    */
+    pub fn get_namespaces(lua: &Lua) -> LuaResult<HashMap<String, u32>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_namespaces")?;
+
+        lfn.call(())
+    }
 }
@@ -29,7 +29,9 @@ impl NeoUtils {
     }
 
     pub fn home_directory() -> PathBuf {
-        std::env::var_os("HOME").unwrap().into()
+        std::env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
     }
 
     fn strip_trailing_newline(input: &str) -> &str {
@@ -1,15 +1,38 @@
 #![allow(unused)]
 use crate::{
     neo_api::NeoApi,
-    neo_api_types::{OptValueType, WinCursor},
-    NeoBuffer,
+    neo_api_types::{OptValueType, TextHeight, TextHeightOpts, WinCursor},
+    NeoBuffer, NeoLuaExt, NeoTabpage, PopupSplit, WinConfig, WinOptions,
 };
 
-use mlua::prelude::{IntoLua, Lua, LuaFunction, LuaResult, LuaValue};
+use mlua::prelude::{IntoLua, Lua, LuaError, LuaFunction, LuaResult, LuaTable, LuaValue};
+use mlua::FromLua;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+/// Serialized as the id
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct NeoWindow(u32);
 
+impl FromLua for NeoWindow {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Integer(num) => Ok(NeoWindow::new(num as u32)),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: "LuaValue",
+                to: "NeoWindow",
+                message: Some("Not a number".to_string()),
+            }),
+        }
+    }
+}
+
+impl IntoLua for NeoWindow {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        Ok(LuaValue::Integer(self.0 as i64))
+    }
+}
+
 impl NeoWindow {
     pub const CURRENT: Self = Self(0);
 
@@ -22,19 +45,227 @@ impl NeoWindow {
     }
 
     pub fn get_current_win(lua: &Lua) -> LuaResult<NeoWindow> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_get_current_win").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_get_current_win")?;
 
         let buf_id = lfn.call(())?;
 
         Ok(NeoWindow::new(buf_id))
     }
 
+    /**
+    Gets the current list of window handles.
+
+    Return: ~
+        List of window handles
+    */
+    pub fn list_wins(lua: &Lua) -> LuaResult<Vec<NeoWindow>> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_list_wins")?;
+
+        lfn.call(())
+    }
+
+    /**
+    Opens a new split window next to this window.
+
+    Parameters: ~
+      • {buf}    Buffer to display
+      • {enter}  Enter the window (make it the current window)
+      • {split}  Split direction: "left", "right", "above", "below"
+
+    Return: ~
+        Window handle
+    */
+    pub fn open_split(
+        &self,
+        lua: &Lua,
+        buf: &NeoBuffer,
+        enter: bool,
+        split: PopupSplit,
+    ) -> LuaResult<NeoWindow> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_open_win")?;
+
+        let config = lua.create_table()?;
+        config.set("win", self.id())?;
+        config.set("split", split.to_string())?;
+
+        lfn.call((buf.id(), enter, config))
+    }
+
     pub fn set_buf(&self, lua: &Lua, buf: &NeoBuffer) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_win_set_buf").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_set_buf")?;
 
         lfn.call((self.id(), buf.id()))
     }
 
+    /**
+    Gets the current buffer in a window
+
+    Return: ~
+        Buffer handle
+    */
+    pub fn get_buf(&self, lua: &Lua) -> LuaResult<NeoBuffer> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_get_buf")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Gets window configuration.
+
+    The returned value may be given to |NeoWindow::set_config()| to restore the
+    configuration of a floating window. Splits have no `relative`.
+
+    Return: ~
+        Map defining the window configuration, see |nvim_open_win()|
+    */
+    pub fn get_config(&self, lua: &Lua) -> LuaResult<WinConfig> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_get_config")?;
+
+        let config: LuaTable = lfn.call(self.id())?;
+
+        if config
+            .get::<Option<String>>("relative")?
+            .is_some_and(|rel| rel.is_empty())
+        {
+            config.set("relative", LuaValue::Nil)?;
+        }
+
+        WinConfig::from_lua(LuaValue::Table(config), lua)
+    }
+
+    /**
+    Configures window layout. Cannot be used to move the last window in a
+    tabpage to a different one.
+
+    When reconfiguring a window, absent option keys will not be changed.
+    `row`/`col` and `relative` must be reconfigured together.
+
+    Parameters: ~
+      • {config}  Map defining the window configuration, see |nvim_open_win()|
+    */
+    pub fn set_config(&self, lua: &Lua, config: WinConfig) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_set_config")?;
+
+        lfn.call((self.id(), config))
+    }
+
+    /// Gets the window width in character cells
+    pub fn get_width(&self, lua: &Lua) -> LuaResult<u32> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_get_width")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Sets the window width. This will only succeed if the screen is split
+    vertically.
+
+    Parameters: ~
+      • {width}   Width as a count of columns
+    */
+    pub fn set_width(&self, lua: &Lua, width: u32) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_set_width")?;
+
+        lfn.call((self.id(), width))
+    }
+
+    /// Gets the window height as a count of rows
+    pub fn get_height(&self, lua: &Lua) -> LuaResult<u32> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_get_height")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Sets the window height.
+
+    Parameters: ~
+      • {height}  Height as a count of rows
+    */
+    pub fn set_height(&self, lua: &Lua, height: u32) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_set_height")?;
+
+        lfn.call((self.id(), height))
+    }
+
+    /**
+    Gets the window position in display cells. First position is zero.
+
+    Return: ~
+        (row, col) tuple with the window position
+    */
+    pub fn get_position(&self, lua: &Lua) -> LuaResult<(u32, u32)> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_get_position")?;
+
+        let position: LuaTable = lfn.call(self.id())?;
+
+        Ok((position.raw_get(1)?, position.raw_get(2)?))
+    }
+
+    /**
+    Gets the window tabpage
+
+    Return: ~
+        Tabpage that contains the window
+    */
+    pub fn get_tabpage(&self, lua: &Lua) -> LuaResult<NeoTabpage> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_get_tabpage")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Closes the window and hide the buffer it contains (like |:hide| with a
+    |window-ID|).
+
+    Like |:hide| the buffer becomes hidden unless another window is editing
+    it, or 'bufhidden' is `unload`, `delete` or `wipe` as opposed to |:close|
+    or |NeoWindow::close()|, which will close the buffer.
+
+    Attributes: ~
+        not allowed when |textlock| is active
+    */
+    pub fn hide(&self, lua: &Lua) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_hide")?;
+
+        lfn.call(self.id())
+    }
+
+    /**
+    Set highlight namespace for a window. This will use highlights defined
+    with |nvim_set_hl()| for this namespace, but fall back to global
+    highlights (ns=0) when missing.
+
+    This takes precedence over the 'winhighlight' option.
+
+    Parameters: ~
+      • {ns_id}  the namespace to use
+    */
+    pub fn set_hl_ns(&self, lua: &Lua, ns_id: u32) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_set_hl_ns")?;
+
+        lfn.call((self.id(), ns_id))
+    }
+
+    /**
+    Computes the number of screen lines occupied by a range of text in a given
+    window. Works for off-screen text and takes folds into account.
+
+    Diff filler or virtual lines above a line are counted as a part of that
+    line, unless the line is on "start_row" and "start_vcol" is specified.
+
+    Line indexing is similar to |nvim_buf_get_text()|.
+
+    Return: ~
+        The number of screen lines ("all") and the number of diff filler or
+        virtual lines among them ("fill")
+    */
+    pub fn text_height(&self, lua: &Lua, opts: TextHeightOpts) -> LuaResult<TextHeight> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_text_height")?;
+
+        lfn.call((self.id(), opts))
+    }
+
     /**
     Sets the value of an option. The behavior of this function matches that of
     |:set|: for global-local options, both the global and local value are set
@@ -53,6 +284,51 @@ impl NeoWindow {
         NeoApi::set_option_value(lua, key, value, OptValueType::Window(*self))
     }
 
+    /**
+    Gets a window-scoped (w:) variable
+
+    Parameters: ~
+      • {name}    Variable name
+
+    Return: ~
+        Variable value
+    */
+    pub fn get_var<V: FromLua>(&self, lua: &Lua, name: &str) -> LuaResult<V> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_get_var")?;
+
+        lfn.call((self.id(), name))
+    }
+
+    /**
+    Sets a window-scoped (w:) variable
+
+    Parameters: ~
+      • {name}    Variable name
+      • {value}   Variable value
+    */
+    pub fn set_var<V: IntoLua>(&self, lua: &Lua, name: &str, value: V) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_set_var")?;
+
+        lfn.call((self.id(), name, value))
+    }
+
+    /**
+    Removes a window-scoped (w:) variable
+
+    Parameters: ~
+      • {name}    Variable name
+    */
+    pub fn del_var(&self, lua: &Lua, name: &str) -> LuaResult<()> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_del_var")?;
+
+        lfn.call((self.id(), name))
+    }
+
+    /// Gets the window local value of an option, see |nvim_get_option_value()|
+    pub fn get_option_value<V: FromLua>(&self, lua: &Lua, key: &str) -> LuaResult<V> {
+        NeoApi::get_option_value(lua, key, OptValueType::Window(*self))
+    }
+
     /**
     Sets the (1,0)-indexed cursor position in the window. |api-indexing| This
     scrolls the window even if it is not the current one.
@@ -62,7 +338,7 @@ impl NeoWindow {
       • WinCursor
     */
     pub fn set_cursor(&self, lua: &Lua, cursor: WinCursor) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_win_set_cursor").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_set_cursor")?;
 
         lfn.call((self.id(), cursor))
     }
@@ -79,20 +355,20 @@ impl NeoWindow {
       • |getcurpos()|
     */
     pub fn get_cursor(&self, lua: &Lua) -> LuaResult<WinCursor> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_win_get_cursor").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_get_cursor")?;
 
         lfn.call(self.id())
     }
 
     /// Adds the namespace scope to the window.
     pub fn add_ns(&self, lua: &Lua, ns_id: u32) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_win_add_ns").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_add_ns")?;
 
         lfn.call((self.id(), ns_id))
     }
 
     pub fn call(&self, lua: &Lua, cb: LuaFunction) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_win_call").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_call")?;
 
         lfn.call((self.id(), cb))
     }
@@ -105,8 +381,125 @@ impl NeoWindow {
         not allowed when |textlock| is active
     */
     pub fn close(&self, lua: &Lua, force: bool) -> LuaResult<()> {
-        let lfn: LuaFunction = lua.load("vim.api.nvim_win_close").eval()?;
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_close")?;
 
         lfn.call((self.id(), force))
     }
+
+    /**
+    Checks if a window is valid
+
+    Parameters: ~
+      • {window}  Window handle, or 0 for current window
+
+    Return: ~
+        true if the window is valid, false otherwise
+    */
+    pub fn is_valid(&self, lua: &Lua) -> LuaResult<bool> {
+        let lfn: LuaFunction = lua.neo_fn("vim.api.nvim_win_is_valid")?;
+
+        lfn.call(self.id())
+    }
+}
+
+#[cfg(all(test, feature = "test-harness"))]
+mod tests {
+    use super::*;
+    use crate::{NeoMock, PopupBorder, PopupRelative};
+
+    const WIN: NeoWindow = NeoWindow(1000);
+
+    fn mock_config(mock: &NeoMock, config: &str) -> LuaResult<()> {
+        let config: LuaTable = mock.load(config).eval()?;
+
+        mock.returns("vim.api.nvim_win_get_config", config)
+    }
+
+    fn sent_config(mock: &NeoMock) -> LuaResult<LuaTable> {
+        mock.calls_to("vim.api.nvim_win_set_config")[0].arg(mock, 1)
+    }
+
+    #[test]
+    fn float_config_round_trips() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        mock_config(
+            &mock,
+            r#"{
+                relative = "editor", anchor = "NW", row = 3.5, col = 2, width = 10, height = 5,
+                focusable = true, external = false, zindex = 50, hide = false, mouse = true,
+                border = { "╭", "─", "╮", "│", "╯", "─", "╰", "│" },
+            }"#,
+        )?;
+
+        let config = WIN.get_config(&mock)?;
+
+        assert_eq!(config.relative, Some(PopupRelative::Editor));
+        assert_eq!(config.row, Some(3.5));
+        assert_eq!(config.col, Some(2.0));
+        assert_eq!(config.width, Some(10));
+        assert!(matches!(config.border, Some(PopupBorder::Custom(_))));
+
+        WIN.set_config(&mock, config)?;
+
+        let sent = sent_config(&mock)?;
+
+        assert_eq!(sent.get::<String>("relative")?, "editor");
+        assert_eq!(sent.get::<f64>("row")?, 3.5);
+        assert_eq!(sent.get::<f64>("col")?, 2.0);
+        assert_eq!(sent.get::<u32>("width")?, 10);
+        assert!(!sent.contains_key("noautocmd")?);
+        assert!(!sent.contains_key("title")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn split_config_stays_a_split() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+        mock_config(
+            &mock,
+            r#"{
+                relative = "", split = "left", win = 1001, width = 80, height = 40,
+                focusable = true, external = false, hide = false,
+            }"#,
+        )?;
+
+        let config = WIN.get_config(&mock)?;
+
+        assert_eq!(config.relative, None);
+        assert_eq!(config.split, Some(PopupSplit::Left));
+
+        WIN.set_config(&mock, config)?;
+
+        let sent = sent_config(&mock)?;
+
+        for key in ["relative", "row", "col", "anchor"] {
+            assert!(!sent.contains_key(key)?, "{key} is set");
+        }
+
+        assert_eq!(sent.get::<String>("split")?, "left");
+
+        Ok(())
+    }
+
+    #[test]
+    fn partial_update_sends_only_the_set_fields() -> LuaResult<()> {
+        let mock = NeoMock::new()?;
+
+        let config = WinConfig {
+            height: Some(20),
+            ..Default::default()
+        };
+
+        WIN.set_config(&mock, config)?;
+
+        let keys = sent_config(&mock)?
+            .pairs::<String, LuaValue>()
+            .map(|pair| pair.map(|(key, _)| key))
+            .collect::<LuaResult<Vec<_>>>()?;
+
+        assert_eq!(keys, ["height"]);
+
+        Ok(())
+    }
 }
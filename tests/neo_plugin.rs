@@ -0,0 +1,165 @@
+//! `#[neo_plugin]` on a plugin with setup, command and async functions, the module function is
+//! called directly because `luaopen_<name>` needs the `module` feature.
+//!
+//! `cargo test --no-default-features --features test-harness`
+
+use neo_api_rs::macros::neo_plugin;
+use neo_api_rs::mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable};
+use neo_api_rs::mlua::IntoLua;
+use neo_api_rs::{CmdOptsMods, ConfigReader, NeoApi, NeoConfig, NeoMock, NeoResult};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+struct GreeterConfig {
+    greeting: String,
+}
+
+impl Default for GreeterConfig {
+    fn default() -> Self {
+        Self {
+            greeting: "Hello".to_string(),
+        }
+    }
+}
+
+impl NeoConfig for GreeterConfig {
+    fn merge(&mut self, reader: &mut ConfigReader) {
+        reader.read("greeting", &mut self.greeting);
+    }
+}
+
+static GREETING: Mutex<String> = Mutex::new(String::new());
+
+static INDEXED: LazyLock<(Sender<String>, Mutex<Receiver<String>>)> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel();
+    (sender, Mutex::new(receiver))
+});
+
+struct Greeter;
+
+#[neo_plugin]
+impl Greeter {
+    #[neo(setup)]
+    pub fn setup(config: GreeterConfig) -> LuaResult<()> {
+        *GREETING.lock().unwrap() = config.greeting;
+
+        Ok(())
+    }
+
+    #[neo(command = "Greet", desc = "Greets someone", nargs = "?")]
+    pub fn greet(lua: &Lua, name: Option<String>) -> LuaResult<()> {
+        let greeting = GREETING.lock().unwrap().clone();
+        let name = name.unwrap_or_else(|| "world".to_string());
+
+        NeoApi::notify(lua, &format!("{greeting}, {name}"))
+    }
+
+    #[neo(name = "count")]
+    pub fn count_chars(text: String) -> LuaResult<usize> {
+        Ok(text.chars().count())
+    }
+
+    /// Runs on the tokio runtime
+    pub async fn index(path: String) -> NeoResult<()> {
+        INDEXED.0.send(path).unwrap();
+
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    fn helper() {}
+}
+
+/// The table a user command callback receives
+fn command_args(mock: &NeoMock, fargs: Vec<&str>) -> LuaResult<LuaTable> {
+    let args = mock.create_table()?;
+    args.set("name", "Greet")?;
+    args.set("args", fargs.join(" "))?;
+    args.set("fargs", fargs)?;
+    args.set("nargs", "?")?;
+    args.set("bang", false)?;
+    args.set("line1", 1)?;
+    args.set("line2", 1)?;
+    args.set("range", 0)?;
+    args.set("count", -1)?;
+    args.set("reg", "")?;
+    args.set("mods", "")?;
+    args.set("smods", CmdOptsMods::default().into_lua(mock)?)?;
+
+    Ok(args)
+}
+
+fn notified(mock: &NeoMock) -> LuaResult<Vec<String>> {
+    mock.calls_to("vim.notify")
+        .iter()
+        .map(|call| call.arg(mock, 0))
+        .collect()
+}
+
+#[test]
+fn pub_functions_are_exported() -> LuaResult<()> {
+    let mock = NeoMock::new()?;
+    let module = greeter(&mock)?;
+
+    for name in ["setup", "greet", "count", "index"] {
+        assert!(module.contains_key(name)?, "{name} is missing");
+    }
+
+    assert!(!module.contains_key("count_chars")?);
+    assert!(!module.contains_key("helper")?);
+
+    let count: LuaFunction = module.get("count")?;
+    assert_eq!(count.call::<usize>("héllo")?, 5);
+
+    Ok(())
+}
+
+#[test]
+fn setup_and_command() -> LuaResult<()> {
+    let mock = NeoMock::new()?;
+    let module = greeter(&mock)?;
+
+    let commands = mock.calls_to("vim.api.nvim_create_user_command");
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].arg::<String>(&mock, 0)?, "Greet");
+
+    let opts: LuaTable = commands[0].arg(&mock, 2)?;
+    assert_eq!(opts.get::<String>("desc")?, "Greets someone");
+    assert_eq!(opts.get::<String>("nargs")?, "?");
+
+    let callback: LuaFunction = commands[0].arg(&mock, 1)?;
+
+    let setup: LuaFunction = module.get("setup")?;
+    let opts: LuaTable = mock.load("{ greeting = 'Hi', greting = 'Hey' }").eval()?;
+    setup.call::<()>(opts)?;
+
+    // Unknown keys are reported with the module name
+    assert_eq!(
+        notified(&mock)?,
+        ["greeter: invalid setup options\n  greting: unknown key"]
+    );
+    mock.clear_calls();
+
+    callback.call::<()>(command_args(&mock, vec!["Bob"])?)?;
+    callback.call::<()>(command_args(&mock, vec![])?)?;
+
+    assert_eq!(notified(&mock)?, ["Hi, Bob", "Hi, world"]);
+
+    Ok(())
+}
+
+#[test]
+fn async_functions_run_on_the_runtime() -> LuaResult<()> {
+    let mock = NeoMock::new()?;
+    let module = greeter(&mock)?;
+
+    let index: LuaFunction = module.get("index")?;
+    index.call::<()>("/tmp/project")?;
+
+    let receiver = INDEXED.1.lock().unwrap();
+    let path = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(path, "/tmp/project");
+
+    Ok(())
+}